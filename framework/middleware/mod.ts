@@ -24,9 +24,11 @@ export {
   wasmMiddleware,
   wasmFunction,
   wasmHandler,
+  wasmRequestHandler,
   wasmLoader,
   getWASMContext,
   setWASMExecution,
   type WASMMiddlewareOptions,
   type WASMContextData,
+  type WASMRequestHandlerOptions,
 } from './wasm.ts';
@@ -43,6 +43,15 @@ export interface WASMMiddlewareOptions {
   /** Key to use when storing result in state */
   stateKey?: string;
 
+  /**
+   * Wrap each execution in an allocator scope (`echelon_begin_scope` /
+   * `echelon_end_scope`) so one long-lived instance does not grow between
+   * requests. Ignored for modules without those exports. Only use with
+   * functions returning plain values: memory allocated during the call is
+   * freed before the result is read back from linear memory.
+   */
+  requestScope?: boolean;
+
   /** Error handler */
   onError?: (error: Error, ctx: Context) => Response | Promise<Response>;
 }
@@ -91,6 +100,7 @@ export function wasmMiddleware(options: WASMMiddlewareOptions): Middleware {
     executionOptions = {},
     storeResultInState = true,
     stateKey = WASM_STATE_KEY,
+    requestScope = false,
     onError,
   } = options;
 
@@ -109,7 +119,9 @@ export function wasmMiddleware(options: WASMMiddlewareOptions): Middleware {
     const updatedCtx = ctx.state.get(stateKey) as WASMContextData;
     if (updatedCtx?.moduleId && updatedCtx?.functionName) {
       try {
-        const result = await runtime.execute(
+        const result = await executeInScope(
+          runtime,
+          requestScope,
           updatedCtx.moduleId,
           updatedCtx.functionName,
           updatedCtx.args ?? [],
@@ -142,6 +154,32 @@ export function wasmMiddleware(options: WASMMiddlewareOptions): Middleware {
   };
 }
 
+/**
+ * Execute a function, inside an allocator scope when requested and supported
+ */
+async function executeInScope(
+  runtime: WASMRuntimeCore,
+  scoped: boolean,
+  moduleId: string,
+  functionName: string,
+  args: unknown[],
+  executionOptions: WASMExecutionOptions
+) {
+  const useScope = scoped &&
+    runtime.hasFunction(moduleId, 'echelon_begin_scope') &&
+    runtime.hasFunction(moduleId, 'echelon_end_scope');
+  if (!useScope) {
+    return await runtime.execute(moduleId, functionName, args, executionOptions);
+  }
+
+  await runtime.execute(moduleId, 'echelon_begin_scope');
+  try {
+    return await runtime.execute(moduleId, functionName, args, executionOptions);
+  } finally {
+    await runtime.execute(moduleId, 'echelon_end_scope');
+  }
+}
+
 /**
  * Create middleware that executes a specific WASM function
  *
@@ -297,6 +335,218 @@ export function wasmHandler(options: {
   };
 }
 
+/**
+ * Options for `wasmRequestHandler`
+ */
+export interface WASMRequestHandlerOptions {
+  /** WASM Runtime instance */
+  runtime: WASMRuntimeCore;
+
+  /** Module implementing the request handler contract */
+  moduleId: string;
+
+  /** Export taking `(ptr, len)` of a serialized request (default: 'echelon_handle_request') */
+  functionName?: string;
+
+  /** Path prefix removed before the module routes the request, e.g. '/edge' */
+  stripPrefix?: string;
+
+  /** Run the call inside an allocator scope (see `WASMMiddlewareOptions.requestScope`) */
+  requestScope?: boolean;
+
+  /** Execution options for the handler call */
+  executionOptions?: WASMExecutionOptions;
+}
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+/**
+ * Create a route handler that passes the whole request to the module and
+ * returns the module's response
+ *
+ * The module receives the method, URL (path and query), headers and body,
+ * and returns status, headers and body, so whole endpoints can be written
+ * in Rust (see `src/http.rs` in echelon_wasm for the layout and router).
+ *
+ * @example
+ * ```typescript
+ * app.all('/edge/*', wasmRequestHandler({
+ *   runtime: wasmRuntime,
+ *   moduleId: 'string_utils',
+ *   stripPrefix: '/edge',
+ * }));
+ * // POST /edge/text/word_count "a b c" -> 200 {"result":3}
+ * ```
+ */
+export function wasmRequestHandler(options: WASMRequestHandlerOptions): (ctx: Context) => Promise<Response> {
+  const {
+    runtime,
+    moduleId,
+    functionName = 'echelon_handle_request',
+    stripPrefix,
+    requestScope = false,
+    executionOptions = {},
+  } = options;
+
+  const call = async (fn: string, args: unknown[] = [], opts?: WASMExecutionOptions) => {
+    const result = await runtime.execute(moduleId, fn, args, opts);
+    if (!result.success) {
+      throw result.error ?? new Error(`WASM call failed: ${fn}`);
+    }
+    return result.value;
+  };
+
+  return async (ctx: Context): Promise<Response> => {
+    const useScope = requestScope &&
+      runtime.hasFunction(moduleId, 'echelon_begin_scope') &&
+      runtime.hasFunction(moduleId, 'echelon_end_scope');
+
+    try {
+      let path = ctx.url.pathname;
+      if (stripPrefix && path.startsWith(stripPrefix)) {
+        path = path.slice(stripPrefix.length) || '/';
+      }
+      const request = encodeWASMRequest(
+        ctx.method,
+        path + ctx.url.search,
+        [...ctx.request.headers],
+        new Uint8Array(await ctx.request.arrayBuffer())
+      );
+
+      if (useScope) await call('echelon_begin_scope');
+      try {
+        const ptr = Number(await call('echelon_alloc', [request.length]));
+        if (ptr === 0) {
+          throw new Error('Cannot allocate WASM request buffer');
+        }
+        new Uint8Array(wasmMemory(runtime, moduleId).buffer, ptr, request.length).set(request);
+
+        const packed = await call(functionName, [ptr, request.length], executionOptions);
+        if (!useScope) await call('echelon_dealloc', [ptr]);
+
+        const code = Number(await call('echelon_last_error_code'));
+        if (code !== 0) {
+          const packedMessage = await call('echelon_last_error_message');
+          const message = decoder.decode(await takeBytes(runtime, moduleId, packedMessage, !useScope));
+          throw new Error(`${functionName} failed (code ${code}): ${message}`);
+        }
+
+        const response = decodeWASMResponse(await takeBytes(runtime, moduleId, packed, !useScope));
+        const empty = response.body.length === 0 || ctx.method === 'HEAD';
+        return new Response(empty ? null : response.body, {
+          status: response.status,
+          headers: response.headers,
+        });
+      } finally {
+        if (useScope) await call('echelon_end_scope');
+      }
+    } catch (error) {
+      logger.error('WASM request handler error', error as Error);
+      return new Response(JSON.stringify({
+        error: error instanceof Error ? error.message : String(error),
+        module: moduleId,
+        function: functionName,
+      }), {
+        status: 500,
+        headers: { 'Content-Type': 'application/json' },
+      });
+    }
+  };
+}
+
+function wasmMemory(runtime: WASMRuntimeCore, moduleId: string): WebAssembly.Memory {
+  const memory = runtime.getModule(moduleId)?.memory;
+  if (!memory) {
+    throw new Error(`Module ${moduleId} has no memory`);
+  }
+  return memory;
+}
+
+/**
+ * Copy out a packed `(ptr << 32) | len` result block, freeing it unless an
+ * allocator scope will
+ */
+async function takeBytes(
+  runtime: WASMRuntimeCore,
+  moduleId: string,
+  packed: unknown,
+  free: boolean
+): Promise<Uint8Array> {
+  const value = BigInt.asUintN(64, BigInt(packed as number | bigint));
+  const ptr = Number(value >> 32n);
+  const len = Number(value & 0xffffffffn);
+  if (ptr === 0) return new Uint8Array(0);
+
+  const bytes = new Uint8Array(wasmMemory(runtime, moduleId).buffer, ptr, len).slice();
+  if (free) await runtime.execute(moduleId, 'echelon_dealloc', [ptr]);
+  return bytes;
+}
+
+/**
+ * Serialize a request for `echelon_handle_request`:
+ * str(method) str(url) u32(count) (str(name) str(value))* bytes(body),
+ * every str / bytes prefixed with its u32 LE length
+ */
+export function encodeWASMRequest(
+  method: string,
+  url: string,
+  headers: Array<[string, string]>,
+  body: Uint8Array
+): Uint8Array {
+  const strings = [method, url, ...headers.flat()].map((s) => encoder.encode(s));
+  const total = strings.reduce((sum, s) => sum + 4 + s.length, 4 + 4 + body.length);
+  const out = new Uint8Array(total);
+  const view = new DataView(out.buffer);
+  let offset = 0;
+
+  const put = (bytes: Uint8Array) => {
+    view.setUint32(offset, bytes.length, true);
+    out.set(bytes, offset + 4);
+    offset += 4 + bytes.length;
+  };
+
+  put(strings[0]);
+  put(strings[1]);
+  view.setUint32(offset, headers.length, true);
+  offset += 4;
+  strings.slice(2).forEach(put);
+  put(body);
+  return out;
+}
+
+/**
+ * Parse a response from `echelon_handle_request`:
+ * u32(status) u32(count) (str(name) str(value))* bytes(body)
+ */
+export function decodeWASMResponse(
+  bytes: Uint8Array
+): { status: number; headers: Array<[string, string]>; body: Uint8Array } {
+  const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+  let offset = 0;
+
+  const u32 = () => {
+    const value = view.getUint32(offset, true);
+    offset += 4;
+    return value;
+  };
+  const take = () => {
+    const len = u32();
+    if (offset + len > bytes.length) {
+      throw new Error('Truncated WASM response');
+    }
+    offset += len;
+    return bytes.subarray(offset - len, offset);
+  };
+
+  const status = u32();
+  const headers: Array<[string, string]> = [];
+  for (let count = u32(); count > 0; count--) {
+    headers.push([decoder.decode(take()), decoder.decode(take())]);
+  }
+  return { status, headers, body: take() };
+}
+
 /**
  * Create middleware that loads a WASM module before processing
  *
@@ -159,8 +159,13 @@ export { AdminRouter as AdminPanel } from './admin/mod.ts';
 export {
   PluginManager,
   EventEmitter,
+  loadWASMPlugin,
+  readWASMPluginMetadata,
   type Plugin,
   type PluginContext,
+  type WASMPluginHost,
+  type WASMPluginMetadata,
+  type WASMPluginOptions,
 } from './plugin/mod.ts';
 
 // Layer 13: API
@@ -267,9 +272,11 @@ export {
   wasmMiddleware,
   wasmFunction,
   wasmHandler,
+  wasmRequestHandler,
   wasmLoader,
   getWASMContext,
   setWASMExecution,
   type WASMMiddlewareOptions,
   type WASMContextData,
+  type WASMRequestHandlerOptions,
 } from './middleware/mod.ts';
@@ -14,6 +14,16 @@
 
 export { PluginManager, type Plugin, type PluginContext } from './plugin.ts';
 export { EventEmitter, type EventHandler } from './events.ts';
+export {
+  ECHELON_PLUGIN_ABI,
+  ECHELON_PLUGIN_SECTION,
+  loadWASMPlugin,
+  readWASMPluginMetadata,
+  type WASMPluginHost,
+  type WASMPluginHostFunction,
+  type WASMPluginMetadata,
+  type WASMPluginOptions,
+} from './wasm_plugin.ts';
 
 // WASM Generator exports
 export {
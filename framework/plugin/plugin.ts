@@ -4,6 +4,8 @@
  * Manages plugins and extensions for Echelon.
  */
 
+import { loadWASMPlugin, type WASMPluginOptions } from './wasm_plugin.ts';
+
 export interface Plugin {
   name: string;
   version: string;
@@ -66,6 +68,18 @@ export class PluginManager {
     return this;
   }
 
+  /**
+   * Register a plugin built with the Rust `echelon_plugin_sdk`
+   */
+  async registerWASM(
+    source: string | URL | BufferSource | WebAssembly.Module,
+    options?: WASMPluginOptions,
+  ): Promise<Plugin> {
+    const plugin = await loadWASMPlugin(source, options);
+    this.register(plugin);
+    return plugin;
+  }
+
   /**
    * Install a plugin
    */
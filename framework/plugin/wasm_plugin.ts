@@ -0,0 +1,285 @@
+/**
+ * WASM Plugins
+ *
+ * Loads plugins written with the Rust `echelon_plugin_sdk` crate
+ * (`wasm_modules/rust_module/plugin_sdk`) as regular `Plugin`s:
+ *
+ * - Metadata comes from the `echelon.plugin` custom section, so a plugin can
+ *   be registered (and its dependencies resolved) without instantiating it.
+ * - `install` instantiates the module with the `echelon` host imports, runs
+ *   `echelon_plugin_init` and forwards subscribed events to
+ *   `echelon_plugin_event` as a JSON array of the emit arguments.
+ * - `uninstall` runs `echelon_plugin_destroy` and drops the instance.
+ *
+ * Strings cross the boundary as `(ptr << 32) | len` into plugin memory;
+ * strings returned to the plugin are written into blocks from its
+ * `echelon_alloc`, which the plugin frees.
+ *
+ * @example
+ * ```typescript
+ * const manager = new PluginManager({ on, emit });
+ * await manager.registerWASM('./hello_plugin.wasm', {
+ *   config: { 'hello.prefix': 'Hi' },
+ *   hostFunctions: {
+ *     greeting: (host, name) => host.writeString(`Hi, ${host.readString(name as bigint)}!`),
+ *   },
+ * });
+ * await manager.install('hello');
+ * ```
+ */
+
+import type { Plugin, PluginContext } from './plugin.ts';
+
+/** Custom section name of the plugin metadata */
+export const ECHELON_PLUGIN_SECTION = 'echelon.plugin';
+
+/** Plugin ABI version this loader implements */
+export const ECHELON_PLUGIN_ABI = 1;
+
+/**
+ * Contents of the `echelon.plugin` custom section
+ */
+export interface WASMPluginMetadata {
+  name: string;
+  version: string;
+  description?: string;
+  dependencies?: string[];
+  abi: number;
+}
+
+/**
+ * Access to plugin memory for custom host functions
+ */
+export interface WASMPluginHost {
+  /** Read a `&str` argument */
+  readString(packed: bigint): string;
+  /** Read a `&[u8]` argument */
+  readBytes(packed: bigint): Uint8Array;
+  /** Return a `String` / `Option<String>` result (`undefined` is `None`) */
+  writeString(value: string | undefined): bigint;
+  /** Return a `Vec<u8>` result */
+  writeBytes(value: Uint8Array | undefined): bigint;
+}
+
+/**
+ * Host function declared with `host_import!` in the plugin
+ *
+ * Receives the raw wasm arguments: numbers for scalars and packed `bigint`s
+ * for `&str` / `&[u8]`.
+ */
+export type WASMPluginHostFunction = (
+  host: WASMPluginHost,
+  ...args: Array<number | bigint>
+) => number | bigint | void;
+
+/**
+ * Options for `loadWASMPlugin`
+ */
+export interface WASMPluginOptions {
+  /** Values returned by `host::config` */
+  config?: Record<string, string>;
+  /** Extra `echelon` imports, keyed by `host_import!` name */
+  hostFunctions?: Record<string, WASMPluginHostFunction>;
+}
+
+const LOG_LEVELS = ['', 'error', 'warn', 'info', 'debug'];
+
+/**
+ * Read the `echelon.plugin` custom section
+ *
+ * Throws if the module has no section, the JSON is malformed, or the
+ * plugin targets a different ABI version.
+ */
+export function readWASMPluginMetadata(module: WebAssembly.Module): WASMPluginMetadata {
+  const sections = WebAssembly.Module.customSections(module, ECHELON_PLUGIN_SECTION);
+  if (sections.length === 0) {
+    throw new Error(`Not an Echelon plugin: missing ${ECHELON_PLUGIN_SECTION} section`);
+  }
+
+  const metadata = JSON.parse(new TextDecoder().decode(sections[0])) as WASMPluginMetadata;
+  if (typeof metadata.name !== 'string' || typeof metadata.version !== 'string') {
+    throw new Error(`${ECHELON_PLUGIN_SECTION} is missing name or version`);
+  }
+  if (metadata.abi !== ECHELON_PLUGIN_ABI) {
+    throw new Error(
+      `Plugin ${metadata.name} targets plugin ABI ${metadata.abi}, expected ${ECHELON_PLUGIN_ABI}`,
+    );
+  }
+  return metadata;
+}
+
+/**
+ * Compile a plugin module and wrap it as a `Plugin`
+ */
+export async function loadWASMPlugin(
+  source: string | URL | BufferSource | WebAssembly.Module,
+  options: WASMPluginOptions = {},
+): Promise<Plugin> {
+  const module = source instanceof WebAssembly.Module
+    ? source
+    : await WebAssembly.compile(
+      typeof source === 'string' || source instanceof URL ? await Deno.readFile(source) : source,
+    );
+  const metadata = readWASMPluginMetadata(module);
+  let instance: WASMPluginInstance | undefined;
+
+  return {
+    name: metadata.name,
+    version: metadata.version,
+    description: metadata.description,
+    dependencies: metadata.dependencies,
+
+    async install(context: PluginContext): Promise<void> {
+      const plugin = new WASMPluginInstance(metadata.name, context, options);
+      await plugin.instantiate(module);
+      plugin.run('echelon_plugin_init');
+      for (const event of plugin.subscriptions()) {
+        context.on(event, (...args) => {
+          if (instance === plugin) plugin.deliver(event, args);
+        });
+      }
+      instance = plugin;
+    },
+
+    uninstall(): void {
+      // `PluginContext.on` has no unsubscribe; handlers check the instance
+      const plugin = instance;
+      instance = undefined;
+      plugin?.run('echelon_plugin_destroy');
+    },
+  };
+}
+
+/**
+ * One instantiation of a plugin module
+ */
+class WASMPluginInstance implements WASMPluginHost {
+  private exports: Record<string, unknown> = {};
+  private readonly encoder = new TextEncoder();
+  private readonly decoder = new TextDecoder();
+
+  constructor(
+    private readonly name: string,
+    private readonly context: PluginContext,
+    private readonly options: WASMPluginOptions,
+  ) {}
+
+  async instantiate(module: WebAssembly.Module): Promise<void> {
+    const config = this.options.config ?? {};
+    const echelon: Record<string, (...args: never[]) => unknown> = {
+      log: (level: number, message: bigint) => {
+        const tag = LOG_LEVELS[level] ?? 'info';
+        this.context.log(`[${this.name}] ${tag}: ${this.readString(message)}`);
+      },
+      emit: (event: bigint, payload: bigint) => {
+        this.context.emit(this.readString(event), JSON.parse(this.readString(payload)));
+      },
+      config_get: (key: bigint) => {
+        const name = this.readString(key);
+        return this.writeString(Object.hasOwn(config, name) ? config[name] : undefined);
+      },
+    };
+    for (const [name, fn] of Object.entries(this.options.hostFunctions ?? {})) {
+      echelon[name] = (...args: Array<number | bigint>) => fn(this, ...args);
+    }
+
+    const imported = new Set(
+      WebAssembly.Module.imports(module)
+        .filter((entry) => entry.module === 'echelon')
+        .map((entry) => entry.name),
+    );
+    for (const name of imported) {
+      if (!(name in echelon)) {
+        throw new Error(`Plugin ${this.name} imports unknown host function: ${name}`);
+      }
+    }
+
+    const instance = await WebAssembly.instantiate(module, { echelon });
+    this.exports = instance.exports as Record<string, unknown>;
+  }
+
+  /** Run a lifecycle hook if the plugin declares it */
+  run(hook: 'echelon_plugin_init' | 'echelon_plugin_destroy'): void {
+    const fn = this.exports[hook];
+    if (typeof fn === 'function') {
+      this.check(hook, fn() as number);
+    }
+  }
+
+  subscriptions(): string[] {
+    const fn = this.exports.echelon_plugin_subscriptions;
+    if (typeof fn !== 'function') return [];
+    return JSON.parse(this.decoder.decode(this.take(fn() as bigint))) as string[];
+  }
+
+  deliver(event: string, args: unknown[]): void {
+    const name = this.encoder.encode(event);
+    const payload = this.encoder.encode(JSON.stringify(args));
+    const namePtr = this.alloc(name);
+    const payloadPtr = this.alloc(payload);
+    try {
+      const status = this.fn('echelon_plugin_event')(namePtr, name.length, payloadPtr, payload.length);
+      this.check(`event ${event}`, status as number);
+    } finally {
+      this.fn('echelon_dealloc')(namePtr);
+      this.fn('echelon_dealloc')(payloadPtr);
+    }
+  }
+
+  readString(packed: bigint): string {
+    return this.decoder.decode(this.readBytes(packed));
+  }
+
+  readBytes(packed: bigint): Uint8Array {
+    const value = BigInt.asUintN(64, packed);
+    const ptr = Number(value >> 32n);
+    const len = Number(value & 0xffffffffn);
+    return ptr === 0 ? new Uint8Array(0) : new Uint8Array(this.memory.buffer, ptr, len).slice();
+  }
+
+  writeString(value: string | undefined): bigint {
+    return this.writeBytes(value === undefined ? undefined : this.encoder.encode(value));
+  }
+
+  writeBytes(value: Uint8Array | undefined): bigint {
+    if (value === undefined) return 0n;
+    const ptr = this.alloc(value);
+    return (BigInt(ptr) << 32n) | BigInt(value.length);
+  }
+
+  private check(what: string, status: number): void {
+    if (status === 0) return;
+    const message = this.decoder.decode(this.take(this.fn('echelon_plugin_error')() as bigint));
+    throw new Error(`Plugin ${this.name} ${what} failed: ${message || 'unknown error'}`);
+  }
+
+  /** Copy bytes into a fresh block in plugin memory */
+  private alloc(bytes: Uint8Array): number {
+    const ptr = this.fn('echelon_alloc')(bytes.length) as number;
+    if (ptr === 0) {
+      throw new Error(`Plugin ${this.name} is out of memory`);
+    }
+    new Uint8Array(this.memory.buffer, ptr, bytes.length).set(bytes);
+    return ptr;
+  }
+
+  /** Copy out and free a packed block returned by the plugin */
+  private take(packed: bigint): Uint8Array {
+    const bytes = this.readBytes(packed);
+    const ptr = Number(BigInt.asUintN(64, packed) >> 32n);
+    if (ptr !== 0) this.fn('echelon_dealloc')(ptr);
+    return bytes;
+  }
+
+  private get memory(): WebAssembly.Memory {
+    return this.exports.memory as WebAssembly.Memory;
+  }
+
+  private fn(name: string): (...args: unknown[]) => unknown {
+    const fn = this.exports[name];
+    if (typeof fn !== 'function') {
+      throw new Error(`Plugin ${this.name} does not export ${name}`);
+    }
+    return fn as (...args: unknown[]) => unknown;
+  }
+}
@@ -140,9 +140,24 @@ export {
   type WASMLoaderOptions,
 } from './wasm_module_loader.ts';
 
+export {
+  loadWASMComponent,
+  parseWIT,
+  renderWITDeclarations,
+  type WASMComponent,
+  type WASMComponentFunction,
+  type WASMComponentOptions,
+  type WITFunction,
+  type WITInterface,
+  type WITPackage,
+  type WITType,
+  type WITWorld,
+} from './wasm_component_loader.ts';
+
 export {
   WASMExecutor,
   TimeoutError,
+  BudgetExhaustedError,
   type ImportConfig,
 } from './wasm_executor.ts';
 
@@ -150,6 +165,14 @@ export { WASMMemoryManager } from './wasm_memory.ts';
 
 export { WASMSandboxManager } from './wasm_sandbox.ts';
 
+export {
+  WASMThreadPool,
+  EchelonBatchOp,
+  memoryImportLimits,
+  type EchelonBatchResult,
+  type WASMThreadPoolOptions,
+} from './wasm_thread_pool.ts';
+
 // WASM Types
 export type {
   WASMValueType,
@@ -168,6 +191,7 @@ export type {
   WASMMemoryConfig,
   WASMMemoryStats,
   WASMModuleMemoryStats,
+  WASMHeapStats,
   WASMCapability,
   WASMCPULimit,
   WASMSandbox,
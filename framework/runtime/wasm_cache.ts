@@ -3,6 +3,10 @@
  *
  * Provides caching for compiled WASM modules with optional persistence
  * to filesystem or KV storage. Reduces compilation overhead.
+ *
+ * Also stores instance state snapshots (`echelon_snapshot` /
+ * `echelon_restore` in the Rust module), so state survives the runtime
+ * recycling an instance.
  */
 
 import type { WASMModule } from './wasm_types.ts';
@@ -57,6 +61,7 @@ export interface CacheStats {
  */
 export class WASMModuleCache {
   private cache: Map<string, CacheEntry> = new Map();
+  private snapshots: Map<string, Uint8Array> = new Map();
   private options: Required<Omit<WASMCacheOptions, 'cacheDir' | 'kvKey'>> & {
     cacheDir?: string;
     kvKey?: string;
@@ -267,6 +272,78 @@ export class WASMModuleCache {
     logger.debug('Cache cleared');
   }
 
+  /**
+   * Store an instance state snapshot under `key`
+   *
+   * Snapshots are kept apart from modules and survive `clear()`; they are
+   * persisted as `<key>.snapshot` files or under the `<kvKey>_snapshot` KV
+   * prefix.
+   */
+  async saveSnapshot(key: string, bytes: Uint8Array): Promise<void> {
+    readWASMSnapshotInfo(bytes);
+    this.snapshots.set(key, bytes);
+
+    if (this.options.persistToFs && this.options.cacheDir) {
+      try {
+        await Deno.writeFile(`${this.options.cacheDir}/${key}.snapshot`, bytes);
+      } catch (error) {
+        logger.error(`Failed to persist snapshot to filesystem: ${key}`, error as Error);
+      }
+    }
+
+    if (this.options.persistToKV && this.kv) {
+      try {
+        await this.kv.set([`${this.options.kvKey}_snapshot`, key], bytes);
+      } catch (error) {
+        logger.error(`Failed to persist snapshot to KV: ${key}`, error as Error);
+      }
+    }
+
+    logger.debug(`Saved WASM snapshot: ${key} (${bytes.length} bytes)`);
+  }
+
+  /**
+   * Get the snapshot stored under `key`, from memory or persistent storage
+   */
+  async loadSnapshot(key: string): Promise<Uint8Array | null> {
+    const cached = this.snapshots.get(key);
+    if (cached) return cached;
+
+    let bytes: Uint8Array | null = null;
+    if (this.options.persistToFs && this.options.cacheDir) {
+      try {
+        bytes = await Deno.readFile(`${this.options.cacheDir}/${key}.snapshot`);
+      } catch {
+        bytes = null;
+      }
+    } else if (this.options.persistToKV && this.kv) {
+      const result = await this.kv.get<Uint8Array>([`${this.options.kvKey}_snapshot`, key]);
+      bytes = result.value;
+    }
+
+    if (bytes) this.snapshots.set(key, bytes);
+    return bytes;
+  }
+
+  /**
+   * Remove the snapshot stored under `key`
+   */
+  async deleteSnapshot(key: string): Promise<void> {
+    this.snapshots.delete(key);
+
+    if (this.options.persistToFs && this.options.cacheDir) {
+      try {
+        await Deno.remove(`${this.options.cacheDir}/${key}.snapshot`);
+      } catch {
+        // Ignore errors
+      }
+    }
+
+    if (this.options.persistToKV && this.kv) {
+      await this.kv.delete([`${this.options.kvKey}_snapshot`, key]);
+    }
+  }
+
   /**
    * Get cache statistics
    */
@@ -541,3 +618,124 @@ export async function warmCache(
 
   logger.info('Cache warming complete');
 }
+
+/** Leading bytes of an `echelon_snapshot` snapshot */
+export const WASM_SNAPSHOT_MAGIC = 'ECHS';
+
+/** Newest snapshot format version this host understands */
+export const WASM_SNAPSHOT_VERSION = 1;
+
+/**
+ * Header of a validated snapshot
+ */
+export interface WASMSnapshotInfo {
+  /** Format version */
+  version: number;
+  /** Section tags, in order */
+  sections: number[];
+  /** Total size in bytes */
+  size: number;
+}
+
+/**
+ * Utility: CRC-32 (IEEE) as used by the snapshot trailer
+ */
+export function crc32(bytes: Uint8Array): number {
+  let crc = 0xffffffff;
+  for (const byte of bytes) {
+    crc ^= byte;
+    for (let bit = 0; bit < 8; bit++) {
+      crc = (crc >>> 1) ^ (0xedb88320 & -(crc & 1));
+    }
+  }
+  return (crc ^ 0xffffffff) >>> 0;
+}
+
+/**
+ * Utility: Validate a snapshot's magic, checksum and framing
+ *
+ * Throws on a corrupted snapshot. Newer format versions are reported, not
+ * rejected; the module decides whether it can restore them.
+ */
+export function readWASMSnapshotInfo(bytes: Uint8Array): WASMSnapshotInfo {
+  const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+  if (bytes.length < 12 || new TextDecoder().decode(bytes.subarray(0, 4)) !== WASM_SNAPSHOT_MAGIC) {
+    throw new Error('Not an echelon_wasm snapshot');
+  }
+  if (crc32(bytes.subarray(0, bytes.length - 4)) !== view.getUint32(bytes.length - 4, true)) {
+    throw new Error('Snapshot checksum mismatch');
+  }
+
+  const sections: number[] = [];
+  let offset = 8;
+  for (let i = 0; i < view.getUint16(6, true); i++) {
+    if (offset + 6 > bytes.length - 4) throw new Error('Truncated snapshot');
+    sections.push(view.getUint16(offset, true));
+    offset += 6 + view.getUint32(offset + 2, true);
+  }
+  if (offset !== bytes.length - 4) throw new Error('Malformed snapshot sections');
+
+  return { version: view.getUint16(4, true), sections, size: bytes.length };
+}
+
+/**
+ * Utility: Take a state snapshot of an `echelon_wasm` instance
+ */
+export function snapshotWASMInstance(instance: WebAssembly.Instance): Uint8Array {
+  const exports = snapshotExports(instance);
+  const packed = BigInt.asUintN(64, exports.echelon_snapshot());
+  const ptr = Number(packed >> 32n);
+  const len = Number(packed & 0xffffffffn);
+  if (ptr === 0) throw new Error('Snapshot failed: out of memory');
+
+  const bytes = new Uint8Array(exports.memory.buffer, ptr, len).slice();
+  exports.echelon_dealloc(ptr);
+  return bytes;
+}
+
+/**
+ * Utility: Restore a snapshot into an `echelon_wasm` instance
+ *
+ * Throws with the module's error message if the snapshot is rejected; the
+ * instance state is then unchanged.
+ */
+export function restoreWASMInstance(instance: WebAssembly.Instance, bytes: Uint8Array): void {
+  const exports = snapshotExports(instance);
+  const ptr = exports.echelon_alloc(bytes.length);
+  if (ptr === 0) throw new Error('Restore failed: out of memory');
+
+  new Uint8Array(exports.memory.buffer, ptr, bytes.length).set(bytes);
+  try {
+    if (exports.echelon_restore(ptr, bytes.length) === 1) return;
+  } finally {
+    exports.echelon_dealloc(ptr);
+  }
+
+  const packed = BigInt.asUintN(64, exports.echelon_last_error_message());
+  const messagePtr = Number(packed >> 32n);
+  const message = new TextDecoder().decode(
+    new Uint8Array(exports.memory.buffer, messagePtr, Number(packed & 0xffffffffn)),
+  );
+  if (messagePtr !== 0) exports.echelon_dealloc(messagePtr);
+  throw new Error(`Restore failed: ${message}`);
+}
+
+/**
+ * Raw snapshot exports of an `echelon_wasm` instance
+ */
+interface WASMSnapshotExports {
+  memory: WebAssembly.Memory;
+  echelon_alloc(len: number): number;
+  echelon_dealloc(ptr: number): void;
+  echelon_snapshot(): bigint;
+  echelon_restore(ptr: number, len: number): number;
+  echelon_last_error_message(): bigint;
+}
+
+function snapshotExports(instance: WebAssembly.Instance): WASMSnapshotExports {
+  const exports = instance.exports as unknown as WASMSnapshotExports;
+  if (typeof exports.echelon_snapshot !== 'function' || typeof exports.echelon_restore !== 'function') {
+    throw new Error('Module was built without the snapshot feature');
+  }
+  return exports;
+}
@@ -0,0 +1,510 @@
+/**
+ * CBOR / MessagePack Codecs for Structured WASM Calls
+ *
+ * Encodes the argument maps taken by `call_encoded` / `echelon_call_encoded`
+ * in the Rust module (`src/codec.rs`) and decodes its results. Values map as:
+ *
+ * - `null` / `undefined` -> null (nil); `undefined` object fields are omitted
+ *   so the Rust side applies the field's default
+ * - booleans and strings
+ * - integers in the safe range and `bigint` -> integers, other numbers -> float64
+ * - `Uint8Array` -> byte string (bin)
+ * - arrays -> arrays, plain objects -> maps with string keys
+ *
+ * Decoded integers outside the safe range are returned as `bigint`.
+ * Indefinite-length CBOR items and MessagePack extension types are rejected.
+ */
+
+/**
+ * Wire format of a structured call
+ */
+export type WASMEncoding = 'cbor' | 'msgpack';
+
+/** `Encoding` values of the Rust module */
+export const WASM_ENCODING_IDS: Record<WASMEncoding, number> = {
+  cbor: 0,
+  msgpack: 1,
+};
+
+/**
+ * A decoded CBOR / MessagePack value
+ */
+export type EncodedValue =
+  | null
+  | boolean
+  | number
+  | bigint
+  | string
+  | Uint8Array
+  | EncodedValue[]
+  | { [key: string]: EncodedValue };
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder('utf-8', { fatal: true });
+
+/**
+ * Growable output buffer (big-endian, as both formats use)
+ */
+class ByteWriter {
+  private buf = new Uint8Array(64);
+  private view = new DataView(this.buf.buffer);
+  private length = 0;
+
+  /** Offset of `n` more bytes; may replace `buf` and `view`, so call it first */
+  private reserve(n: number): number {
+    if (this.length + n > this.buf.length) {
+      const next = new Uint8Array(Math.max(this.buf.length * 2, this.length + n));
+      next.set(this.buf.subarray(0, this.length));
+      this.buf = next;
+      this.view = new DataView(next.buffer);
+    }
+    const offset = this.length;
+    this.length += n;
+    return offset;
+  }
+
+  u8(value: number): void {
+    const offset = this.reserve(1);
+    this.buf[offset] = value;
+  }
+
+  u16(value: number): void {
+    const offset = this.reserve(2);
+    this.view.setUint16(offset, value);
+  }
+
+  u32(value: number): void {
+    const offset = this.reserve(4);
+    this.view.setUint32(offset, value);
+  }
+
+  u64(value: bigint): void {
+    const offset = this.reserve(8);
+    this.view.setBigUint64(offset, value);
+  }
+
+  i64(value: bigint): void {
+    const offset = this.reserve(8);
+    this.view.setBigInt64(offset, value);
+  }
+
+  f64(value: number): void {
+    const offset = this.reserve(8);
+    this.view.setFloat64(offset, value);
+  }
+
+  bytes(bytes: Uint8Array): void {
+    const offset = this.reserve(bytes.length);
+    this.buf.set(bytes, offset);
+  }
+
+  finish(): Uint8Array {
+    return this.buf.slice(0, this.length);
+  }
+}
+
+/**
+ * Cursor over an input buffer
+ */
+class ByteReader {
+  private offset = 0;
+  private readonly view: DataView;
+
+  constructor(private readonly buf: Uint8Array, private readonly format: string) {
+    this.view = new DataView(buf.buffer, buf.byteOffset, buf.byteLength);
+  }
+
+  private take(n: number): number {
+    if (this.offset + n > this.buf.length) {
+      throw new Error(`${this.format}: unexpected end of input`);
+    }
+    const offset = this.offset;
+    this.offset += n;
+    return offset;
+  }
+
+  u8(): number {
+    return this.buf[this.take(1)];
+  }
+
+  u16(): number {
+    return this.view.getUint16(this.take(2));
+  }
+
+  u32(): number {
+    return this.view.getUint32(this.take(4));
+  }
+
+  u64(): bigint {
+    return this.view.getBigUint64(this.take(8));
+  }
+
+  i64(): bigint {
+    return this.view.getBigInt64(this.take(8));
+  }
+
+  f16(): number {
+    const half = this.u16();
+    const exponent = (half >> 10) & 0x1f;
+    const fraction = half & 0x3ff;
+    const sign = half & 0x8000 ? -1 : 1;
+    if (exponent === 0) return sign * fraction * 2 ** -24;
+    if (exponent === 0x1f) return fraction ? NaN : sign * Infinity;
+    return sign * (1 + fraction / 1024) * 2 ** (exponent - 15);
+  }
+
+  f32(): number {
+    return this.view.getFloat32(this.take(4));
+  }
+
+  f64(): number {
+    return this.view.getFloat64(this.take(8));
+  }
+
+  bytes(n: number): Uint8Array {
+    const offset = this.take(n);
+    return this.buf.slice(offset, offset + n);
+  }
+
+  string(n: number): string {
+    const offset = this.take(n);
+    return decoder.decode(this.buf.subarray(offset, offset + n));
+  }
+
+  error(message: string): Error {
+    return new Error(`${this.format}: ${message} at byte ${this.offset - 1}`);
+  }
+
+  finish(): void {
+    if (this.offset !== this.buf.length) {
+      throw new Error(`${this.format}: ${this.buf.length - this.offset} trailing bytes`);
+    }
+  }
+}
+
+/** Integer as `number` when it is safe, `bigint` otherwise */
+function integer(value: bigint): number | bigint {
+  return value >= BigInt(Number.MIN_SAFE_INTEGER) && value <= BigInt(Number.MAX_SAFE_INTEGER)
+    ? Number(value)
+    : value;
+}
+
+function objectEntries(value: object): Array<[string, unknown]> {
+  return Object.entries(value).filter(([, field]) => field !== undefined);
+}
+
+// ============================================================================
+// CBOR (RFC 8949)
+// ============================================================================
+
+function cborHead(out: ByteWriter, major: number, n: number | bigint): void {
+  const type = major << 5;
+  if (typeof n === 'bigint' || n > 0xffffffff) {
+    out.u8(type | 27);
+    out.u64(BigInt(n));
+  } else if (n < 24) {
+    out.u8(type | n);
+  } else if (n <= 0xff) {
+    out.u8(type | 24);
+    out.u8(n);
+  } else if (n <= 0xffff) {
+    out.u8(type | 25);
+    out.u16(n);
+  } else {
+    out.u8(type | 26);
+    out.u32(n);
+  }
+}
+
+function cborEncode(out: ByteWriter, value: unknown): void {
+  if (value === null || value === undefined) {
+    out.u8(0xf6);
+  } else if (typeof value === 'boolean') {
+    out.u8(value ? 0xf5 : 0xf4);
+  } else if (typeof value === 'bigint') {
+    if (value < -(2n ** 64n) || value >= 2n ** 64n) {
+      throw new RangeError(`CBOR: integer ${value} out of range`);
+    }
+    if (value >= 0n) cborHead(out, 0, value);
+    else cborHead(out, 1, -1n - value);
+  } else if (typeof value === 'number') {
+    if (Number.isSafeInteger(value)) {
+      if (value >= 0) cborHead(out, 0, value);
+      else cborHead(out, 1, -1 - value);
+    } else {
+      out.u8(0xfb);
+      out.f64(value);
+    }
+  } else if (typeof value === 'string') {
+    const bytes = encoder.encode(value);
+    cborHead(out, 3, bytes.length);
+    out.bytes(bytes);
+  } else if (value instanceof Uint8Array) {
+    cborHead(out, 2, value.length);
+    out.bytes(value);
+  } else if (Array.isArray(value)) {
+    cborHead(out, 4, value.length);
+    for (const item of value) cborEncode(out, item);
+  } else if (typeof value === 'object') {
+    const entries = objectEntries(value);
+    cborHead(out, 5, entries.length);
+    for (const [key, field] of entries) {
+      cborEncode(out, key);
+      cborEncode(out, field);
+    }
+  } else {
+    throw new TypeError(`CBOR: cannot encode ${typeof value}`);
+  }
+}
+
+function cborLength(input: ByteReader, info: number): bigint {
+  if (info < 24) return BigInt(info);
+  switch (info) {
+    case 24: return BigInt(input.u8());
+    case 25: return BigInt(input.u16());
+    case 26: return BigInt(input.u32());
+    case 27: return input.u64();
+    case 31: throw input.error('indefinite-length items are not supported');
+    default: throw input.error(`invalid additional information ${info}`);
+  }
+}
+
+function cborDecode(input: ByteReader): EncodedValue {
+  const initial = input.u8();
+  const major = initial >> 5;
+  const info = initial & 0x1f;
+
+  if (major === 7) {
+    switch (info) {
+      case 20: return false;
+      case 21: return true;
+      case 22:
+      case 23: return null;
+      case 25: return input.f16();
+      case 26: return input.f32();
+      case 27: return input.f64();
+      default: throw input.error(`unsupported simple value ${info}`);
+    }
+  }
+
+  const n = cborLength(input, info);
+  switch (major) {
+    case 0: return integer(n);
+    case 1: return integer(-1n - n);
+    case 2: return input.bytes(Number(n));
+    case 3: return input.string(Number(n));
+    case 4: return Array.from({ length: Number(n) }, () => cborDecode(input));
+    case 5: {
+      const map: { [key: string]: EncodedValue } = {};
+      for (let i = 0n; i < n; i++) {
+        const key = cborDecode(input);
+        map[typeof key === 'string' ? key : String(key)] = cborDecode(input);
+      }
+      return map;
+    }
+    // Tags (dates, bignums, ...) decode to their content
+    default: return cborDecode(input);
+  }
+}
+
+/**
+ * Encode a value as CBOR
+ */
+export function encodeCBOR(value: unknown): Uint8Array {
+  const out = new ByteWriter();
+  cborEncode(out, value);
+  return out.finish();
+}
+
+/**
+ * Decode one CBOR item (the whole buffer)
+ */
+export function decodeCBOR(bytes: Uint8Array): EncodedValue {
+  const input = new ByteReader(bytes, 'CBOR');
+  const value = cborDecode(input);
+  input.finish();
+  return value;
+}
+
+// ============================================================================
+// MessagePack
+// ============================================================================
+
+/** Length header: fixed form below `fixLimit`, then 8/16/32-bit forms (`0` = no 8-bit form) */
+function msgpackHead(
+  out: ByteWriter,
+  n: number,
+  fix: [code: number, limit: number] | null,
+  codes: [number, number, number],
+): void {
+  if (fix !== null && n < fix[1]) {
+    out.u8(fix[0] | n);
+  } else if (codes[0] !== 0 && n <= 0xff) {
+    out.u8(codes[0]);
+    out.u8(n);
+  } else if (n <= 0xffff) {
+    out.u8(codes[1]);
+    out.u16(n);
+  } else {
+    out.u8(codes[2]);
+    out.u32(n);
+  }
+}
+
+function msgpackInteger(out: ByteWriter, value: bigint): void {
+  if (value >= 0n && value < 128n) {
+    out.u8(Number(value));
+  } else if (value < 0n && value >= -32n) {
+    out.u8(Number(value) & 0xff);
+  } else if (value >= 0n && value <= 0xffffffffn) {
+    const n = Number(value);
+    if (n <= 0xff) {
+      out.u8(0xcc);
+      out.u8(n);
+    } else if (n <= 0xffff) {
+      out.u8(0xcd);
+      out.u16(n);
+    } else {
+      out.u8(0xce);
+      out.u32(n);
+    }
+  } else if (value < 0n && value >= -(2n ** 31n)) {
+    const n = Number(value);
+    if (n >= -128) {
+      out.u8(0xd0);
+      out.u8(n & 0xff);
+    } else if (n >= -32768) {
+      out.u8(0xd1);
+      out.u16(n & 0xffff);
+    } else {
+      out.u8(0xd2);
+      out.u32(n >>> 0);
+    }
+  } else if (value > 0n && value < 2n ** 64n) {
+    out.u8(0xcf);
+    out.u64(value);
+  } else if (value < 0n && value >= -(2n ** 63n)) {
+    out.u8(0xd3);
+    out.i64(value);
+  } else {
+    throw new RangeError(`MessagePack: integer ${value} out of range`);
+  }
+}
+
+function msgpackEncode(out: ByteWriter, value: unknown): void {
+  if (value === null || value === undefined) {
+    out.u8(0xc0);
+  } else if (typeof value === 'boolean') {
+    out.u8(value ? 0xc3 : 0xc2);
+  } else if (typeof value === 'bigint') {
+    msgpackInteger(out, value);
+  } else if (typeof value === 'number') {
+    if (Number.isSafeInteger(value)) {
+      msgpackInteger(out, BigInt(value));
+    } else {
+      out.u8(0xcb);
+      out.f64(value);
+    }
+  } else if (typeof value === 'string') {
+    const bytes = encoder.encode(value);
+    msgpackHead(out, bytes.length, [0xa0, 32], [0xd9, 0xda, 0xdb]);
+    out.bytes(bytes);
+  } else if (value instanceof Uint8Array) {
+    msgpackHead(out, value.length, null, [0xc4, 0xc5, 0xc6]);
+    out.bytes(value);
+  } else if (Array.isArray(value)) {
+    msgpackHead(out, value.length, [0x90, 16], [0, 0xdc, 0xdd]);
+    for (const item of value) msgpackEncode(out, item);
+  } else if (typeof value === 'object') {
+    const entries = objectEntries(value);
+    msgpackHead(out, entries.length, [0x80, 16], [0, 0xde, 0xdf]);
+    for (const [key, field] of entries) {
+      msgpackEncode(out, key);
+      msgpackEncode(out, field);
+    }
+  } else {
+    throw new TypeError(`MessagePack: cannot encode ${typeof value}`);
+  }
+}
+
+function msgpackArray(input: ByteReader, n: number): EncodedValue[] {
+  return Array.from({ length: n }, () => msgpackDecode(input));
+}
+
+function msgpackMap(input: ByteReader, n: number): { [key: string]: EncodedValue } {
+  const map: { [key: string]: EncodedValue } = {};
+  for (let i = 0; i < n; i++) {
+    const key = msgpackDecode(input);
+    map[typeof key === 'string' ? key : String(key)] = msgpackDecode(input);
+  }
+  return map;
+}
+
+function msgpackDecode(input: ByteReader): EncodedValue {
+  const code = input.u8();
+  if (code < 0x80) return code;
+  if (code >= 0xe0) return code - 0x100;
+  if (code < 0x90) return msgpackMap(input, code & 0x0f);
+  if (code < 0xa0) return msgpackArray(input, code & 0x0f);
+  if (code < 0xc0) return input.string(code & 0x1f);
+
+  switch (code) {
+    case 0xc0: return null;
+    case 0xc2: return false;
+    case 0xc3: return true;
+    case 0xc4: return input.bytes(input.u8());
+    case 0xc5: return input.bytes(input.u16());
+    case 0xc6: return input.bytes(input.u32());
+    case 0xca: return input.f32();
+    case 0xcb: return input.f64();
+    case 0xcc: return input.u8();
+    case 0xcd: return input.u16();
+    case 0xce: return input.u32();
+    case 0xcf: return integer(input.u64());
+    case 0xd0: return (input.u8() << 24) >> 24;
+    case 0xd1: return (input.u16() << 16) >> 16;
+    case 0xd2: return input.u32() | 0;
+    case 0xd3: return integer(input.i64());
+    case 0xd9: return input.string(input.u8());
+    case 0xda: return input.string(input.u16());
+    case 0xdb: return input.string(input.u32());
+    case 0xdc: return msgpackArray(input, input.u16());
+    case 0xdd: return msgpackArray(input, input.u32());
+    case 0xde: return msgpackMap(input, input.u16());
+    case 0xdf: return msgpackMap(input, input.u32());
+    default: throw input.error(`unsupported type 0x${code.toString(16)}`);
+  }
+}
+
+/**
+ * Encode a value as MessagePack
+ */
+export function encodeMessagePack(value: unknown): Uint8Array {
+  const out = new ByteWriter();
+  msgpackEncode(out, value);
+  return out.finish();
+}
+
+/**
+ * Decode one MessagePack value (the whole buffer)
+ */
+export function decodeMessagePack(bytes: Uint8Array): EncodedValue {
+  const input = new ByteReader(bytes, 'MessagePack');
+  const value = msgpackDecode(input);
+  input.finish();
+  return value;
+}
+
+/**
+ * Encode call arguments in `encoding`
+ */
+export function encodeWASMValue(encoding: WASMEncoding, value: unknown): Uint8Array {
+  return encoding === 'cbor' ? encodeCBOR(value) : encodeMessagePack(value);
+}
+
+/**
+ * Decode a call result in `encoding`
+ */
+export function decodeWASMValue(encoding: WASMEncoding, bytes: Uint8Array): EncodedValue {
+  return encoding === 'cbor' ? decodeCBOR(bytes) : decodeMessagePack(bytes);
+}
@@ -0,0 +1,480 @@
+/**
+ * WASM Component Loader
+ *
+ * Typed bindings for modules that implement a WIT world through the
+ * component model's canonical ABI, such as the `echelon:wasm` world in
+ * `wasm_modules/rust_module/wit/echelon.wit`.
+ *
+ * Deno cannot instantiate component binaries directly, so the loader takes
+ * the world's core module (`string_utils_component_core.wasm` from
+ * `COMPONENT=1 ./build.sh`) plus its WIT, and lifts and lowers values itself:
+ * strings are copied in through `cabi_realloc`, results are read from the
+ * return area and released through `cabi_post_*`. The component binary
+ * (`string_utils_component.wasm`) is for other component hosts (wasmtime,
+ * jco, ...).
+ *
+ * The loader understands the WIT subset the echelon world uses: interfaces
+ * of functions over scalars, `char`, `string` and `list<u8>`.
+ *
+ * @example
+ * ```typescript
+ * const component = await loadWASMComponent('./wasm_modules/string_utils_component_core.wasm', {
+ *   wit: await Deno.readTextFile('./wasm_modules/string_utils.wit'),
+ * });
+ * component.exports.text.reverseString('hello'); // 'olleh'
+ * component.exports.cipher.caesarEncrypt('abc', 3); // 'def'
+ * ```
+ */
+
+import { getLogger } from '../telemetry/logger.ts';
+
+const logger = getLogger();
+
+/** WIT value types supported by the loader */
+export type WITType =
+  | 'bool'
+  | 'u8'
+  | 'u16'
+  | 'u32'
+  | 'u64'
+  | 's8'
+  | 's16'
+  | 's32'
+  | 's64'
+  | 'f32'
+  | 'f64'
+  | 'char'
+  | 'string'
+  | 'list<u8>';
+
+const WIT_TYPES = new Set<string>([
+  'bool', 'u8', 'u16', 'u32', 'u64', 's8', 's16', 's32', 's64', 'f32', 'f64', 'char', 'string', 'list<u8>',
+]);
+
+/**
+ * Function declared in a WIT interface
+ */
+export interface WITFunction {
+  name: string;
+  params: Array<{ name: string; type: WITType }>;
+  result?: WITType;
+  doc?: string;
+}
+
+/**
+ * WIT interface
+ */
+export interface WITInterface {
+  name: string;
+  functions: WITFunction[];
+  doc?: string;
+}
+
+/**
+ * WIT world
+ */
+export interface WITWorld {
+  name: string;
+  imports: string[];
+  exports: string[];
+}
+
+/**
+ * Parsed WIT package
+ */
+export interface WITPackage {
+  namespace: string;
+  name: string;
+  version?: string;
+  interfaces: Map<string, WITInterface>;
+  worlds: Map<string, WITWorld>;
+}
+
+/**
+ * Options for `loadWASMComponent`
+ */
+export interface WASMComponentOptions {
+  /** WIT source of the package the module implements */
+  wit: string;
+  /** World to bind (default: the package's only world) */
+  world?: string;
+  /** Imports of the core module; unset function imports become no-ops */
+  imports?: WebAssembly.Imports;
+}
+
+/** Bound function of an exported interface */
+export type WASMComponentFunction = (...args: unknown[]) => unknown;
+
+/**
+ * Instantiated component world
+ */
+export interface WASMComponent {
+  package: WITPackage;
+  world: WITWorld;
+  /** Exported interfaces by name, functions by camelCase name */
+  exports: Record<string, Record<string, WASMComponentFunction>>;
+}
+
+/**
+ * Parse a WIT package (the subset described in the module docs)
+ */
+export function parseWIT(source: string): WITPackage {
+  const tokens = tokenizeWIT(source);
+  let pos = 0;
+
+  const peek = () => tokens[pos];
+  const next = () => {
+    const token = tokens[pos++];
+    if (token === undefined) throw new Error('WIT: unexpected end of input');
+    return token;
+  };
+  const expect = (value: string) => {
+    const token = next();
+    if (token.value !== value) {
+      throw new Error(`WIT: expected \`${value}\`, found \`${token.value}\``);
+    }
+  };
+
+  const pkg: WITPackage = { namespace: '', name: '', interfaces: new Map(), worlds: new Map() };
+
+  while (pos < tokens.length) {
+    const { value: keyword, doc } = next();
+    if (keyword === 'package') {
+      const id = next().value;
+      const match = /^([a-z][\w-]*):([a-z][\w-]*)(?:@(.+))?$/.exec(id);
+      if (!match) throw new Error(`WIT: invalid package name \`${id}\``);
+      [, pkg.namespace, pkg.name, pkg.version] = match;
+      expect(';');
+    } else if (keyword === 'interface') {
+      const iface: WITInterface = { name: next().value, functions: [], doc };
+      expect('{');
+      while (peek()?.value !== '}') {
+        const name = next();
+        expect(':');
+        expect('func');
+        expect('(');
+        const fn: WITFunction = { name: name.value, params: [], doc: name.doc };
+        while (peek()?.value !== ')') {
+          const param = next().value;
+          expect(':');
+          fn.params.push({ name: param, type: witType(next().value) });
+          if (peek()?.value === ',') next();
+        }
+        expect(')');
+        if (peek()?.value === '->') {
+          next();
+          fn.result = witType(next().value);
+        }
+        expect(';');
+        iface.functions.push(fn);
+      }
+      expect('}');
+      pkg.interfaces.set(iface.name, iface);
+    } else if (keyword === 'world') {
+      const world: WITWorld = { name: next().value, imports: [], exports: [] };
+      expect('{');
+      while (peek()?.value !== '}') {
+        const direction = next().value;
+        if (direction !== 'import' && direction !== 'export') {
+          throw new Error(`WIT: unsupported world item \`${direction}\``);
+        }
+        (direction === 'import' ? world.imports : world.exports).push(next().value);
+        expect(';');
+      }
+      expect('}');
+      pkg.worlds.set(world.name, world);
+    } else {
+      throw new Error(`WIT: unsupported item \`${keyword}\``);
+    }
+  }
+
+  if (!pkg.name) throw new Error('WIT: missing package declaration');
+  return pkg;
+}
+
+/**
+ * Instantiate a core module implementing a WIT world and bind its exports
+ */
+export async function loadWASMComponent(
+  source: string | URL | BufferSource | WebAssembly.Module,
+  options: WASMComponentOptions,
+): Promise<WASMComponent> {
+  const pkg = parseWIT(options.wit);
+  const world = selectWorld(pkg, options.world);
+
+  let module: WebAssembly.Module;
+  if (source instanceof WebAssembly.Module) {
+    module = source;
+  } else {
+    const bytes = typeof source === 'string' || source instanceof URL ? await Deno.readFile(source) : source;
+    if (isComponentBinary(bytes)) {
+      throw new Error(
+        'Component binaries cannot be instantiated directly; load the core module ' +
+          '(string_utils_component_core.wasm) or transpile the component with jco',
+      );
+    }
+    module = await WebAssembly.compile(bytes);
+  }
+
+  const imports: WebAssembly.Imports = { ...options.imports };
+  for (const { module: ns, name, kind } of WebAssembly.Module.imports(module)) {
+    if (kind !== 'function' || imports[ns]?.[name] !== undefined) continue;
+    // WASI imports of the core module are only reached on abort
+    imports[ns] = { ...imports[ns], [name]: () => 0 };
+  }
+
+  const instance = await WebAssembly.instantiate(module, imports);
+  const exports = instance.exports as Record<string, unknown>;
+  if (typeof exports._initialize === 'function') {
+    (exports._initialize as () => void)();
+  }
+
+  const abi = new CanonicalABI(exports);
+  const bound: WASMComponent['exports'] = {};
+  for (const name of world.exports) {
+    const iface = pkg.interfaces.get(name);
+    if (!iface) throw new Error(`WIT: world ${world.name} exports unknown interface ${name}`);
+
+    bound[name] = {};
+    for (const fn of iface.functions) {
+      bound[name][camelCase(fn.name)] = abi.bind(exportName(pkg, iface, fn), fn);
+    }
+  }
+
+  logger.debug(`Loaded WASM component world ${pkg.namespace}:${pkg.name}/${world.name}`);
+  return { package: pkg, world, exports: bound };
+}
+
+/**
+ * TypeScript declarations for a world's exports
+ */
+export function renderWITDeclarations(pkg: WITPackage, worldName?: string): string {
+  const world = selectWorld(pkg, worldName);
+  const lines = [`// ${pkg.namespace}:${pkg.name}${pkg.version ? `@${pkg.version}` : ''} world ${world.name}`, ''];
+
+  for (const name of world.exports) {
+    const iface = pkg.interfaces.get(name);
+    if (!iface) continue;
+    if (iface.doc) lines.push(`/** ${iface.doc} */`);
+    lines.push(`export interface ${pascalCase(iface.name)} {`);
+    for (const fn of iface.functions) {
+      if (fn.doc) lines.push(`  /** ${fn.doc} */`);
+      const params = fn.params.map((p) => `${camelCase(p.name)}: ${tsType(p.type)}`).join(', ');
+      lines.push(`  ${camelCase(fn.name)}(${params}): ${fn.result ? tsType(fn.result) : 'void'};`);
+    }
+    lines.push('}', '');
+  }
+
+  lines.push(`export interface ${pascalCase(world.name)}Exports {`);
+  for (const name of world.exports) {
+    lines.push(`  ${JSON.stringify(name)}: ${pascalCase(name)};`);
+  }
+  lines.push('}', '');
+  return lines.join('\n');
+}
+
+/**
+ * Canonical ABI lifting and lowering over a core instance
+ */
+class CanonicalABI {
+  private readonly encoder = new TextEncoder();
+  private readonly decoder = new TextDecoder('utf-8', { fatal: true });
+
+  constructor(private readonly exports: Record<string, unknown>) {}
+
+  bind(name: string, fn: WITFunction): WASMComponentFunction {
+    const core = this.exports[name];
+    if (typeof core !== 'function') {
+      throw new Error(`Core module does not export ${name}`);
+    }
+    const post = this.exports[`cabi_post_${name}`];
+    const wit = `${name}(${fn.params.map((p) => p.type).join(', ')})`;
+
+    return (...args: unknown[]) => {
+      if (args.length !== fn.params.length) {
+        throw new TypeError(`${wit}: expected ${fn.params.length} arguments, got ${args.length}`);
+      }
+      const flat = fn.params.flatMap((param, index) => this.lower(param.type, args[index], wit));
+      const raw = (core as (...args: unknown[]) => unknown)(...flat);
+      if (fn.result === undefined) return undefined;
+
+      const result = this.lift(fn.result, raw as number | bigint);
+      if (typeof post === 'function') (post as (ret: unknown) => void)(raw);
+      return result;
+    };
+  }
+
+  private lower(type: WITType, value: unknown, wit: string): Array<number | bigint> {
+    switch (type) {
+      case 'bool':
+        return [value ? 1 : 0];
+      case 'u8':
+      case 'u16':
+      case 'u32':
+      case 's8':
+      case 's16':
+      case 's32':
+      case 'f32':
+      case 'f64':
+        if (typeof value !== 'number') throw new TypeError(`${wit}: expected a number for ${type}`);
+        return [type.startsWith('f') ? value : INT_MASKS[type](value)];
+      case 'u64':
+      case 's64':
+        return [BigInt.asIntN(64, BigInt(value as number | bigint))];
+      case 'char': {
+        const code = typeof value === 'string' ? value.codePointAt(0) : undefined;
+        if (code === undefined) throw new TypeError(`${wit}: expected a character`);
+        return [code];
+      }
+      case 'string':
+        if (typeof value !== 'string') throw new TypeError(`${wit}: expected a string`);
+        return this.lowerBytes(this.encoder.encode(value));
+      case 'list<u8>':
+        if (!(value instanceof Uint8Array)) throw new TypeError(`${wit}: expected a Uint8Array`);
+        return this.lowerBytes(value);
+    }
+  }
+
+  private lift(type: WITType, raw: number | bigint): unknown {
+    switch (type) {
+      case 'bool':
+        return raw !== 0;
+      case 'u8':
+      case 'u16':
+      case 'u32':
+      case 's8':
+      case 's16':
+      case 's32':
+        return INT_MASKS[type](raw as number);
+      case 'u64':
+        return BigInt.asUintN(64, raw as bigint);
+      case 's64':
+        return BigInt.asIntN(64, raw as bigint);
+      case 'f32':
+      case 'f64':
+        return raw;
+      case 'char':
+        return String.fromCodePoint(raw as number);
+      case 'string':
+        return this.decoder.decode(this.liftBytes(raw as number));
+      case 'list<u8>':
+        return this.liftBytes(raw as number);
+    }
+  }
+
+  /** Copy bytes into a `cabi_realloc` block: `[ptr, len]` */
+  private lowerBytes(bytes: Uint8Array): number[] {
+    const realloc = this.exports.cabi_realloc as (old: number, oldSize: number, align: number, size: number) => number;
+    const ptr = realloc(0, 0, 1, bytes.length);
+    new Uint8Array(this.memory.buffer, ptr, bytes.length).set(bytes);
+    return [ptr, bytes.length];
+  }
+
+  /** Copy bytes out of the `(ptr, len)` return area at `area` */
+  private liftBytes(area: number): Uint8Array {
+    const view = new DataView(this.memory.buffer);
+    const ptr = view.getUint32(area, true);
+    const len = view.getUint32(area + 4, true);
+    return new Uint8Array(this.memory.buffer, ptr, len).slice();
+  }
+
+  private get memory(): WebAssembly.Memory {
+    return this.exports.memory as WebAssembly.Memory;
+  }
+}
+
+const INT_MASKS: Record<string, (value: number) => number> = {
+  u8: (value) => value & 0xff,
+  u16: (value) => value & 0xffff,
+  u32: (value) => value >>> 0,
+  s8: (value) => (value << 24) >> 24,
+  s16: (value) => (value << 16) >> 16,
+  s32: (value) => value | 0,
+};
+
+function tokenizeWIT(source: string): Array<{ value: string; doc?: string }> {
+  const tokens: Array<{ value: string; doc?: string }> = [];
+  const pattern = /\/\/\/(.*)|\/\/.*|\/\*[\s\S]*?\*\/|(->|[{}():;,]|[\w@.:<>-]+)|\s+/gy;
+  let doc: string[] = [];
+
+  while (pattern.lastIndex < source.length) {
+    const offset = pattern.lastIndex;
+    const match = pattern.exec(source);
+    if (match === null) {
+      throw new Error(`WIT: unexpected input at offset ${offset}`);
+    }
+    if (match[1] !== undefined) {
+      doc.push(match[1].trim());
+    } else if (match[2] !== undefined) {
+      tokens.push({ value: match[2], doc: doc.length > 0 ? doc.join(' ') : undefined });
+      doc = [];
+    }
+  }
+
+  // `name:` / `param:` tokenize together with the word; split the colon off
+  // unless it separates a package namespace
+  return tokens.flatMap((token) =>
+    token.value.length > 1 && token.value.endsWith(':')
+      ? [{ value: token.value.slice(0, -1), doc: token.doc }, { value: ':' }]
+      : [token]
+  );
+}
+
+function witType(name: string): WITType {
+  if (!WIT_TYPES.has(name)) throw new Error(`WIT: unsupported type \`${name}\``);
+  return name as WITType;
+}
+
+function selectWorld(pkg: WITPackage, name?: string): WITWorld {
+  if (name !== undefined) {
+    const world = pkg.worlds.get(name);
+    if (!world) throw new Error(`WIT: no world named ${name}`);
+    return world;
+  }
+  if (pkg.worlds.size !== 1) {
+    throw new Error(`WIT: package declares ${pkg.worlds.size} worlds; pass \`world\``);
+  }
+  return pkg.worlds.values().next().value!;
+}
+
+/** Core export name of an interface function, e.g. `echelon:wasm/text@0.1.0#word-count` */
+function exportName(pkg: WITPackage, iface: WITInterface, fn: WITFunction): string {
+  const version = pkg.version ? `@${pkg.version}` : '';
+  return `${pkg.namespace}:${pkg.name}/${iface.name}${version}#${fn.name}`;
+}
+
+/** Whether bytes hold a component (layer 1) rather than a core module */
+function isComponentBinary(source: BufferSource): boolean {
+  const bytes = source instanceof Uint8Array
+    ? source
+    : ArrayBuffer.isView(source)
+    ? new Uint8Array(source.buffer, source.byteOffset, source.byteLength)
+    : new Uint8Array(source);
+  return bytes.length >= 8 && bytes[6] === 0x01 && bytes[7] === 0x00;
+}
+
+function tsType(type: WITType): string {
+  switch (type) {
+    case 'bool':
+      return 'boolean';
+    case 'u64':
+    case 's64':
+      return 'bigint';
+    case 'char':
+    case 'string':
+      return 'string';
+    case 'list<u8>':
+      return 'Uint8Array';
+    default:
+      return 'number';
+  }
+}
+
+function camelCase(name: string): string {
+  return name.replace(/-([a-z0-9])/g, (_, c: string) => c.toUpperCase());
+}
+
+function pascalCase(name: string): string {
+  const camel = camelCase(name);
+  return camel.charAt(0).toUpperCase() + camel.slice(1);
+}
@@ -23,6 +23,10 @@ import { WASI, type WASIOptions } from './wasm_wasi.ts';
 import {
   WASMHostFunctionRegistry,
   StandardHostFunctions,
+  DeterministicHostSource,
+  SystemEntropySource,
+  type DeterministicHostOptions,
+  type HostEntropySource,
   type HostFunctionContext,
 } from './wasm_host_functions.ts';
 
@@ -49,6 +53,11 @@ export interface ImportConfig {
 export interface WASMExecutorOptions {
   enableWASI?: boolean;
   enableHostFunctionRegistry?: boolean;
+  /**
+   * Seed the host RNG and freeze the host clocks, for every import that
+   * reads them (`env.Math.random`, `env.Date.now`, `math.*`, `time.*`, WASI)
+   */
+  deterministic?: DeterministicHostOptions;
 }
 
 /**
@@ -65,6 +74,7 @@ export class WASMExecutor {
   private hostFunctionRegistry: WASMHostFunctionRegistry;
   private enableWASI: boolean;
   private enableRegistry: boolean;
+  private entropy: HostEntropySource;
 
   constructor(
     events: EventEmitter,
@@ -75,6 +85,9 @@ export class WASMExecutor {
     this.memoryManager = memoryManager;
     this.enableWASI = options.enableWASI ?? true;
     this.enableRegistry = options.enableHostFunctionRegistry ?? true;
+    this.entropy = options.deterministic
+      ? new DeterministicHostSource(options.deterministic)
+      : SystemEntropySource;
 
     // Initialize host function registry
     this.hostFunctionRegistry = new WASMHostFunctionRegistry();
@@ -205,6 +218,9 @@ export class WASMExecutor {
     options: WASMExecutionOptions = {}
   ): Promise<WASMExecutionResult<T>> {
     const startTime = performance.now();
+    if (module.instance) {
+      this.memoryManager.sampleHeap(module.id, module.instance.exports);
+    }
     const startMemory = this.memoryManager.getStats(module.id).used;
 
     this.events.emit(WASMEvents.EXEC_START, {
@@ -226,15 +242,36 @@ export class WASMExecutor {
         throw new Error(`Export '${funcName}' is not a function`);
       }
 
+      // Cooperative fuel budget (the module meters its own loops)
+      const fuel = options.gasLimit !== undefined ? fuelControls(instance) : undefined;
+      fuel?.limit(options.gasLimit!);
+
       // Execute with optional timeout
       let result: T;
-      if (options.timeout) {
-        result = await this.executeWithTimeout(func, args, options.timeout);
-      } else {
-        result = func(...args) as T;
+      let gasUsed: number | undefined;
+      try {
+        if (options.timeout) {
+          result = await this.executeWithTimeout(func, args, options.timeout);
+        } else {
+          result = func(...args) as T;
+        }
+      } finally {
+        if (fuel) {
+          gasUsed = fuel.used();
+          fuel.reset();
+        }
+      }
+
+      if (fuel?.exhausted()) {
+        throw new BudgetExhaustedError(
+          `Fuel budget of ${options.gasLimit} exhausted in '${funcName}'`,
+          gasUsed!,
+          fuel.resumable()
+        );
       }
 
       const duration = performance.now() - startTime;
+      this.memoryManager.sampleHeap(module.id, instance.exports);
       const endMemory = this.memoryManager.getStats(module.id).used;
 
       // Update module stats
@@ -252,6 +289,7 @@ export class WASMExecutor {
         success: true,
         value: result,
         duration,
+        gasUsed,
         memoryUsed: endMemory - startMemory,
       };
     } catch (error) {
@@ -263,6 +301,13 @@ export class WASMExecutor {
           function: funcName,
           timeout: options.timeout,
         });
+      } else if (error instanceof BudgetExhaustedError) {
+        this.events.emit(WASMEvents.EXEC_BUDGET_EXHAUSTED, {
+          moduleId: module.id,
+          function: funcName,
+          gasLimit: options.gasLimit,
+          resumable: error.resumable,
+        });
       } else {
         this.events.emit(WASMEvents.EXEC_ERROR, {
           moduleId: module.id,
@@ -275,6 +320,7 @@ export class WASMExecutor {
         success: false,
         error: error instanceof Error ? error : new Error(String(error)),
         duration,
+        gasUsed: error instanceof BudgetExhaustedError ? error.gasUsed : undefined,
         memoryUsed: 0,
       };
     }
@@ -381,7 +427,11 @@ export class WASMExecutor {
 
     // Add WASI imports if enabled
     if (this.enableWASI && config.enableWASI !== false && config.moduleId) {
-      const wasi = new WASI(config.wasiOptions || {});
+      // WASI keeps its cryptographic RNG unless the executor is deterministic
+      const wasi = new WASI({
+        ...(this.entropy instanceof DeterministicHostSource ? { entropy: this.entropy } : {}),
+        ...config.wasiOptions,
+      });
       this.wasiInstances.set(config.moduleId, wasi);
 
       const wasiImports = wasi.getImports();
@@ -471,6 +521,11 @@ export class WASMExecutor {
         // Grow memory
         return -1; // Fail by default, modules should use their own memory
       },
+
+      // Entropy and time (seeded and frozen in deterministic mode)
+      'Math.random': () => this.entropy.random(),
+      'Date.now': () => this.entropy.now(),
+      'performance.now': () => this.entropy.performanceNow(),
     };
   }
 
@@ -494,8 +549,17 @@ export class WASMExecutor {
    */
   private registerStandardHostFunctions(): void {
     // Register all standard host functions from the registry
+    const entropy: Record<string, Record<string, unknown>> = {
+      math: { random: () => this.entropy.random() },
+      time: {
+        now: () => this.entropy.now(),
+        performance_now: () => this.entropy.performanceNow(),
+      },
+    };
+
     for (const [module, functions] of Object.entries(StandardHostFunctions)) {
-      for (const [name, func] of Object.entries(functions)) {
+      for (const [name, standard] of Object.entries(functions)) {
+        const func = entropy[module]?.[name] ?? standard;
         this.hostFunctionRegistry.registerGlobal(
           module,
           name,
@@ -559,14 +623,14 @@ export class WASMExecutor {
     this.registerHostFunction({
       name: 'now',
       module: 'time',
-      func: () => Date.now(),
+      func: () => this.entropy.now(),
       signature: { params: [], results: ['f64'] },
     });
 
     this.registerHostFunction({
       name: 'performance_now',
       module: 'time',
-      func: () => performance.now(),
+      func: () => this.entropy.performanceNow(),
       signature: { params: [], results: ['f64'] },
     });
 
@@ -574,7 +638,7 @@ export class WASMExecutor {
     this.registerHostFunction({
       name: 'random',
       module: 'math',
-      func: () => Math.random(),
+      func: () => this.entropy.random(),
       signature: { params: [], results: ['f64'] },
     });
 
@@ -631,6 +695,13 @@ export class WASMExecutor {
     return this.wasiInstances.get(moduleId);
   }
 
+  /**
+   * Get the RNG and clock source behind the host imports
+   */
+  getEntropySource(): HostEntropySource {
+    return this.entropy;
+  }
+
   /**
    * Get host function registry
    */
@@ -695,3 +766,46 @@ export class TimeoutError extends Error {
     this.name = 'TimeoutError';
   }
 }
+
+/**
+ * Error raised when a call runs out of its cooperative fuel budget.
+ * If `resumable`, calling the same function again with the same arguments
+ * (and a new budget) continues where it stopped.
+ */
+export class BudgetExhaustedError extends Error {
+  constructor(
+    message: string,
+    public readonly gasUsed: number,
+    public readonly resumable: boolean
+  ) {
+    super(message);
+    this.name = 'BudgetExhaustedError';
+  }
+}
+
+/** `ErrorCode.BudgetExhausted` of echelon_wasm modules */
+const BUDGET_EXHAUSTED = 5;
+
+/**
+ * Fuel exports of a module, undefined if it does not meter itself
+ */
+function fuelControls(instance: WebAssembly.Instance) {
+  const exports = instance.exports as Record<string, unknown>;
+  const fn = (name: string) =>
+    typeof exports[name] === 'function' ? exports[name] as (...args: number[]) => number : undefined;
+
+  const setFuel = fn('echelon_set_fuel');
+  const fuelUsed = fn('echelon_fuel_used');
+  if (!setFuel || !fuelUsed) return undefined;
+
+  const lastErrorCode = fn('echelon_last_error_code');
+  const isSuspended = fn('echelon_is_suspended');
+  return {
+    // 0 means unlimited to the module, so the smallest budget is 1 unit
+    limit: (units: number) => setFuel(Math.max(1, Math.min(Math.floor(units), 0xffffffff)) >>> 0),
+    reset: () => setFuel(0),
+    used: () => fuelUsed() >>> 0,
+    exhausted: () => lastErrorCode?.() === BUDGET_EXHAUSTED,
+    resumable: () => isSuspended?.() === 1,
+  };
+}
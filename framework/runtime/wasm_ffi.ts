@@ -0,0 +1,470 @@
+/**
+ * echelon_wasm Backends (WASM and native FFI)
+ *
+ * The `echelon_wasm` crate builds to both `string_utils.wasm` and a native
+ * cdylib (`libechelon_wasm.so` / `.dylib` / `echelon_wasm.dll`) exposing the
+ * same C ABI. This module wraps either artifact behind one
+ * `EchelonStringUtils` interface so callers can pick the fast native path on
+ * servers with `--allow-ffi` and fall back to WASM everywhere else.
+ *
+ * Both backends share the protocol from `src/abi.rs`: inputs are copied into
+ * blocks from `echelon_alloc`, string results are freed with
+ * `echelon_dealloc`, and failures are read from `echelon_last_error_code` /
+ * `echelon_last_error_message` and thrown as `EchelonCallError`. For large
+ * payloads on WASM, `EchelonSharedBuffers` (`wasm_shared_buffers.ts`) works
+ * on regions reused across calls instead.
+ *
+ * @example
+ * ```typescript
+ * const utils = await loadEchelonBackend({
+ *   ffiPath: './wasm_modules/rust_module/target/release/libechelon_wasm.so',
+ *   wasmPath: './wasm_modules/string_utils.wasm',
+ * });
+ * utils.reverse_string('hello'); // 'olleh' (native if FFI is allowed)
+ * utils.close();
+ * ```
+ */
+
+import { getLogger } from '../telemetry/logger.ts';
+import type { CheckResult, HealthChecker } from '../admin/health.ts';
+import { entropyImports } from './wasm_host_functions.ts';
+import {
+  decodeWASMValue,
+  type EncodedValue,
+  encodeWASMValue,
+  WASM_ENCODING_IDS,
+  type WASMEncoding,
+} from './wasm_codec.ts';
+
+const logger = getLogger();
+
+/** Which artifact backs an `EchelonStringUtils` instance */
+export type EchelonBackendKind = 'ffi' | 'wasm';
+
+/**
+ * The echelon_wasm functions, identical across backends.
+ * Functions from export groups not compiled into the artifact throw.
+ */
+export interface EchelonStringUtils {
+  readonly backend: EchelonBackendKind;
+  count_vowels(s: string): number;
+  reverse_string(s: string): string;
+  is_palindrome(s: string): boolean;
+  hash_string(s: string): number;
+  longest_word_length(s: string): number;
+  word_count(s: string): number;
+  caesar_encrypt(s: string, shift: number): string;
+  add(a: number, b: number): number;
+  multiply(a: number, b: number): number;
+  memory_intensive(size: number): number;
+  /**
+   * Call a method with an options map (`codec` feature). Arguments are named
+   * after the Rust parameters; optional fields can be left out.
+   *
+   * @example
+   * ```typescript
+   * utils.call<string>('caesar_encrypt', { s: 'def', shift: 3, decrypt: true });
+   * ```
+   */
+  call<T = EncodedValue>(method: string, args: Record<string, unknown>, encoding?: WASMEncoding): T;
+  /** ABI contract version (`null` for builds that predate it) */
+  abi_version(): number | null;
+  /** Whether the build enables a cargo feature or contract, e.g. `hash.djb2` */
+  supports(name: string): boolean;
+  /** Check every export against the module's known-answer vectors (`selftest` feature) */
+  run_self_test(): EchelonSelfTestReport;
+  /** Time `iterations` calls of every export (`selftest` feature) */
+  run_benchmarks(iterations: number): EchelonBenchmarkReport;
+  /** Release the library or instance */
+  close(): void;
+}
+
+/**
+ * Result of `run_self_test`
+ */
+export interface EchelonSelfTestReport {
+  passed: number;
+  failed: number;
+  checks: Array<{
+    function: string;
+    /** Known-answer vectors run for this function */
+    vectors: number;
+    passed: boolean;
+    /** First failing vector (Rust `Debug` formatting) */
+    input?: string;
+    expected?: string;
+    actual?: string;
+  }>;
+}
+
+/**
+ * Result of `run_benchmarks`
+ *
+ * Throughput is `null` when the module has no real clock: wasm32 builds
+ * without `host-entropy`, or any build in deterministic mode (`clock: 'virtual'`).
+ */
+export interface EchelonBenchmarkReport {
+  iterations: number;
+  clock: 'host' | 'virtual';
+  results: Array<{
+    function: string;
+    /** Input size per call (`0` for scalar functions) */
+    bytes_per_call: number;
+    elapsed_ms: number;
+    calls_per_sec: number | null;
+    mb_per_sec: number | null;
+  }>;
+}
+
+/**
+ * Error raised when an export records a failure in the last-error slot
+ */
+export class EchelonCallError extends Error {
+  constructor(
+    public readonly fn: string,
+    /** `ErrorCode` value from the Rust side */
+    public readonly code: number,
+    message: string
+  ) {
+    super(`${fn}: ${message}`);
+    this.name = 'EchelonCallError';
+  }
+}
+
+/**
+ * Low-level access to the C ABI, implemented once per backend
+ */
+interface EchelonRawABI {
+  readonly kind: EchelonBackendKind;
+  has(name: string): boolean;
+  /** Call an export with plain numeric arguments */
+  call(name: string, ...args: number[]): number | bigint;
+  /** Call an export taking `(ptr, len, ...args)` for a UTF-8 input */
+  callWithBytes(name: string, input: Uint8Array, ...args: number[]): number | bigint;
+  /** Call an export taking `(ptr, len)` for every input, then `...args` */
+  callWithBuffers(name: string, inputs: Uint8Array[], ...args: number[]): number | bigint;
+  /** Copy out and free a packed string result */
+  takeBytes(packed: number | bigint): Uint8Array;
+  close(): void;
+}
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+/** ErrorCode.OutOfMemory */
+const OUT_OF_MEMORY = 4;
+
+/** Exports only present with the matching cargo feature, or in newer builds */
+const OPTIONAL_EXPORTS: Record<string, { parameters: Deno.NativeType[]; result: Deno.NativeResultType }> = {
+  echelon_count_vowels: { parameters: ['buffer', 'usize'], result: 'u32' },
+  echelon_reverse_string: { parameters: ['buffer', 'usize'], result: 'u64' },
+  echelon_is_palindrome: { parameters: ['buffer', 'usize'], result: 'u32' },
+  echelon_hash_string: { parameters: ['buffer', 'usize'], result: 'u32' },
+  echelon_longest_word_length: { parameters: ['buffer', 'usize'], result: 'u32' },
+  echelon_word_count: { parameters: ['buffer', 'usize'], result: 'u32' },
+  echelon_caesar_encrypt: { parameters: ['buffer', 'usize', 'u8'], result: 'u64' },
+  echelon_add: { parameters: ['i32', 'i32'], result: 'i32' },
+  echelon_multiply: { parameters: ['i32', 'i32'], result: 'i32' },
+  echelon_memory_intensive: { parameters: ['usize'], result: 'i32' },
+  echelon_call_encoded: { parameters: ['buffer', 'usize', 'buffer', 'usize', 'u32'], result: 'u64' },
+  echelon_abi_version: { parameters: [], result: 'u32' },
+  echelon_supports: { parameters: ['buffer', 'usize'], result: 'u32' },
+  echelon_run_self_test: { parameters: [], result: 'u64' },
+  echelon_run_benchmarks: { parameters: ['u32'], result: 'u64' },
+};
+
+const FFI_SYMBOLS = {
+  echelon_alloc: { parameters: ['usize'], result: 'pointer' },
+  echelon_dealloc: { parameters: ['pointer'], result: 'void' },
+  echelon_block_len: { parameters: ['pointer'], result: 'usize' },
+  echelon_last_error_code: { parameters: [], result: 'u32' },
+  echelon_last_error_message: { parameters: [], result: 'u64' },
+  ...Object.fromEntries(
+    Object.entries(OPTIONAL_EXPORTS).map(([name, def]) => [name, { ...def, optional: true }])
+  ),
+} as const satisfies Deno.ForeignLibraryInterface;
+
+type FFIFunction = (...args: unknown[]) => unknown;
+
+/**
+ * Native cdylib through `Deno.dlopen`.
+ * On 64-bit hosts a packed result is the block pointer; the length comes
+ * from `echelon_block_len`.
+ */
+class FFIRawABI implements EchelonRawABI {
+  readonly kind = 'ffi' as const;
+  private readonly symbols: Record<string, FFIFunction | null>;
+
+  constructor(private readonly lib: Deno.DynamicLibrary<typeof FFI_SYMBOLS>) {
+    this.symbols = lib.symbols as unknown as Record<string, FFIFunction | null>;
+  }
+
+  has(name: string): boolean {
+    return typeof this.symbols[name] === 'function';
+  }
+
+  call(name: string, ...args: number[]): number | bigint {
+    return this.fn(name)(...args) as number | bigint;
+  }
+
+  callWithBytes(name: string, input: Uint8Array, ...args: number[]): number | bigint {
+    return this.callWithBuffers(name, [input], ...args);
+  }
+
+  callWithBuffers(name: string, inputs: Uint8Array[], ...args: number[]): number | bigint {
+    return this.fn(name)(...inputs.flatMap((input) => [input, input.length]), ...args) as number | bigint;
+  }
+
+  takeBytes(packed: number | bigint): Uint8Array {
+    const ptr = Deno.UnsafePointer.create(BigInt(packed));
+    if (ptr === null) return new Uint8Array(0);
+
+    const len = Number(this.fn('echelon_block_len')(ptr));
+    const bytes = len === 0
+      ? new Uint8Array(0)
+      : new Uint8Array(Deno.UnsafePointerView.getArrayBuffer(ptr, len)).slice();
+    this.fn('echelon_dealloc')(ptr);
+    return bytes;
+  }
+
+  close(): void {
+    this.lib.close();
+  }
+
+  private fn(name: string): FFIFunction {
+    const fn = this.symbols[name];
+    if (typeof fn !== 'function') {
+      throw new Error(`echelon_wasm symbol not available in this build: ${name}`);
+    }
+    return fn;
+  }
+}
+
+/**
+ * Raw exports of `string_utils.wasm` (no wasm-bindgen glue).
+ * Packed results are `(ptr << 32) | len`.
+ */
+class WASMRawABI implements EchelonRawABI {
+  readonly kind = 'wasm' as const;
+
+  constructor(private readonly exports: Record<string, unknown>) {}
+
+  has(name: string): boolean {
+    return typeof this.exports[name] === 'function';
+  }
+
+  call(name: string, ...args: number[]): number | bigint {
+    return this.fn(name)(...args) as number | bigint;
+  }
+
+  callWithBytes(name: string, input: Uint8Array, ...args: number[]): number | bigint {
+    return this.callWithBuffers(name, [input], ...args);
+  }
+
+  callWithBuffers(name: string, inputs: Uint8Array[], ...args: number[]): number | bigint {
+    const ptrs: number[] = [];
+    try {
+      for (const input of inputs) {
+        const ptr = this.fn('echelon_alloc')(input.length) as number;
+        if (ptr === 0) {
+          throw new EchelonCallError(name, OUT_OF_MEMORY, 'cannot allocate input buffer');
+        }
+        ptrs.push(ptr);
+        new Uint8Array(this.memory.buffer, ptr, input.length).set(input);
+      }
+      const pointers = inputs.flatMap((input, i) => [ptrs[i], input.length]);
+      return this.fn(name)(...pointers, ...args) as number | bigint;
+    } finally {
+      for (const ptr of ptrs) this.fn('echelon_dealloc')(ptr);
+    }
+  }
+
+  takeBytes(packed: number | bigint): Uint8Array {
+    const value = BigInt.asUintN(64, BigInt(packed));
+    const ptr = Number(value >> 32n);
+    const len = Number(value & 0xffffffffn);
+    if (ptr === 0) return new Uint8Array(0);
+
+    const bytes = new Uint8Array(this.memory.buffer, ptr, len).slice();
+    this.fn('echelon_dealloc')(ptr);
+    return bytes;
+  }
+
+  close(): void {
+    // Instance memory is reclaimed by GC
+  }
+
+  private get memory(): WebAssembly.Memory {
+    return this.exports.memory as WebAssembly.Memory;
+  }
+
+  private fn(name: string): FFIFunction {
+    const fn = this.exports[name];
+    if (typeof fn !== 'function') {
+      throw new Error(`echelon_wasm export not available in this build: ${name}`);
+    }
+    return fn as FFIFunction;
+  }
+}
+
+/**
+ * Build the shared API over a raw backend
+ */
+function wrap(raw: EchelonRawABI): EchelonStringUtils {
+  const check = <T>(fn: string, value: T): T => {
+    const code = Number(raw.call('echelon_last_error_code'));
+    if (code !== 0) {
+      const message = decoder.decode(raw.takeBytes(raw.call('echelon_last_error_message')));
+      throw new EchelonCallError(fn, code, message);
+    }
+    return value;
+  };
+
+  const numeric = (fn: string) => (s: string): number =>
+    check(fn, Number(raw.callWithBytes(`echelon_${fn}`, encoder.encode(s))));
+
+  const string = (fn: string, ...args: number[]) => (s: string): string => {
+    const packed = raw.callWithBytes(`echelon_${fn}`, encoder.encode(s), ...args);
+    const bytes = raw.takeBytes(packed);
+    return check(fn, decoder.decode(bytes));
+  };
+
+  const scalar = (fn: string) => (...args: number[]): number =>
+    check(fn, Number(raw.call(`echelon_${fn}`, ...args)));
+
+  const report = <T>(fn: string, ...args: number[]): T => {
+    const bytes = raw.takeBytes(raw.call(`echelon_${fn}`, ...args));
+    return JSON.parse(check(fn, decoder.decode(bytes))) as T;
+  };
+
+  const call = <T>(method: string, args: Record<string, unknown>, encoding: WASMEncoding = 'cbor'): T => {
+    const inputs = [encoder.encode(method), encodeWASMValue(encoding, args)];
+    const packed = raw.callWithBuffers('echelon_call_encoded', inputs, WASM_ENCODING_IDS[encoding]);
+    const bytes = check(method, raw.takeBytes(packed));
+    return decodeWASMValue(encoding, bytes) as T;
+  };
+
+  return {
+    backend: raw.kind,
+    count_vowels: numeric('count_vowels'),
+    reverse_string: string('reverse_string'),
+    is_palindrome: (s) => numeric('is_palindrome')(s) !== 0,
+    hash_string: (s) => numeric('hash_string')(s) >>> 0,
+    longest_word_length: numeric('longest_word_length'),
+    word_count: numeric('word_count'),
+    caesar_encrypt: (s, shift) => string('caesar_encrypt', shift & 0xff)(s),
+    add: (a, b) => scalar('add')(a, b),
+    multiply: (a, b) => scalar('multiply')(a, b),
+    memory_intensive: (size) => scalar('memory_intensive')(size),
+    call,
+    abi_version: () => (raw.has('echelon_abi_version') ? scalar('abi_version')() : null),
+    supports: (name) => raw.has('echelon_supports') && numeric('supports')(name) !== 0,
+    run_self_test: () => report<EchelonSelfTestReport>('run_self_test'),
+    run_benchmarks: (iterations) => report<EchelonBenchmarkReport>('run_benchmarks', iterations),
+    close: () => raw.close(),
+  };
+}
+
+/**
+ * Load the native cdylib through Deno FFI (requires `--allow-ffi`)
+ */
+export function loadEchelonFFI(libPath: string | URL): EchelonStringUtils {
+  const lib = Deno.dlopen(libPath, FFI_SYMBOLS);
+  logger.debug(`Loaded echelon_wasm native library: ${libPath}`);
+  return wrap(new FFIRawABI(lib));
+}
+
+/**
+ * Instantiate `string_utils.wasm` for its raw C ABI exports.
+ * wasm-bindgen and `env.console_*` imports are stubbed; the C ABI does not
+ * use them. `env."Math.random"` / `env."Date.now"` / `env."performance.now"`
+ * (the default `host-entropy` feature) get the system RNG and clocks.
+ * `hostImports` take precedence (e.g. `WASMKvHost.imports` for `host-kv`).
+ */
+export async function instantiateEchelonWASM(
+  source: string | URL | BufferSource,
+  hostImports: WebAssembly.Imports = {},
+): Promise<WebAssembly.Instance> {
+  const bytes = typeof source === 'string' || source instanceof URL
+    ? await Deno.readFile(source)
+    : source;
+  const module = await WebAssembly.compile(bytes);
+
+  const entropy = entropyImports();
+  const imports: WebAssembly.Imports = {};
+  for (const { module: ns, name, kind } of WebAssembly.Module.imports(module)) {
+    if (kind !== 'function') continue;
+    // The C ABI never reaches glue imports; `env.console_*` become no-ops
+    (imports[ns] ??= {})[name] = hostImports[ns]?.[name] ?? ((ns === 'env' && entropy[name]) || (() => 0));
+  }
+
+  return await WebAssembly.instantiate(module, imports);
+}
+
+/**
+ * Instantiate `string_utils.wasm` and use its raw C ABI exports
+ */
+export async function loadEchelonWASM(source: string | URL | BufferSource): Promise<EchelonStringUtils> {
+  const instance = await instantiateEchelonWASM(source);
+  logger.debug('Loaded echelon_wasm WASM module');
+  return wrap(new WASMRawABI(instance.exports as Record<string, unknown>));
+}
+
+/**
+ * Options for `loadEchelonBackend`
+ */
+export interface EchelonBackendOptions {
+  /** Path to the native cdylib */
+  ffiPath?: string | URL;
+  /** Path to `string_utils.wasm` or its bytes */
+  wasmPath?: string | URL | BufferSource;
+  /** Backend to try first (default: 'ffi') */
+  prefer?: EchelonBackendKind;
+}
+
+/**
+ * Load the preferred backend, falling back to the other one if it is not
+ * configured or cannot be loaded (e.g. FFI permission denied)
+ */
+export async function loadEchelonBackend(options: EchelonBackendOptions): Promise<EchelonStringUtils> {
+  const order: EchelonBackendKind[] = options.prefer === 'wasm' ? ['wasm', 'ffi'] : ['ffi', 'wasm'];
+  let lastError: unknown;
+
+  for (const kind of order) {
+    try {
+      if (kind === 'ffi' && options.ffiPath !== undefined && typeof Deno.dlopen === 'function') {
+        return loadEchelonFFI(options.ffiPath);
+      }
+      if (kind === 'wasm' && options.wasmPath !== undefined) {
+        return await loadEchelonWASM(options.wasmPath);
+      }
+    } catch (error) {
+      lastError = error;
+      logger.warn(`echelon_wasm ${kind} backend unavailable, trying fallback`, {
+        error: error instanceof Error ? error.message : String(error),
+      });
+    }
+  }
+
+  throw lastError ?? new Error('No echelon_wasm backend configured (set ffiPath or wasmPath)');
+}
+
+/**
+ * Health check that runs the module's known-answer self-test
+ *
+ * @example
+ * ```typescript
+ * health.register('string_utils', echelonSelfTestCheck(utils));
+ * ```
+ */
+export function echelonSelfTestCheck(utils: EchelonStringUtils): HealthChecker {
+  return () => {
+    const report = utils.run_self_test();
+    const failed = report.checks.filter((check) => !check.passed).map((check) => check.function);
+    return Promise.resolve<CheckResult>(
+      failed.length === 0
+        ? { status: 'pass', message: `${report.passed} functions passed (${utils.backend})` }
+        : { status: 'fail', message: `Self-test failed: ${failed.join(', ')} (${utils.backend})` },
+    );
+  };
+}
@@ -211,7 +211,7 @@ export class WASMHostFunctionRegistry {
 
     // Add global functions
     for (const [key, metadata] of this.globalFunctions) {
-      const [moduleName, funcName] = key.split('.');
+      const [moduleName, funcName] = splitImportKey(key);
 
       if (!imports[moduleName]) {
         imports[moduleName] = {};
@@ -224,7 +224,7 @@ export class WASMHostFunctionRegistry {
     const moduleFuncs = this.functions.get(moduleId);
     if (moduleFuncs) {
       for (const [key, metadata] of moduleFuncs) {
-        const [moduleName, funcName] = key.split('.');
+        const [moduleName, funcName] = splitImportKey(key);
 
         if (!imports[moduleName]) {
           imports[moduleName] = {};
@@ -289,6 +289,112 @@ export class WASMHostFunctionRegistry {
   }
 }
 
+/**
+ * Split a `module.name` key at the first dot (`env.Math.random` is import
+ * `Math.random` of module `env`)
+ */
+function splitImportKey(key: string): [string, string] {
+  const dot = key.indexOf('.');
+  return [key.slice(0, dot), key.slice(dot + 1)];
+}
+
+/**
+ * Where the standard host functions get randomness and time
+ */
+export interface HostEntropySource {
+  /** Float in `[0, 1)`, like `Math.random` */
+  random(): number;
+  /** Milliseconds since the Unix epoch, like `Date.now` */
+  now(): number;
+  /** Monotonic milliseconds, like `performance.now` */
+  performanceNow(): number;
+}
+
+/**
+ * The host's real RNG and clocks
+ */
+export const SystemEntropySource: HostEntropySource = {
+  random: () => Math.random(),
+  now: () => Date.now(),
+  performanceNow: () => performance.now(),
+};
+
+/**
+ * The `env` imports of a `host-entropy` build, for loaders that stub the rest
+ */
+export function entropyImports(source: HostEntropySource = SystemEntropySource): Record<string, () => number> {
+  return {
+    'Math.random': () => source.random(),
+    'Date.now': () => source.now(),
+    'performance.now': () => source.performanceNow(),
+  };
+}
+
+/**
+ * Options for `DeterministicHostSource`
+ */
+export interface DeterministicHostOptions {
+  /** Generator seed (a 64-bit value) */
+  seed: number | bigint;
+  /** Initial `now()` in milliseconds since the Unix epoch (default `0`) */
+  startTime?: number;
+  /** Milliseconds both clocks advance on every read (default `0`: frozen) */
+  tickMs?: number;
+}
+
+const MASK_64 = (1n << 64n) - 1n;
+
+/**
+ * Seeded RNG and virtual clock for reproducible runs
+ *
+ * Runs the SplitMix64 generator of the Rust module's deterministic mode
+ * (`entropy.rs`), so a module reading `env.Math.random` from this source
+ * with seed `s` sees the same values as one given `set_deterministic(s, ...)`.
+ * The clocks only move by `tickMs` per read and through `advance`.
+ */
+export class DeterministicHostSource implements HostEntropySource {
+  private state = 0n;
+  private elapsed = 0;
+
+  constructor(private readonly options: DeterministicHostOptions) {
+    this.reset();
+  }
+
+  random(): number {
+    this.state = (this.state + 0x9e3779b97f4a7c15n) & MASK_64;
+    let z = this.state;
+    z = ((z ^ (z >> 30n)) * 0xbf58476d1ce4e5b9n) & MASK_64;
+    z = ((z ^ (z >> 27n)) * 0x94d049bb133111ebn) & MASK_64;
+    z ^= z >> 31n;
+    return Number(z >> 11n) / 2 ** 53;
+  }
+
+  now(): number {
+    return (this.options.startTime ?? 0) + this.tick();
+  }
+
+  performanceNow(): number {
+    return this.tick();
+  }
+
+  /** Move both clocks forward by `ms` */
+  advance(ms: number): void {
+    this.elapsed += Math.max(0, ms);
+  }
+
+  /** Rewind to the seed and start time */
+  reset(): void {
+    this.state = BigInt.asUintN(64, BigInt(this.options.seed));
+    this.elapsed = 0;
+  }
+
+  private tick(): number {
+    const elapsed = this.elapsed;
+    this.elapsed += this.options.tickMs ?? 0;
+    return elapsed;
+  }
+}
+
 /**
  * Common host function helpers
  */
@@ -477,10 +583,23 @@ export const StandardHostFunctions = {
   },
 };
 
+/**
+ * Options for `registerStandardHostFunctions`
+ */
+export interface StandardHostFunctionOptions {
+  /** Source of `Math.random`, `Date.now` and `performance.now` (default: the system) */
+  entropy?: HostEntropySource;
+}
+
 /**
  * Register all standard host functions
  */
-export function registerStandardHostFunctions(registry: WASMHostFunctionRegistry): void {
+export function registerStandardHostFunctions(
+  registry: WASMHostFunctionRegistry,
+  options: StandardHostFunctionOptions = {},
+): void {
+  const entropy = options.entropy ?? SystemEntropySource;
+
   // Console functions
   registry.registerGlobal('env', 'console_log', StandardHostFunctions.console.log, {
     params: ['i32', 'i32'],
@@ -504,7 +623,7 @@ export function registerStandardHostFunctions(registry: WASMHostFunctionRegistry
   });
 
   // Math functions
-  registry.registerGlobal('env', 'Math.random', () => StandardHostFunctions.math.random(), {
+  registry.registerGlobal('env', 'Math.random', () => entropy.random(), {
     params: [],
     results: ['f64'],
   });
@@ -530,11 +649,11 @@ export function registerStandardHostFunctions(registry: WASMHostFunctionRegistry
   });
 
   // Time functions
-  registry.registerGlobal('env', 'Date.now', () => StandardHostFunctions.time.now(), {
+  registry.registerGlobal('env', 'Date.now', () => entropy.now(), {
     params: [],
     results: ['f64'],
   });
-  registry.registerGlobal('env', 'performance.now', () => StandardHostFunctions.time.performance_now(), {
+  registry.registerGlobal('env', 'performance.now', () => entropy.performanceNow(), {
     params: [],
     results: ['f64'],
   });
@@ -555,11 +674,14 @@ export function registerStandardHostFunctions(registry: WASMHostFunctionRegistry
 /**
  * Create a host function registry with standard functions
  */
-export function createHostFunctionRegistry(includeStandard = true): WASMHostFunctionRegistry {
+export function createHostFunctionRegistry(
+  includeStandard = true,
+  options: StandardHostFunctionOptions = {},
+): WASMHostFunctionRegistry {
   const registry = new WASMHostFunctionRegistry();
 
   if (includeStandard) {
-    registerStandardHostFunctions(registry);
+    registerStandardHostFunctions(registry, options);
   }
 
   return registry;
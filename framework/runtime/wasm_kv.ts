@@ -0,0 +1,344 @@
+/**
+ * echelon_wasm Host KV
+ *
+ * Serves the `env.kv_get` / `env.kv_set` / `env.kv_list` imports of
+ * `string_utils.wasm` builds with the `host-kv` feature (see `src/kv.rs`)
+ * from a `Deno.Kv` store, so module code awaits lookups on demand instead of
+ * the host prefetching data:
+ *
+ * - An import copies its arguments, starts the KV operation and returns.
+ * - When the operation settles the result is written into module memory and
+ *   reported with `echelon_kv_complete(op, status, ptr, len)`.
+ * - Reads need the `kv-read` capability and writes `kv-write` when a
+ *   `WASMSandboxManager` is given; a denied call completes with
+ *   `KvStatus.Denied` and is recorded as a violation.
+ *
+ * A module's keys live under `['wasm', moduleId]` unless `prefix` says
+ * otherwise. `EchelonKvIndex` drives the module's word index through the C
+ * ABI tasks and resolves a Promise when a task finishes.
+ *
+ * @example
+ * ```typescript
+ * const host = new WASMKvHost(await Deno.openKv(), { sandbox });
+ * const { exports } = await instantiateEchelonWASM(wasmPath, host.imports('search'));
+ * const index = host.attach('search', exports);
+ * await index.indexDocument('doc-1', 'The quick brown fox');
+ * await index.searchIndex('quick fox'); // ['doc-1']
+ * ```
+ */
+
+import { EventEmitter } from '../plugin/events.ts';
+import { EchelonCallError } from './wasm_ffi.ts';
+import type { HostFunctionContext, WASMHostFunctionRegistry } from './wasm_host_functions.ts';
+import { WASMMemoryManager } from './wasm_memory.ts';
+import type { WASMSandboxManager } from './wasm_sandbox.ts';
+
+/** Outcome passed to `echelon_kv_complete` (`KvStatus` in `src/kv.rs`) */
+export enum KvStatus {
+  Ok = 0,
+  NotFound = 1,
+  Denied = 2,
+  Failed = 3,
+}
+
+/**
+ * Options for `WASMKvHost`
+ */
+export interface WASMKvHostOptions {
+  /** Capability checks (`kv-read` for `kv_get` / `kv_list`, `kv-write` for `kv_set`) */
+  sandbox?: WASMSandboxManager;
+  /** Key prefix of a module's entries (default `['wasm', moduleId]`) */
+  prefix?: (moduleId: string) => Deno.KvKey;
+}
+
+type KvExport = (...args: number[]) => number | bigint | void;
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+/**
+ * `Deno.Kv` backend of the `host-kv` imports, shared by any number of modules
+ */
+export class WASMKvHost {
+  private readonly modules = new Map<string, EchelonKvIndex>();
+
+  constructor(
+    private readonly kv: Deno.Kv,
+    private readonly options: WASMKvHostOptions = {},
+    private readonly memoryManager: WASMMemoryManager = new WASMMemoryManager(new EventEmitter()),
+  ) {}
+
+  /**
+   * Register the imports for `moduleId` with a host function registry
+   */
+  register(registry: WASMHostFunctionRegistry, moduleId: string): void {
+    // Imports return at once and complete later, so they are not async host functions
+    for (const [name, func] of Object.entries(this.imports(moduleId).env)) {
+      registry.register(
+        moduleId,
+        'env',
+        name,
+        (_ctx: HostFunctionContext, ...args: unknown[]) => func(...(args as number[])),
+        { params: Array(func.length).fill('i32'), results: [] },
+      );
+    }
+  }
+
+  /**
+   * `env` imports for `moduleId`, for `instantiateEchelonWASM(source, imports)`
+   */
+  imports(moduleId: string): { env: Record<string, KvExport> } {
+    return {
+      env: {
+        kv_get: (op: number, ptr: number, len: number) => {
+          const key = this.read(moduleId, ptr, len);
+          this.run(moduleId, op, 'kv-read', async () => {
+            const entry = await this.kv.get<Uint8Array>(this.key(moduleId, key));
+            return entry.value === null ? KvStatus.NotFound : entry.value;
+          });
+        },
+        kv_set: (op: number, ptr: number, len: number, valuePtr: number, valueLen: number) => {
+          const key = this.read(moduleId, ptr, len);
+          const value = this.memoryManager.readBytes(this.memory(moduleId), valuePtr, valueLen);
+          this.run(moduleId, op, 'kv-write', async () => {
+            await this.kv.set(this.key(moduleId, key), value);
+            return new Uint8Array(0);
+          });
+        },
+        kv_list: (op: number, ptr: number, len: number, limit: number) => {
+          const prefix = this.read(moduleId, ptr, len);
+          this.run(moduleId, op, 'kv-read', async () => {
+            const base = this.key(moduleId);
+            const entries = this.kv.list<Uint8Array>(
+              { start: [...base, prefix], end: [...base, `${prefix}\u{10ffff}`] },
+              { limit },
+            );
+            const items: Uint8Array[] = [];
+            for await (const entry of entries) {
+              items.push(encoder.encode(String(entry.key[entry.key.length - 1])), entry.value);
+            }
+            return lengthPrefixed(items);
+          });
+        },
+      },
+    };
+  }
+
+  /**
+   * Start serving an instantiated module; returns its word index
+   */
+  attach(moduleId: string, exports: WebAssembly.Exports): EchelonKvIndex {
+    const index = new EchelonKvIndex(exports, this.memoryManager);
+    this.modules.get(moduleId)?.close();
+    this.modules.set(moduleId, index);
+    return index;
+  }
+
+  /**
+   * Stop serving a module; later completions are dropped
+   */
+  detach(moduleId: string): void {
+    this.modules.get(moduleId)?.close();
+    this.modules.delete(moduleId);
+  }
+
+  private key(moduleId: string, key?: string): Deno.KvKey {
+    const base = this.options.prefix?.(moduleId) ?? ['wasm', moduleId];
+    return key === undefined ? base : [...base, key];
+  }
+
+  private memory(moduleId: string): WebAssembly.Memory {
+    const index = this.modules.get(moduleId);
+    if (!index) {
+      throw new Error(`WASM module not attached to the KV host: ${moduleId}`);
+    }
+    return index.memory;
+  }
+
+  private read(moduleId: string, ptr: number, len: number): string {
+    return this.memoryManager.readString(this.memory(moduleId), ptr, len);
+  }
+
+  /** Run an operation after the capability check and report its outcome */
+  private run(
+    moduleId: string,
+    op: number,
+    capability: 'kv-read' | 'kv-write',
+    operation: () => Promise<Uint8Array | KvStatus.NotFound>,
+  ): void {
+    const sandbox = this.options.sandbox;
+    if (sandbox && !sandbox.hasCapability(moduleId, capability)) {
+      try {
+        sandbox.enforceCapability(moduleId, capability);
+      } catch (error) {
+        // Completed after the import returns, like any other operation
+        queueMicrotask(() => this.complete(moduleId, op, KvStatus.Denied, errorBytes(error)));
+      }
+      return;
+    }
+
+    operation().then(
+      (result) =>
+        result === KvStatus.NotFound
+          ? this.complete(moduleId, op, KvStatus.NotFound, new Uint8Array(0))
+          : this.complete(moduleId, op, KvStatus.Ok, result),
+      (error) => this.complete(moduleId, op, KvStatus.Failed, errorBytes(error)),
+    );
+  }
+
+  private complete(moduleId: string, op: number, status: KvStatus, payload: Uint8Array): void {
+    this.modules.get(moduleId)?.complete(op, status, payload);
+  }
+}
+
+/**
+ * Word index of one `host-kv` instance, run as C ABI tasks
+ */
+export class EchelonKvIndex {
+  private readonly pending = new Map<number, {
+    fn: string;
+    resolve: (bytes: Uint8Array) => void;
+    reject: (error: Error) => void;
+  }>();
+
+  constructor(
+    private readonly exports: WebAssembly.Exports,
+    private readonly memoryManager: WASMMemoryManager = new WASMMemoryManager(new EventEmitter()),
+  ) {
+    if (typeof exports.echelon_kv_complete !== 'function') {
+      throw new Error('echelon_wasm build without the host-kv feature');
+    }
+  }
+
+  get memory(): WebAssembly.Memory {
+    return this.exports.memory as WebAssembly.Memory;
+  }
+
+  /** Index the words of `text` under `docId`, resolves to the number of distinct words */
+  async indexDocument(docId: string, text: string): Promise<number> {
+    const bytes = await this.start('index_document', [encoder.encode(docId), encoder.encode(text)]);
+    return new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength).getUint32(0, true);
+  }
+
+  /** Documents containing every word of `query` */
+  async searchIndex(query: string): Promise<string[]> {
+    return lines(await this.start('search_index', [encoder.encode(query)]));
+  }
+
+  /** Up to `limit` indexed words starting with `prefix` */
+  async indexedWords(prefix: string, limit = 100): Promise<string[]> {
+    return lines(await this.start('indexed_words', [encoder.encode(prefix)], limit));
+  }
+
+  /** Hand an operation's outcome to the module and settle finished tasks */
+  complete(op: number, status: KvStatus, payload: Uint8Array): void {
+    const ptr = payload.length > 0 ? this.alloc('kv_complete', payload.length) : 0;
+    try {
+      if (ptr !== 0) this.memoryManager.writeBytes(this.memory, ptr, payload);
+      this.fn('echelon_kv_complete')(op, status, ptr, payload.length);
+    } finally {
+      if (ptr !== 0) this.fn('echelon_dealloc')(ptr);
+    }
+    this.settle();
+  }
+
+  /** Cancel every running task, rejecting its Promise */
+  close(): void {
+    for (const [task, { fn, reject }] of this.pending) {
+      this.fn('echelon_task_cancel')(task);
+      reject(new Error(`${fn}: cancelled`));
+    }
+    this.pending.clear();
+  }
+
+  private start(fn: string, inputs: Uint8Array[], ...args: number[]): Promise<Uint8Array> {
+    const ptrs: number[] = [];
+    let task = 0;
+    try {
+      for (const input of inputs) {
+        const ptr = this.alloc(fn, input.length);
+        ptrs.push(ptr);
+        this.memoryManager.writeBytes(this.memory, ptr, input);
+      }
+      const pointers = inputs.flatMap((input, i) => [ptrs[i], input.length]);
+      task = this.check(fn, Number(this.fn(`echelon_${fn}`)(...pointers, ...args)));
+    } catch (error) {
+      return Promise.reject(error);
+    } finally {
+      for (const ptr of ptrs) this.fn('echelon_dealloc')(ptr);
+    }
+
+    const result = new Promise<Uint8Array>((resolve, reject) => this.pending.set(task, { fn, resolve, reject }));
+    // Finished without waiting when every operation completed synchronously
+    this.settle();
+    return result;
+  }
+
+  /** Take the results of finished tasks */
+  private settle(): void {
+    for (const [task, { fn, resolve, reject }] of this.pending) {
+      if (Number(this.fn('echelon_task_state')(task)) === 1) continue;
+      this.pending.delete(task);
+      try {
+        const packed = BigInt.asUintN(64, BigInt(this.fn('echelon_task_take')(task) as number | bigint));
+        resolve(this.check(fn, this.take(packed)));
+      } catch (error) {
+        reject(error as Error);
+      }
+    }
+  }
+
+  private alloc(fn: string, len: number): number {
+    const ptr = Number(this.fn('echelon_alloc')(len));
+    if (ptr === 0) {
+      throw new EchelonCallError(fn, 4, 'cannot allocate input buffer');
+    }
+    return ptr;
+  }
+
+  private take(packed: bigint): Uint8Array {
+    const ptr = Number(packed >> 32n);
+    if (ptr === 0) return new Uint8Array(0);
+    const bytes = this.memoryManager.readBytes(this.memory, ptr, Number(packed & 0xffffffffn));
+    this.fn('echelon_dealloc')(ptr);
+    return bytes;
+  }
+
+  private check<T>(fn: string, value: T): T {
+    const code = Number(this.fn('echelon_last_error_code')());
+    if (code === 0) return value;
+
+    const packed = BigInt.asUintN(64, BigInt(this.fn('echelon_last_error_message')() as number | bigint));
+    const message = packed === 0n ? 'unknown error' : decoder.decode(this.take(packed));
+    throw new EchelonCallError(fn, code, message);
+  }
+
+  private fn(name: string): KvExport {
+    const fn = this.exports[name];
+    if (typeof fn !== 'function') {
+      throw new Error(`echelon_wasm export not available in this build: ${name}`);
+    }
+    return fn as KvExport;
+  }
+}
+
+/** `u32` LE length-prefixed concatenation, the `kv_list` payload */
+function lengthPrefixed(items: Uint8Array[]): Uint8Array {
+  const out = new Uint8Array(items.reduce((sum, item) => sum + 4 + item.length, 0));
+  const view = new DataView(out.buffer);
+  let offset = 0;
+  for (const item of items) {
+    view.setUint32(offset, item.length, true);
+    out.set(item, offset + 4);
+    offset += 4 + item.length;
+  }
+  return out;
+}
+
+function lines(bytes: Uint8Array): string[] {
+  return bytes.length === 0 ? [] : decoder.decode(bytes).split('\n');
+}
+
+function errorBytes(error: unknown): Uint8Array {
+  return encoder.encode(error instanceof Error ? error.message : String(error));
+}
@@ -6,6 +6,7 @@
  */
 
 import type {
+  WASMHeapStats,
   WASMMemoryConfig,
   WASMMemoryStats,
   WASMModuleMemoryStats,
@@ -21,6 +22,7 @@ import { EventEmitter } from '../plugin/events.ts';
 export class WASMMemoryManager {
   private memories: Map<string, WebAssembly.Memory> = new Map();
   private moduleStats: Map<string, WASMModuleMemoryStats> = new Map();
+  private heapStats: Map<string, WASMHeapStats> = new Map();
   private globalLimit: number;
   private moduleLimits: Map<string, number> = new Map();
   private events: EventEmitter;
@@ -187,6 +189,7 @@ export class WASMMemoryManager {
 
       this.moduleStats.delete(moduleId);
     }
+    this.heapStats.delete(moduleId);
   }
 
   /**
@@ -241,6 +244,16 @@ export class WASMMemoryManager {
     return encoded.length;
   }
 
+  /**
+   * Encode a string straight into a region of WASM memory (no intermediate copy)
+   * Returns the number of bytes written, or -1 if it does not fit in `capacity` bytes
+   */
+  writeStringInto(memory: WebAssembly.Memory, ptr: number, capacity: number, str: string): number {
+    const view = new Uint8Array(memory.buffer, ptr, capacity);
+    const { read, written } = new TextEncoder().encodeInto(str, view);
+    return read === str.length ? written : -1;
+  }
+
   /**
    * Write a null-terminated string to WASM memory
    */
@@ -258,6 +271,14 @@ export class WASMMemoryManager {
     return new Uint8Array(memory.buffer, ptr, len).slice();
   }
 
+  /**
+   * View bytes of WASM memory without copying
+   * The view is detached when the memory grows
+   */
+  viewBytes(memory: WebAssembly.Memory, ptr: number, len: number): Uint8Array {
+    return new Uint8Array(memory.buffer, ptr, len);
+  }
+
   /**
    * Write bytes to WASM memory
    */
@@ -337,13 +358,15 @@ export class WASMMemoryManager {
     if (moduleId) {
       const stats = this.moduleStats.get(moduleId);
       const memory = this.memories.get(moduleId);
+      const heap = this.heapStats.get(moduleId);
 
       if (!stats || !memory) {
         return {
           allocated: 0,
-          used: 0,
+          used: heap?.liveBytes ?? 0,
           available: 0,
           pageCount: 0,
+          heap: heap ? { ...heap } : undefined,
         };
       }
 
@@ -356,13 +379,15 @@ export class WASMMemoryManager {
         available: (limit || this.globalLimit) - stats.allocated,
         pageCount,
         maxPages: limit ? Math.floor(limit / WASMMemoryManager.PAGE_SIZE) : undefined,
+        heap: heap ? { ...heap } : undefined,
       };
     }
 
     // Global stats
     const allModuleStats = new Map<string, WASMModuleMemoryStats>();
     for (const [id, stats] of this.moduleStats) {
-      allModuleStats.set(id, { ...stats });
+      const heap = this.heapStats.get(id);
+      allModuleStats.set(id, { ...stats, heap: heap ? { ...heap } : undefined });
     }
 
     let totalPages = 0;
@@ -377,7 +402,73 @@ export class WASMMemoryManager {
       pageCount: totalPages,
       maxPages: Math.floor(this.globalLimit / WASMMemoryManager.PAGE_SIZE),
       moduleStats: allModuleStats,
+      heap: this.heapStats.size > 0 ? this.totalHeapStats() : undefined,
+    };
+  }
+
+  /**
+   * Read allocator statistics from a module's exports.
+   *
+   * Modules with an instrumented allocator (e.g. `echelon_wasm` built with
+   * `heap-stats`) export `echelon_heap_*` counters. These reflect real heap
+   * usage inside linear memory, which page counts cannot show. The sample
+   * becomes the module's `used` / `peakUsage`. Returns undefined if the
+   * module has no such exports.
+   */
+  sampleHeap(moduleId: string, exports: WebAssembly.Exports): WASMHeapStats | undefined {
+    const read = (name: string): number | undefined => {
+      const fn = exports[`echelon_heap_${name}`];
+      return typeof fn === 'function' ? Number((fn as () => number | bigint)()) : undefined;
     };
+
+    const liveBytes = read('live_bytes');
+    if (liveBytes === undefined) return undefined;
+
+    const heap: WASMHeapStats = {
+      liveBytes,
+      peakBytes: read('peak_bytes') ?? liveBytes,
+      allocations: read('allocations') ?? 0,
+      deallocations: read('deallocations') ?? 0,
+      reallocations: read('reallocations') ?? 0,
+      heapSize: read('size') ?? 0,
+      fragmentation: read('fragmentation') ?? 0,
+    };
+    this.heapStats.set(moduleId, heap);
+
+    const stats = this.moduleStats.get(moduleId);
+    if (stats) {
+      stats.used = heap.liveBytes;
+      stats.peakUsage = Math.max(stats.peakUsage, heap.peakBytes);
+    }
+
+    return heap;
+  }
+
+  /**
+   * Sum of the last heap samples across modules
+   */
+  private totalHeapStats(): WASMHeapStats {
+    const total: WASMHeapStats = {
+      liveBytes: 0,
+      peakBytes: 0,
+      allocations: 0,
+      deallocations: 0,
+      reallocations: 0,
+      heapSize: 0,
+      fragmentation: 0,
+    };
+    for (const heap of this.heapStats.values()) {
+      total.liveBytes += heap.liveBytes;
+      total.peakBytes += heap.peakBytes;
+      total.allocations += heap.allocations;
+      total.deallocations += heap.deallocations;
+      total.reallocations += heap.reallocations;
+      total.heapSize += heap.heapSize;
+    }
+    total.fragmentation = total.heapSize > 0
+      ? Math.max(0, total.heapSize - total.liveBytes) / total.heapSize
+      : 0;
+    return total;
   }
 
   /**
@@ -423,6 +514,7 @@ export class WASMMemoryManager {
     }
     this.memories.clear();
     this.moduleStats.clear();
+    this.heapStats.clear();
     this.moduleLimits.clear();
     this.totalAllocated = 0;
   }
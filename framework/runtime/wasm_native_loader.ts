@@ -17,6 +17,11 @@
  * const registry = new NativeWASMRegistry();
  * await registry.register('math', './math.wasm');
  * const result = registry.call<number>('math', 'add', 1, 2);
+ *
+ * // Refuse builds whose exports changed behaviour
+ * await registry.register('strings', './string_utils.wasm', {
+ *   compatibility: { abiVersion: 1, requires: ['hash.djb2'] },
+ * });
  * ```
  */
 
@@ -37,6 +42,53 @@ export interface NativeWASMModule {
   registeredAt: Date;
   /** Number of times call() has been invoked */
   callCount: number;
+  /** ABI negotiation exports, `null` for modules without them */
+  abi: WASMABIInfo | null;
+}
+
+/**
+ * What a module reports through `abi_version()` / `supports(name)`
+ */
+export interface WASMABIInfo {
+  /** ABI contract version; bumped when existing exports change behaviour */
+  version: number;
+  /** Whether the build enables a cargo feature or versioned contract (e.g. `hash.djb2`) */
+  supports(name: string): boolean;
+}
+
+/**
+ * Which module builds a host accepts
+ */
+export interface WASMCompatibilityPolicy {
+  /** Accepted ABI versions: exact, or an inclusive range */
+  abiVersion?: number | { min?: number; max?: number };
+  /** Features or contracts the module must support */
+  requires?: string[];
+  /** Accept modules without `abi_version` (default: false) */
+  allowUnversioned?: boolean;
+  /** `reject` (default) throws and keeps the current registration; `warn` registers anyway */
+  onIncompatible?: 'reject' | 'warn';
+}
+
+/**
+ * Options for `NativeWASMRegistry.register`
+ */
+export interface NativeWASMRegisterOptions {
+  /** Overrides the registry's default policy (`null` disables the check) */
+  compatibility?: WASMCompatibilityPolicy | null;
+}
+
+/**
+ * Raised when a module does not satisfy the compatibility policy
+ */
+export class WASMIncompatibleModuleError extends Error {
+  constructor(
+    public readonly specifier: string,
+    public readonly issues: string[]
+  ) {
+    super(`Incompatible WASM module '${specifier}': ${issues.join('; ')}`);
+    this.name = 'WASMIncompatibleModuleError';
+  }
 }
 
 /**
@@ -47,6 +99,8 @@ export interface NativeWASMRegistryConfig {
   events?: EventEmitter;
   /** Whether to log debug information */
   debug?: boolean;
+  /** Default compatibility policy for `register` (default: none) */
+  compatibility?: WASMCompatibilityPolicy;
 }
 
 /**
@@ -55,6 +109,7 @@ export interface NativeWASMRegistryConfig {
 export const NativeWASMEvents = {
   MODULE_REGISTERED: 'native_wasm:module_registered',
   MODULE_UNREGISTERED: 'native_wasm:module_unregistered',
+  MODULE_INCOMPATIBLE: 'native_wasm:module_incompatible',
   CALL_START: 'native_wasm:call_start',
   CALL_COMPLETE: 'native_wasm:call_complete',
   CALL_ERROR: 'native_wasm:call_error',
@@ -77,11 +132,13 @@ export async function loadNativeWASM(specifier: string): Promise<NativeWASMModul
     // Dynamic import of WASM module - Deno handles compilation
     const module = await import(specifier);
 
+    const exports = module as Record<string, unknown>;
     return {
       specifier,
-      exports: module as Record<string, unknown>,
+      exports,
       registeredAt: new Date(),
       callCount: 0,
+      abi: readWASMABI(exports),
     };
   } catch (error) {
     throw new Error(
@@ -90,6 +147,77 @@ export async function loadNativeWASM(specifier: string): Promise<NativeWASMModul
   }
 }
 
+/**
+ * Read `abi_version()` / `supports(name)` from module exports.
+ *
+ * Raw modules are queried through the C ABI (`echelon_abi_version`,
+ * `echelon_supports` with an `echelon_alloc` input buffer); wasm-bindgen
+ * glue modules through `abi_version` / `supports`. Returns `null` if the
+ * module has neither.
+ */
+export function readWASMABI(exports: Record<string, unknown>): WASMABIInfo | null {
+  type Fn = (...args: unknown[]) => unknown;
+  const fn = (name: string) => (typeof exports[name] === 'function' ? exports[name] as Fn : undefined);
+
+  const rawVersion = fn('echelon_abi_version');
+  if (rawVersion) {
+    const rawSupports = fn('echelon_supports');
+    const alloc = fn('echelon_alloc');
+    const dealloc = fn('echelon_dealloc');
+    const memory = exports.memory;
+    return {
+      version: Number(rawVersion()),
+      supports: (name) => {
+        if (!rawSupports || !alloc || !dealloc || !(memory instanceof WebAssembly.Memory)) return false;
+        const bytes = new TextEncoder().encode(name);
+        const ptr = Number(alloc(bytes.length));
+        if (ptr === 0) return false;
+        try {
+          new Uint8Array(memory.buffer, ptr, bytes.length).set(bytes);
+          return Number(rawSupports(ptr, bytes.length)) === 1;
+        } finally {
+          dealloc(ptr);
+        }
+      },
+    };
+  }
+
+  const version = fn('abi_version');
+  if (version) {
+    const supports = fn('supports');
+    return {
+      version: Number(version()),
+      supports: (name) => supports?.(name) === true,
+    };
+  }
+
+  return null;
+}
+
+/**
+ * Problems with `abi` under `policy` (empty when compatible)
+ */
+export function checkWASMCompatibility(abi: WASMABIInfo | null, policy: WASMCompatibilityPolicy): string[] {
+  if (abi === null) {
+    return policy.allowUnversioned ? [] : ['module does not export abi_version'];
+  }
+
+  const issues: string[] = [];
+  const range = typeof policy.abiVersion === 'number'
+    ? { min: policy.abiVersion, max: policy.abiVersion }
+    : policy.abiVersion ?? {};
+  if ((range.min !== undefined && abi.version < range.min) || (range.max !== undefined && abi.version > range.max)) {
+    const expected = range.min === range.max ? `${range.min}` : `${range.min ?? '*'}..${range.max ?? '*'}`;
+    issues.push(`ABI version ${abi.version} is not supported (expected ${expected})`);
+  }
+  for (const name of policy.requires ?? []) {
+    if (!abi.supports(name)) {
+      issues.push(`missing '${name}'`);
+    }
+  }
+  return issues;
+}
+
 /**
  * Registry for native WASM modules loaded via Deno's import system.
  *
@@ -117,10 +245,12 @@ export class NativeWASMRegistry {
   private modules: Map<string, NativeWASMModule> = new Map();
   private events?: EventEmitter;
   private debug: boolean;
+  private compatibility?: WASMCompatibilityPolicy;
 
   constructor(config: NativeWASMRegistryConfig = {}) {
     this.events = config.events;
     this.debug = config.debug ?? false;
+    this.compatibility = config.compatibility;
   }
 
   /**
@@ -132,23 +262,46 @@ export class NativeWASMRegistry {
    * - An absolute path: `/path/to/module.wasm`
    * - An import map alias: `@wasm/module` (requires deno.json config)
    * - A URL: `https://example.com/module.wasm`
+   *
+   * With a compatibility policy the module's `abi_version()` and
+   * `supports(name)` are checked first; a rejected module throws
+   * `WASMIncompatibleModuleError` and leaves the current registration
+   * in place.
    */
-  async register(alias: string, specifier: string): Promise<void> {
-    if (this.modules.has(alias)) {
-      logger.warn(`Native WASM module '${alias}' already registered, replacing`);
-    }
-
+  async register(alias: string, specifier: string, options: NativeWASMRegisterOptions = {}): Promise<void> {
     if (this.debug) {
       logger.debug(`Registering native WASM module: ${alias} -> ${specifier}`);
     }
 
     const module = await loadNativeWASM(specifier);
+
+    const policy = options.compatibility === undefined ? this.compatibility : options.compatibility;
+    const issues = policy ? checkWASMCompatibility(module.abi, policy) : [];
+    if (policy && issues.length > 0) {
+      const rejected = (policy.onIncompatible ?? 'reject') === 'reject';
+      this.events?.emit(NativeWASMEvents.MODULE_INCOMPATIBLE, {
+        alias,
+        specifier,
+        abiVersion: module.abi?.version ?? null,
+        issues,
+        rejected,
+      });
+      if (rejected) {
+        throw new WASMIncompatibleModuleError(specifier, issues);
+      }
+      logger.warn(`Native WASM module '${alias}' is incompatible, registering anyway`, { issues });
+    }
+
+    if (this.modules.has(alias)) {
+      logger.warn(`Native WASM module '${alias}' already registered, replacing`);
+    }
     this.modules.set(alias, module);
 
     this.events?.emit(NativeWASMEvents.MODULE_REGISTERED, {
       alias,
       specifier,
       exports: Object.keys(module.exports),
+      abiVersion: module.abi?.version ?? null,
     });
 
     if (this.debug) {
@@ -183,6 +336,21 @@ export class NativeWASMRegistry {
     return this.modules.has(alias);
   }
 
+  /**
+   * ABI version of a registered module (`null` if it does not report one)
+   */
+  getABIVersion(alias: string): number | null {
+    return this.modules.get(alias)?.abi?.version ?? null;
+  }
+
+  /**
+   * Whether a registered module reports a feature or contract, for hosts
+   * that adapt to a build instead of refusing it
+   */
+  supports(alias: string, name: string): boolean {
+    return this.modules.get(alias)?.abi?.supports(name) ?? false;
+  }
+
   /**
    * Get all registered module aliases
    */
@@ -289,6 +457,7 @@ export class NativeWASMRegistry {
       exportCount: number;
       callCount: number;
       registeredAt: Date;
+      abiVersion: number | null;
     }>;
   } {
     const modules = Array.from(this.modules.entries()).map(([alias, module]) => ({
@@ -297,6 +466,7 @@ export class NativeWASMRegistry {
       exportCount: Object.keys(module.exports).length,
       callCount: module.callCount,
       registeredAt: module.registeredAt,
+      abiVersion: module.abi?.version ?? null,
     }));
 
     return {
@@ -20,9 +20,15 @@ import type {
 import { WASMEvents, DEFAULT_WASM_CAPABILITIES } from './wasm_types.ts';
 import { WASMModuleLoader, type WASMLoaderOptions } from './wasm_module_loader.ts';
 import { WASMExecutor, type ImportConfig } from './wasm_executor.ts';
+import type { DeterministicHostOptions, HostEntropySource } from './wasm_host_functions.ts';
 import { WASMMemoryManager } from './wasm_memory.ts';
 import { WASMSandboxManager } from './wasm_sandbox.ts';
-import { NativeWASMRegistry, type NativeWASMModule } from './wasm_native_loader.ts';
+import {
+  NativeWASMRegistry,
+  type NativeWASMModule,
+  type NativeWASMRegisterOptions,
+  type WASMCompatibilityPolicy,
+} from './wasm_native_loader.ts';
 import { EventEmitter } from '../plugin/events.ts';
 import { Lifecycle } from './lifecycle.ts';
 import { getLogger } from '../telemetry/logger.ts';
@@ -83,6 +89,20 @@ export interface WASMRuntimeConfig {
    * @default true
    */
   enableHostFunctionRegistry?: boolean;
+
+  /**
+   * Deterministic mode: seed the host RNG and freeze the host clocks seen by
+   * modules, so the same inputs always give the same outputs.
+   * @default null (system RNG and clocks)
+   */
+  deterministic?: DeterministicHostOptions | null;
+
+  /**
+   * Default compatibility policy for modules registered through the native
+   * registry: accepted `abi_version()` range and required `supports()` names.
+   * @default null (no check)
+   */
+  nativeCompatibility?: WASMCompatibilityPolicy | null;
 }
 
 /**
@@ -154,6 +174,8 @@ export class WASMRuntimeCore {
       // WASI and host function registry
       enableWASI: config.enableWASI ?? true,
       enableHostFunctionRegistry: config.enableHostFunctionRegistry ?? true,
+      deterministic: config.deterministic ?? null,
+      nativeCompatibility: config.nativeCompatibility ?? null,
     };
 
     this.metricsEnabled = this.config.enableMetrics;
@@ -170,12 +192,17 @@ export class WASMRuntimeCore {
     this.executor = new WASMExecutor(events, this.memoryManager, {
       enableWASI: this.config.enableWASI,
       enableHostFunctionRegistry: this.config.enableHostFunctionRegistry,
+      deterministic: this.config.deterministic ?? undefined,
     });
     this.sandboxManager = new WASMSandboxManager(events, this.memoryManager);
 
     // Initialize native registry if enabled (Deno 2.1+ feature)
     if (this.config.enableNativeImports) {
-      this.nativeRegistry = new NativeWASMRegistry({ events, debug: false });
+      this.nativeRegistry = new NativeWASMRegistry({
+        events,
+        debug: false,
+        compatibility: this.config.nativeCompatibility ?? undefined,
+      });
       logger.debug('Native WASM imports enabled');
     }
 
@@ -553,6 +580,16 @@ export class WASMRuntimeCore {
     };
   }
 
+  /**
+   * Get the RNG and clock source behind the host imports
+   *
+   * In deterministic mode this is a `DeterministicHostSource`; `advance` it to
+   * move the clocks modules see.
+   */
+  getEntropySource(): HostEntropySource {
+    return this.executor.getEntropySource();
+  }
+
   /**
    * Build import object for module instantiation
    */
@@ -652,13 +689,17 @@ export class WASMRuntimeCore {
    * const result = runtime.callNative<number>('math', 'add', 1, 2);
    * ```
    */
-  async registerNativeModule(alias: string, specifier: string): Promise<void> {
+  async registerNativeModule(
+    alias: string,
+    specifier: string,
+    options?: NativeWASMRegisterOptions
+  ): Promise<void> {
     if (!this.nativeRegistry) {
       throw new Error(
         'Native WASM imports are not enabled. Set enableNativeImports: true in config.'
       );
     }
-    await this.nativeRegistry.register(alias, specifier);
+    await this.nativeRegistry.register(alias, specifier, options);
   }
 
   /**
@@ -0,0 +1,158 @@
+/**
+ * echelon_wasm Shared Buffers
+ *
+ * Zero-copy string calls for large payloads on `string_utils.wasm` builds
+ * with the `shared-buffers` feature (see `src/shared.rs`). Instead of an
+ * `echelon_alloc` block per call, the module keeps one input and one output
+ * region that are reused across calls:
+ *
+ * - Strings are UTF-8 encoded straight into the input region with
+ *   `WASMMemoryManager.writeStringInto`; byte inputs are written as they are.
+ * - `echelon_<fn>_shared(len)` reads the region in place.
+ * - String results land in the output region and are decoded from there,
+ *   or viewed without copying with `viewOutput`.
+ *
+ * Failures are read from the last-error slot and thrown as
+ * `EchelonCallError`, as in `wasm_ffi.ts`.
+ *
+ * @example
+ * ```typescript
+ * const { exports } = await instantiateEchelonWASM('./wasm_modules/string_utils.wasm');
+ * const shared = new EchelonSharedBuffers(exports);
+ * shared.hash_string(largeBody);
+ * shared.reverse_string(largeBody);
+ * ```
+ */
+
+import { EventEmitter } from '../plugin/events.ts';
+import { EchelonCallError } from './wasm_ffi.ts';
+import { WASMMemoryManager } from './wasm_memory.ts';
+
+type SharedExport = (...args: number[]) => number | bigint;
+
+/**
+ * Input/output regions of one `string_utils.wasm` instance
+ */
+export class EchelonSharedBuffers {
+  constructor(
+    private readonly exports: WebAssembly.Exports,
+    private readonly memoryManager: WASMMemoryManager = new WASMMemoryManager(new EventEmitter()),
+  ) {
+    if (!EchelonSharedBuffers.isSupported(exports)) {
+      throw new Error('echelon_wasm build without the shared-buffers feature');
+    }
+  }
+
+  /** Whether the instance exports the shared buffer ABI */
+  static isSupported(exports: WebAssembly.Exports): boolean {
+    return typeof exports.echelon_input_buffer === 'function' && exports.memory instanceof WebAssembly.Memory;
+  }
+
+  /**
+   * Write an input into the input region, growing it if needed.
+   * Returns its length in bytes.
+   */
+  write(input: string | Uint8Array): number {
+    if (typeof input !== 'string') {
+      this.memoryManager.writeBytes(this.memory, this.reserve(input.length), input);
+      return input.length;
+    }
+
+    // Sized for ASCII first, then for the UTF-8 worst case of 3 bytes per UTF-16 unit
+    const written = this.encode(input, input.length);
+    return written >= 0 ? written : this.encode(input, input.length * 3);
+  }
+
+  /** Decode the first `len` bytes of the output region */
+  readOutput(len: number): string {
+    return this.memoryManager.readString(this.memory, this.outputPtr(), len);
+  }
+
+  /** View the first `len` bytes of the output region (valid until the next call) */
+  viewOutput(len: number): Uint8Array {
+    return this.memoryManager.viewBytes(this.memory, this.outputPtr(), len);
+  }
+
+  /** Bytes the input region can hold */
+  get inputCapacity(): number {
+    return Number(this.fn('echelon_input_capacity')());
+  }
+
+  count_vowels(input: string | Uint8Array): number {
+    return this.run('count_vowels', input);
+  }
+
+  reverse_string(input: string | Uint8Array): string {
+    return this.readOutput(this.run('reverse_string', input));
+  }
+
+  is_palindrome(input: string | Uint8Array): boolean {
+    return this.run('is_palindrome', input) !== 0;
+  }
+
+  hash_string(input: string | Uint8Array): number {
+    return this.run('hash_string', input) >>> 0;
+  }
+
+  longest_word_length(input: string | Uint8Array): number {
+    return this.run('longest_word_length', input);
+  }
+
+  word_count(input: string | Uint8Array): number {
+    return this.run('word_count', input);
+  }
+
+  caesar_encrypt(input: string | Uint8Array, shift: number): string {
+    return this.readOutput(this.run('caesar_encrypt', input, shift & 0xff));
+  }
+
+  /** Free both regions; later calls allocate them again */
+  release(): void {
+    this.fn('echelon_release_shared_buffers')();
+  }
+
+  private get memory(): WebAssembly.Memory {
+    return this.exports.memory as WebAssembly.Memory;
+  }
+
+  private run(fn: string, input: string | Uint8Array, ...args: number[]): number {
+    const len = this.write(input);
+    return this.check(fn, Number(this.fn(`echelon_${fn}_shared`)(len, ...args)));
+  }
+
+  /** Encode into a region of at least `capacity` bytes, -1 if it does not fit */
+  private encode(input: string, capacity: number): number {
+    const ptr = this.reserve(capacity);
+    return this.memoryManager.writeStringInto(this.memory, ptr, this.inputCapacity, input);
+  }
+
+  private reserve(capacity: number): number {
+    const ptr = Number(this.fn('echelon_input_buffer')(capacity));
+    return ptr === 0 ? this.check('input_buffer', ptr) : ptr;
+  }
+
+  private outputPtr(): number {
+    return Number(this.fn('echelon_output_buffer')());
+  }
+
+  private check<T>(fn: string, value: T): T {
+    const code = Number(this.fn('echelon_last_error_code')());
+    if (code === 0) return value;
+
+    const packed = BigInt.asUintN(64, BigInt(this.fn('echelon_last_error_message')()));
+    const ptr = Number(packed >> 32n);
+    const message = ptr === 0
+      ? 'unknown error'
+      : this.memoryManager.readString(this.memory, ptr, Number(packed & 0xffffffffn));
+    if (ptr !== 0) this.fn('echelon_dealloc')(ptr);
+    throw new EchelonCallError(fn, code, message);
+  }
+
+  private fn(name: string): SharedExport {
+    const fn = this.exports[name];
+    if (typeof fn !== 'function') {
+      throw new Error(`echelon_wasm export not available in this build: ${name}`);
+    }
+    return fn as SharedExport;
+  }
+}
@@ -0,0 +1,374 @@
+/**
+ * WASM Thread Pool
+ *
+ * Runs echelon_wasm batch operations across Deno workers that share one
+ * `WebAssembly.Memory`. Built for the threads artifact
+ * (`THREADS=1 ./build.sh` → `string_utils_threads.wasm`), which imports a
+ * shared memory and exports `__wasm_init_tls` so every worker gets its own
+ * stack and thread-local state.
+ *
+ * A batch is copied in once, split into a `ParallelJob` by
+ * `echelon_par_begin`, and then drained by every worker plus the calling
+ * thread through `echelon_par_work`. Results come back in batch order and
+ * match the sequential `hash_strings` / `vowel_counts` / ... exports.
+ *
+ * Modules without the threads exports (e.g. the default `string_utils.wasm`)
+ * still work: the pool starts no workers and runs jobs on the calling thread.
+ *
+ * @example
+ * ```typescript
+ * const pool = await WASMThreadPool.create('./wasm_modules/string_utils_threads.wasm', {
+ *   threads: 4,
+ * });
+ * const hashes = await pool.run(EchelonBatchOp.HashStrings, lines);
+ * pool.terminate();
+ * ```
+ */
+
+import { getLogger } from '../telemetry/logger.ts';
+import { EchelonCallError } from './wasm_ffi.ts';
+import { entropyImports } from './wasm_host_functions.ts';
+
+const logger = getLogger();
+
+/** Batch operations (matches `BatchOp` in `src/parallel.rs`) */
+export const EchelonBatchOp = {
+  HashStrings: 0,
+  VowelCounts: 1,
+  WordCounts: 2,
+  LongestWordLengths: 3,
+  PalindromeFlags: 4,
+} as const;
+
+export type EchelonBatchOp = typeof EchelonBatchOp[keyof typeof EchelonBatchOp];
+
+/** Results per item: `u32`s, or one flag byte per item for `PalindromeFlags` */
+export type EchelonBatchResult = Uint32Array | Uint8Array;
+
+/**
+ * Thread pool options
+ */
+export interface WASMThreadPoolOptions {
+  /** Workers to start besides the calling thread (default: hardware threads - 1) */
+  threads?: number;
+
+  /**
+   * Shared memory to run in, e.g. the `memory` of a sandbox created with the
+   * `threads` capability. Created from the module's memory import otherwise.
+   */
+  memory?: WebAssembly.Memory;
+
+  /** Stack size per worker in bytes (default: 1MB, the Rust wasm32 default) */
+  stackSize?: number;
+}
+
+type PoolExports = Record<string, unknown> & {
+  echelon_alloc(size: number): number;
+  echelon_dealloc(ptr: number): void;
+  echelon_last_error_code(): number;
+  echelon_last_error_message(): bigint;
+  echelon_par_begin(op: number, ptr: number, len: number): number;
+  echelon_par_work(job: number): number;
+  echelon_par_finish(job: number): bigint;
+  echelon_par_cancel(job: number): void;
+  __tls_size?: WebAssembly.Global;
+  __tls_align?: WebAssembly.Global;
+  __wasm_init_tls?: (tls: number) => void;
+};
+
+interface PoolWorker {
+  worker: Worker;
+  /** Pending replies, resolved in order */
+  waiting: Array<{ resolve: () => void; reject: (error: Error) => void }>;
+}
+
+const DEFAULT_STACK_SIZE = 1024 * 1024;
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+/**
+ * Pool of workers sharing one echelon_wasm instance's memory
+ */
+export class WASMThreadPool {
+  private constructor(
+    private readonly exports: PoolExports,
+    readonly memory: WebAssembly.Memory,
+    private readonly workers: PoolWorker[]
+  ) {}
+
+  /**
+   * Compile the module, instantiate it on the calling thread and start the
+   * workers. Without a shared memory import the pool has no workers.
+   */
+  static async create(
+    source: string | URL | BufferSource,
+    options: WASMThreadPoolOptions = {}
+  ): Promise<WASMThreadPool> {
+    const bytes = typeof source === 'string' || source instanceof URL
+      ? await Deno.readFile(source)
+      : source;
+    const module = await WebAssembly.compile(bytes);
+    const limits = memoryImportLimits(bytes);
+
+    let memory = options.memory;
+    if (!memory && limits?.shared) {
+      memory = new WebAssembly.Memory({
+        initial: limits.initial,
+        maximum: limits.maximum ?? limits.initial,
+        shared: true,
+      });
+    }
+
+    const entropy = entropyImports();
+    const imports: WebAssembly.Imports = {};
+    for (const { module: ns, name, kind } of WebAssembly.Module.imports(module)) {
+      if (kind !== 'function') continue;
+      // host-entropy builds need a real RNG and clock; the rest are never reached
+      (imports[ns] ??= {})[name] = (ns === 'env' && entropy[name]) || (() => 0);
+    }
+    if (limits && memory) {
+      (imports.env ??= {}).memory = memory;
+    }
+
+    const instance = await WebAssembly.instantiate(module, imports);
+    const exports = instance.exports as PoolExports;
+    memory ??= exports.memory as WebAssembly.Memory;
+
+    const threaded = typeof exports.__wasm_init_tls === 'function' &&
+      memory.buffer instanceof SharedArrayBuffer;
+    if (!threaded) {
+      logger.debug('echelon_wasm module has no threads support, running jobs on the calling thread');
+      return new WASMThreadPool(exports, memory, []);
+    }
+
+    const count = options.threads ?? Math.max(0, (navigator.hardwareConcurrency ?? 1) - 1);
+    const pool = new WASMThreadPool(exports, memory, []);
+    try {
+      for (let i = 0; i < count; i++) {
+        pool.workers.push(await pool.spawn(module, options.stackSize ?? DEFAULT_STACK_SIZE));
+      }
+    } catch (error) {
+      pool.terminate();
+      throw error;
+    }
+
+    logger.debug(`Started echelon_wasm thread pool with ${count} workers`);
+    return pool;
+  }
+
+  /** Workers besides the calling thread */
+  get size(): number {
+    return this.workers.length;
+  }
+
+  /**
+   * Run `op` over every item, spread across the workers and this thread
+   */
+  async run(op: EchelonBatchOp, items: string[]): Promise<EchelonBatchResult> {
+    const job = this.begin(op, items);
+
+    const done = this.workers.map((w) => this.post(w, { type: 'work', job }));
+    this.exports.echelon_par_work(job);
+    const settled = await Promise.allSettled(done);
+
+    const failed = settled.find((r): r is PromiseRejectedResult => r.status === 'rejected');
+    if (failed) {
+      this.exports.echelon_par_cancel(job);
+      throw failed.reason;
+    }
+
+    const bytes = this.take(this.exports.echelon_par_finish(job));
+    this.check('echelon_par_finish');
+    if (op === EchelonBatchOp.PalindromeFlags) {
+      return bytes;
+    }
+    return new Uint32Array(bytes.buffer, bytes.byteOffset, bytes.byteLength / 4);
+  }
+
+  /**
+   * Stop the workers. Their stacks stay allocated since a worker may have
+   * been stopped mid-call.
+   */
+  terminate(): void {
+    for (const w of this.workers) {
+      w.worker.terminate();
+      for (const waiting of w.waiting.splice(0)) {
+        waiting.reject(new Error('WASM thread pool terminated'));
+      }
+    }
+    this.workers.length = 0;
+  }
+
+  private begin(op: EchelonBatchOp, items: string[]): number {
+    const encoded = items.map((item) => encoder.encode(item));
+    const total = encoded.reduce((sum, item) => sum + 4 + item.length, 4);
+
+    const ptr = this.exports.echelon_alloc(total);
+    if (ptr === 0) {
+      throw new EchelonCallError('echelon_par_begin', 4, 'cannot allocate input buffer');
+    }
+    try {
+      const view = new DataView(this.memory.buffer, ptr, total);
+      const target = new Uint8Array(this.memory.buffer, ptr, total);
+      view.setUint32(0, items.length, true);
+      let offset = 4;
+      for (const item of encoded) {
+        view.setUint32(offset, item.length, true);
+        target.set(item, offset + 4);
+        offset += 4 + item.length;
+      }
+      const job = this.exports.echelon_par_begin(op, ptr, total);
+      this.check('echelon_par_begin');
+      return job;
+    } finally {
+      this.exports.echelon_dealloc(ptr);
+    }
+  }
+
+  private async spawn(module: WebAssembly.Module, stackSize: number): Promise<PoolWorker> {
+    const tlsSize = Number(this.exports.__tls_size?.value ?? 0);
+    const tlsAlign = Math.max(Number(this.exports.__tls_align?.value ?? 1), 1);
+
+    const stack = this.exports.echelon_alloc(stackSize);
+    const tls = tlsSize > 0 ? this.exports.echelon_alloc(tlsSize + tlsAlign) : 0;
+    if (stack === 0 || (tlsSize > 0 && tls === 0)) {
+      throw new EchelonCallError('echelon_alloc', 4, 'cannot allocate worker stack');
+    }
+
+    const entry: PoolWorker = {
+      worker: new Worker(new URL('./wasm_thread_worker.ts', import.meta.url).href, { type: 'module' }),
+      waiting: [],
+    };
+    entry.worker.onmessage = (event: MessageEvent) => {
+      const waiting = entry.waiting.shift();
+      if (event.data?.type === 'error') {
+        waiting?.reject(new Error(`WASM thread failed: ${event.data.message}`));
+      } else {
+        waiting?.resolve();
+      }
+    };
+    entry.worker.onerror = (event: ErrorEvent) => {
+      event.preventDefault();
+      for (const waiting of entry.waiting.splice(0)) {
+        waiting.reject(new Error(`WASM thread crashed: ${event.message}`));
+      }
+    };
+
+    await this.post(entry, {
+      type: 'init',
+      module,
+      memory: this.memory,
+      stackTop: (stack + stackSize) & ~15,
+      tls: alignUp(tls, tlsAlign),
+    });
+    return entry;
+  }
+
+  private post(entry: PoolWorker, message: Record<string, unknown>): Promise<void> {
+    return new Promise((resolve, reject) => {
+      entry.waiting.push({ resolve, reject });
+      entry.worker.postMessage(message);
+    });
+  }
+
+  private check(fn: string): void {
+    const code = this.exports.echelon_last_error_code();
+    if (code !== 0) {
+      const message = decoder.decode(this.take(this.exports.echelon_last_error_message()));
+      throw new EchelonCallError(fn, code, message);
+    }
+  }
+
+  /** Copy out and free a packed `(ptr << 32) | len` block */
+  private take(packed: bigint): Uint8Array {
+    const value = BigInt.asUintN(64, packed);
+    const ptr = Number(value >> 32n);
+    const len = Number(value & 0xffffffffn);
+    if (ptr === 0) return new Uint8Array(0);
+
+    const bytes = new Uint8Array(this.memory.buffer, ptr, len).slice();
+    this.exports.echelon_dealloc(ptr);
+    return bytes;
+  }
+}
+
+function alignUp(value: number, align: number): number {
+  return Math.ceil(value / align) * align;
+}
+
+/**
+ * Limits of the module's `env.memory` import, or `undefined` if the memory
+ * is not imported. `WebAssembly.Module.imports` does not report them.
+ */
+export function memoryImportLimits(
+  source: BufferSource
+): { initial: number; maximum?: number; shared: boolean } | undefined {
+  const bytes = source instanceof Uint8Array
+    ? source
+    : ArrayBuffer.isView(source)
+    ? new Uint8Array(source.buffer, source.byteOffset, source.byteLength)
+    : new Uint8Array(source);
+  let offset = 8;
+
+  const u32 = (): number => {
+    let result = 0;
+    let shift = 0;
+    let byte: number;
+    do {
+      byte = bytes[offset++];
+      result += (byte & 0x7f) * 2 ** shift;
+      shift += 7;
+    } while (byte & 0x80);
+    return result;
+  };
+  const name = (): string => {
+    const len = u32();
+    offset += len;
+    return decoder.decode(bytes.subarray(offset - len, offset));
+  };
+
+  while (offset < bytes.length) {
+    const id = bytes[offset++];
+    const size = u32();
+    const end = offset + size;
+    if (id !== 2) {
+      offset = end;
+      continue;
+    }
+
+    for (let count = u32(); count > 0; count--) {
+      const ns = name();
+      const field = name();
+      const kind = bytes[offset++];
+      switch (kind) {
+        case 0: // function: type index
+          u32();
+          break;
+        case 1: { // table: reftype + limits
+          offset++;
+          const flags = u32();
+          u32();
+          if (flags & 1) u32();
+          break;
+        }
+        case 2: { // memory: limits
+          const flags = u32();
+          const initial = u32();
+          const maximum = flags & 1 ? u32() : undefined;
+          if (ns === 'env' && field === 'memory') {
+            return { initial, maximum, shared: (flags & 2) !== 0 };
+          }
+          break;
+        }
+        case 3: // global: valtype + mutability
+          offset += 2;
+          break;
+        default: // tag: attribute + type index
+          offset++;
+          u32();
+      }
+    }
+    return undefined;
+  }
+  return undefined;
+}
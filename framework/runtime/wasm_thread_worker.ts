@@ -0,0 +1,65 @@
+/**
+ * WASMThreadPool worker
+ *
+ * Instantiates the threads build of echelon_wasm on the pool's shared
+ * memory, gives this thread its own stack and TLS block, then answers
+ * `work` messages by calling `echelon_par_work(job)` until the job has no
+ * unclaimed items left.
+ */
+
+/// <reference lib="deno.worker" />
+
+import { entropyImports } from './wasm_host_functions.ts';
+
+interface InitMessage {
+  type: 'init';
+  module: WebAssembly.Module;
+  memory: WebAssembly.Memory;
+  /** Top of this thread's stack (16-byte aligned) */
+  stackTop: number;
+  /** Aligned TLS block for `__wasm_init_tls` */
+  tls: number;
+}
+
+interface WorkMessage {
+  type: 'work';
+  job: number;
+}
+
+type ThreadExports = Record<string, unknown> & {
+  __stack_pointer: WebAssembly.Global;
+  __wasm_init_tls(tls: number): void;
+  echelon_par_work(job: number): number;
+};
+
+let exports: ThreadExports | undefined;
+
+// Registered before any await so no message is lost
+self.onmessage = async (event: MessageEvent<InitMessage | WorkMessage>) => {
+  const message = event.data;
+  try {
+    if (message.type === 'init') {
+      const entropy = entropyImports();
+      const imports: WebAssembly.Imports = { env: { memory: message.memory } };
+      for (const { module: ns, name, kind } of WebAssembly.Module.imports(message.module)) {
+        if (kind !== 'function') continue;
+        (imports[ns] ??= {})[name] = (ns === 'env' && entropy[name]) || (() => 0);
+      }
+      const instance = await WebAssembly.instantiate(message.module, imports);
+      exports = instance.exports as ThreadExports;
+      exports.__stack_pointer.value = message.stackTop;
+      exports.__wasm_init_tls(message.tls);
+      self.postMessage({ type: 'ready' });
+    } else if (message.type === 'work') {
+      if (!exports) throw new Error('worker not initialized');
+      const processed = exports.echelon_par_work(message.job);
+      self.postMessage({ type: 'done', job: message.job, processed });
+    }
+  } catch (error) {
+    self.postMessage({
+      type: 'error',
+      job: message.type === 'work' ? message.job : undefined,
+      message: error instanceof Error ? error.message : String(error),
+    });
+  }
+};
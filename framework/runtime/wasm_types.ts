@@ -132,6 +132,7 @@ export interface WASMExecutionResult<T = unknown> {
  */
 export interface WASMExecutionOptions {
   timeout?: number;
+  /** Cooperative fuel budget, for modules exporting `echelon_set_fuel` */
   gasLimit?: number;
   memoryLimit?: number;
   sandboxId?: string;
@@ -177,6 +178,21 @@ export interface WASMMemoryStats {
   pageCount: number;    // Number of pages
   maxPages?: number;    // Maximum pages allowed
   moduleStats?: Map<string, WASMModuleMemoryStats>;
+  heap?: WASMHeapStats; // Allocator stats reported by the module(s)
+}
+
+/**
+ * Heap statistics reported by an instrumented module allocator
+ * (the `echelon_heap_*` exports)
+ */
+export interface WASMHeapStats {
+  liveBytes: number;      // Bytes currently allocated
+  peakBytes: number;      // Highest live bytes
+  allocations: number;    // Successful allocations
+  deallocations: number;  // Deallocations
+  reallocations: number;  // Successful reallocations
+  heapSize: number;       // Bytes claimed by the allocator
+  fragmentation: number;  // Share of heapSize not holding live data (0-1)
 }
 
 /**
@@ -189,6 +205,7 @@ export interface WASMModuleMemoryStats {
   peakUsage: number;
   allocations: number;
   frees: number;
+  heap?: WASMHeapStats;
 }
 
 // ============================================================================
@@ -585,6 +602,7 @@ export const WASMEvents = {
   EXEC_COMPLETE: 'wasm:exec:complete',
   EXEC_ERROR: 'wasm:exec:error',
   EXEC_TIMEOUT: 'wasm:exec:timeout',
+  EXEC_BUDGET_EXHAUSTED: 'wasm:exec:budget_exhausted',
 
   // Generator events
   GEN_START: 'wasm:gen:start',
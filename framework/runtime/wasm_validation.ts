@@ -47,9 +47,60 @@ export interface ValidationResult {
     tables: number;
     functions: number;
     globals: number;
+    manifest?: EchelonManifest;
   };
 }
 
+/**
+ * Export entry of an `echelon.manifest` custom section
+ */
+export interface EchelonManifestExport {
+  name: string;
+  abi: 'wasm-bindgen' | 'c';
+  class?: string;
+  /** `async fn`: the wasm-bindgen glue returns a Promise */
+  async?: boolean;
+  params: Array<{ name: string; type: string }>;
+  returns: string;
+  pure: boolean;
+  doc: string;
+}
+
+/**
+ * Contents of the `echelon.manifest` custom section embedded by echelon_wasm
+ */
+export interface EchelonManifest {
+  name: string;
+  version: string;
+  /** ABI contract version (absent in builds that predate `abi_version()`) */
+  abi_version?: number;
+  features: string[];
+  string_encoding: string;
+  abis: Record<string, string>;
+  exports: EchelonManifestExport[];
+}
+
+/** Custom section name of the export manifest */
+export const ECHELON_MANIFEST_SECTION = 'echelon.manifest';
+
+/**
+ * Read the `echelon.manifest` custom section, if the module has one
+ *
+ * Throws if the section exists but is not valid manifest JSON.
+ */
+export function readEchelonManifest(module: WebAssembly.Module): EchelonManifest | undefined {
+  const sections = WebAssembly.Module.customSections(module, ECHELON_MANIFEST_SECTION);
+  if (sections.length === 0) {
+    return undefined;
+  }
+
+  const manifest = JSON.parse(new TextDecoder().decode(sections[0])) as EchelonManifest;
+  if (typeof manifest.version !== 'string' || !Array.isArray(manifest.exports)) {
+    throw new Error('echelon.manifest is missing version or exports');
+  }
+  return manifest;
+}
+
 /**
  * Security scan result
  */
@@ -210,6 +261,30 @@ export class WASMValidator {
       });
     }
 
+    // Export manifest validation
+    try {
+      const manifest = readEchelonManifest(compiledModule);
+      if (manifest) {
+        metadata.manifest = manifest;
+        const exported = new Set(WebAssembly.Module.exports(compiledModule).map((e) => e.name));
+        const missing = manifest.exports.filter((e) => !exported.has(e.name)).map((e) => e.name);
+        if (missing.length > 0) {
+          issues.push({
+            severity: ValidationSeverity.WARNING,
+            code: 'MANIFEST_EXPORT_MISSING',
+            message: `Manifest lists ${missing.length} exports the module does not export`,
+            details: { missing, version: manifest.version },
+          });
+        }
+      }
+    } catch (error) {
+      issues.push({
+        severity: ValidationSeverity.ERROR,
+        code: 'MANIFEST_INVALID',
+        message: `Invalid ${ECHELON_MANIFEST_SECTION} section: ${error}`,
+      });
+    }
+
     // Determine if module is valid
     const valid = !issues.some((issue) =>
       issue.severity === ValidationSeverity.ERROR || issue.severity === ValidationSeverity.CRITICAL
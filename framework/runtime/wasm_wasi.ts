@@ -14,6 +14,7 @@
 import { resolve, join, dirname, basename } from 'https://deno.land/std@0.220.0/path/mod.ts';
 import { getLogger } from '../telemetry/logger.ts';
 import type { WASMCapability } from './wasm_types.ts';
+import type { HostEntropySource } from './wasm_host_functions.ts';
 
 const logger = getLogger();
 
@@ -233,6 +234,12 @@ export interface WASIOptions {
 
   /** Granted capabilities (Echelon security model) */
   capabilities?: WASMCapability[];
+
+  /**
+   * Source for the clocks and `random_get` instead of the system, e.g. a
+   * `DeterministicHostSource` for reproducible runs
+   */
+  entropy?: HostEntropySource;
 }
 
 /**
@@ -262,6 +269,7 @@ export class WASI {
   private memory: WebAssembly.Memory | null = null;
   private view: DataView | null = null;
   private capabilities: Set<WASMCapability>;
+  private entropy?: HostEntropySource;
 
   // Security flags
   private allowRead: boolean;
@@ -278,6 +286,7 @@ export class WASI {
     this.args = options.args ?? [];
     this.env = options.env ?? {};
     this.capabilities = new Set(options.capabilities ?? []);
+    this.entropy = options.entropy;
 
     // Security configuration
     this.allowRead = options.allowRead ?? false;
@@ -538,14 +547,12 @@ export class WASI {
     let time: bigint;
     switch (clock_id) {
       case WASIClockId.REALTIME:
-        time = BigInt(Date.now()) * 1000000n; // Convert ms to ns
+        time = BigInt(Math.floor(this.entropy?.now() ?? Date.now())) * 1000000n; // Convert ms to ns
         break;
       case WASIClockId.MONOTONIC:
-        time = BigInt(Math.floor(performance.now() * 1000000)); // Convert ms to ns
-        break;
       case WASIClockId.PROCESS_CPUTIME_ID:
       case WASIClockId.THREAD_CPUTIME_ID:
-        time = BigInt(Math.floor(performance.now() * 1000000));
+        time = BigInt(Math.floor((this.entropy?.performanceNow() ?? performance.now()) * 1000000)); // Convert ms to ns
         break;
       default:
         return WASIErrno.INVAL;
@@ -566,7 +573,17 @@ export class WASI {
     }
 
     const buffer = new Uint8Array(this.memory!.buffer, buf, buf_len);
-    crypto.getRandomValues(buffer);
+    if (this.entropy) {
+      // One 32-bit draw per 4 bytes, like the Rust module's `fill_random`
+      for (let offset = 0; offset < buf_len; offset += 4) {
+        const word = Math.floor(this.entropy.random() * 2 ** 32);
+        for (let i = 0; i < 4 && offset + i < buf_len; i++) {
+          buffer[offset + i] = (word >>> (8 * i)) & 0xff;
+        }
+      }
+    } else {
+      crypto.getRandomValues(buffer);
+    }
 
     return WASIErrno.SUCCESS;
   }
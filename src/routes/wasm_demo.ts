@@ -8,12 +8,14 @@
  * - Code generation from TypeScript and Rust
  * - Memory management and limits
  * - Metrics and monitoring
+ * - Self-test and benchmarks of the Rust module
  *
  * @module
  */
 
 import type { Context } from '@echelon/http/types.ts';
 import type { Application } from '@echelon/app.ts';
+import { loadEchelonWASM } from '@echelon/runtime/wasm_ffi.ts';
 
 /**
  * Helper to create JSON responses
@@ -442,6 +444,7 @@ export function setupWasmDemoRoutes(app: Application) {
   app.get('/api/wasm/demo/metrics', (_ctx: Context) => {
     try {
       const stats = app.wasm.getStats();
+      const heap = app.wasm.getMemoryUsage().heap;
 
       return json({
         success: true,
@@ -455,6 +458,8 @@ export function setupWasmDemoRoutes(app: Application) {
           sandboxes: stats.sandboxes,
           cacheSize: stats.cacheSize,
         },
+        // Allocator stats from modules with an instrumented heap (echelon_heap_*)
+        heap: heap ?? null,
         timestamp: new Date().toISOString(),
       });
     } catch (error) {
@@ -465,6 +470,43 @@ export function setupWasmDemoRoutes(app: Application) {
     }
   });
 
+  // ==========================================================================
+  // Self-Test and Benchmarks
+  // ==========================================================================
+
+  /**
+   * GET /api/wasm/demo/selftest?iterations=1000
+   * Runs the Rust module's known-answer vectors and micro-benchmarks
+   */
+  app.get('/api/wasm/demo/selftest', async (ctx: Context) => {
+    try {
+      const iterations = Number(ctx.query.get('iterations') ?? 1000);
+      const utils = await loadEchelonWASM('./wasm_modules/string_utils.wasm');
+      try {
+        const selfTest = utils.run_self_test();
+        const benchmarks = utils.run_benchmarks(iterations);
+
+        return json({
+          success: selfTest.failed === 0,
+          demo: 'self-test',
+          selfTest,
+          benchmarks,
+          note: benchmarks.clock === 'virtual'
+            ? 'No host clock: deterministic mode, or a subset build without host-entropy'
+            : undefined,
+        }, selfTest.failed === 0 ? 200 : 500);
+      } finally {
+        utils.close();
+      }
+    } catch (error) {
+      return json({
+        success: false,
+        error: error instanceof Error ? error.message : 'Unknown error',
+        note: 'Run: cd wasm_modules/rust_module && ./build.sh',
+      }, 500);
+    }
+  });
+
   // ==========================================================================
   // Comprehensive Demo (All Features)
   // ==========================================================================
@@ -622,6 +664,11 @@ export function setupWasmDemoRoutes(app: Application) {
           path: 'GET /api/wasm/demo/metrics',
           description: 'Runtime statistics and metrics',
         },
+        {
+          path: 'GET /api/wasm/demo/selftest',
+          description: 'Rust module self-test and benchmarks',
+          query: { iterations: 'number (default 1000)' },
+        },
         {
           path: 'POST /api/wasm/demo/comprehensive',
           description: 'Run all tests in sequence',
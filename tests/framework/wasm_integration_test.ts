@@ -5,13 +5,47 @@
  * caching, templates, and performance.
  */
 
-import { assertEquals, assertExists, assert, assertRejects } from 'jsr:@std/assert';
+import { assertEquals, assertExists, assert, assertRejects, assertThrows } from 'jsr:@std/assert';
 import { Application } from '../../framework/app.ts';
 import { WASI } from '../../framework/runtime/wasm_wasi.ts';
-import { WASMHostFunctionRegistry, HostFunctionHelpers } from '../../framework/runtime/wasm_host_functions.ts';
+import {
+  WASMHostFunctionRegistry,
+  HostFunctionHelpers,
+  DeterministicHostSource,
+  createHostFunctionRegistry,
+} from '../../framework/runtime/wasm_host_functions.ts';
 import { WASMValidator, WASMSecurityScanner, ValidationSeverity } from '../../framework/runtime/wasm_validation.ts';
-import { WASMModuleCache, generateCacheKey, warmCache } from '../../framework/runtime/wasm_cache.ts';
+import {
+  WASMModuleCache,
+  generateCacheKey,
+  warmCache,
+  crc32,
+  readWASMSnapshotInfo,
+} from '../../framework/runtime/wasm_cache.ts';
 import { getTemplateRegistry, compileTemplate } from '../../framework/runtime/wasm_templates.ts';
+import {
+  NativeWASMRegistry,
+  WASMIncompatibleModuleError,
+  checkWASMCompatibility,
+  readWASMABI,
+} from '../../framework/runtime/wasm_native_loader.ts';
+import {
+  decodeCBOR,
+  decodeMessagePack,
+  encodeCBOR,
+  encodeMessagePack,
+} from '../../framework/runtime/wasm_codec.ts';
+import {
+  echelonSelfTestCheck,
+  type EchelonSelfTestReport,
+  type EchelonStringUtils,
+} from '../../framework/runtime/wasm_ffi.ts';
+import { EchelonSharedBuffers } from '../../framework/runtime/wasm_shared_buffers.ts';
+import { WASMMemoryManager } from '../../framework/runtime/wasm_memory.ts';
+import { EventEmitter } from '../../framework/plugin/events.ts';
+import { KvStatus, WASMKvHost } from '../../framework/runtime/wasm_kv.ts';
+import { WASMSandboxManager } from '../../framework/runtime/wasm_sandbox.ts';
+import { WASMEvents } from '../../framework/runtime/wasm_types.ts';
 
 // ============================================================================
 // WASI Tests
@@ -436,6 +470,47 @@ Deno.test('WASMModuleCache: Clear cache', async () => {
   assertEquals(statsAfter.misses, 0);
 });
 
+/** Snapshot with one logging section (level 3), as `echelon_snapshot` writes it */
+function buildSnapshot(): Uint8Array {
+  const body = new Uint8Array([
+    ...new TextEncoder().encode('ECHS'), 1, 0, 1, 0, // version 1, 1 section
+    3, 0, 1, 0, 0, 0, 3, // tag 3, 1 byte: level 3
+  ]);
+  const bytes = new Uint8Array(body.length + 4);
+  bytes.set(body);
+  new DataView(bytes.buffer).setUint32(body.length, crc32(body), true);
+  return bytes;
+}
+
+Deno.test('WASMModuleCache: Snapshot checksum and framing', () => {
+  assertEquals(crc32(new TextEncoder().encode('123456789')), 0xcbf43926);
+
+  const snapshot = buildSnapshot();
+  assertEquals(readWASMSnapshotInfo(snapshot), { version: 1, sections: [3], size: snapshot.length });
+
+  const corrupted = snapshot.slice();
+  corrupted[14] ^= 1;
+  assertThrows(() => readWASMSnapshotInfo(corrupted), Error, 'checksum');
+  assertThrows(() => readWASMSnapshotInfo(snapshot.subarray(0, 8)), Error, 'Not an echelon_wasm snapshot');
+});
+
+Deno.test('WASMModuleCache: Save and load snapshots', async () => {
+  const cache = new WASMModuleCache();
+  await cache.initialize();
+
+  const snapshot = buildSnapshot();
+  await cache.saveSnapshot('counter', snapshot);
+  assertEquals(await cache.loadSnapshot('counter'), snapshot);
+
+  // Snapshots are independent of the module cache
+  await cache.clear();
+  assertEquals(await cache.loadSnapshot('counter'), snapshot);
+
+  await cache.deleteSnapshot('counter');
+  assertEquals(await cache.loadSnapshot('counter'), null);
+  await assertRejects(() => cache.saveSnapshot('bad', new Uint8Array(16)), Error, 'Not an echelon_wasm snapshot');
+});
+
 // ============================================================================
 // Template System Tests
 // ============================================================================
@@ -526,6 +601,316 @@ Deno.test('TemplateRegistry: Template interpolation', async () => {
   }
 });
 
+// ============================================================================
+// Deterministic Mode Tests
+// ============================================================================
+
+Deno.test('DeterministicHostSource: Same seed gives the same sequence', () => {
+  const a = new DeterministicHostSource({ seed: 42 });
+  const b = new DeterministicHostSource({ seed: 42n });
+  const c = new DeterministicHostSource({ seed: 43 });
+
+  const first = Array.from({ length: 8 }, () => a.random());
+  assertEquals(Array.from({ length: 8 }, () => b.random()), first);
+  assert(first.every((value) => value >= 0 && value < 1));
+  assert(Array.from({ length: 8 }, () => c.random()).some((value, i) => value !== first[i]));
+
+  a.reset();
+  assertEquals(a.random(), first[0]);
+});
+
+Deno.test('DeterministicHostSource: Clock is frozen until advanced', () => {
+  const frozen = new DeterministicHostSource({ seed: 1, startTime: 1_700_000_000_000 });
+  assertEquals(frozen.now(), 1_700_000_000_000);
+  assertEquals(frozen.now(), 1_700_000_000_000);
+  assertEquals(frozen.performanceNow(), 0);
+
+  frozen.advance(250);
+  assertEquals(frozen.now(), 1_700_000_000_250);
+  assertEquals(frozen.performanceNow(), 250);
+
+  const ticking = new DeterministicHostSource({ seed: 1, tickMs: 10 });
+  assertEquals([ticking.now(), ticking.now(), ticking.performanceNow()], [0, 10, 20]);
+});
+
+Deno.test('DeterministicHostSource: Registry imports are reproducible', () => {
+  const memory = new WebAssembly.Memory({ initial: 1 });
+  const run = () => {
+    const registry = createHostFunctionRegistry(true, {
+      entropy: new DeterministicHostSource({ seed: 7, startTime: 1000 }),
+    });
+    const env = registry.getImports('test-module', memory).env as Record<string, () => number>;
+    return [env['Math.random'](), env['Math.random'](), env['Date.now'](), env['performance.now']()];
+  };
+
+  const first = run();
+  assertEquals(run(), first);
+  assertEquals(first.slice(2), [1000, 0]);
+});
+
+Deno.test('DeterministicHostSource: WASI random_get and clocks', () => {
+  const memory = new WebAssembly.Memory({ initial: 1 });
+  const run = () => {
+    const wasi = new WASI({
+      capabilities: ['crypto'],
+      entropy: new DeterministicHostSource({ seed: 7, startTime: 5 }),
+    });
+    wasi.setMemory(memory);
+    const imports = wasi.getImports().wasi_snapshot_preview1 as Record<string, (...args: unknown[]) => number>;
+    assertEquals(imports.random_get(0, 10), 0);
+    assertEquals(imports.clock_time_get(0, 0n, 16), 0);
+    return [
+      ...new Uint8Array(memory.buffer, 0, 10),
+      new DataView(memory.buffer).getBigUint64(16, true),
+    ];
+  };
+
+  const first = run();
+  assertEquals(run(), first);
+  assertEquals(first[10], 5_000_000n);
+});
+
+// ============================================================================
+// Self-Test Tests
+// ============================================================================
+
+Deno.test('echelonSelfTestCheck: Reports failing functions', async () => {
+  const report = (failing: string[]): EchelonSelfTestReport => ({
+    passed: 2 - failing.length,
+    failed: failing.length,
+    checks: ['hash_string', 'word_count'].map((name) => ({
+      function: name,
+      vectors: 3,
+      passed: !failing.includes(name),
+    })),
+  });
+  const utils = (failing: string[]) =>
+    ({ backend: 'wasm', run_self_test: () => report(failing) }) as unknown as EchelonStringUtils;
+
+  assertEquals(await echelonSelfTestCheck(utils([]))(), {
+    status: 'pass',
+    message: '2 functions passed (wasm)',
+  });
+  assertEquals(await echelonSelfTestCheck(utils(['word_count']))(), {
+    status: 'fail',
+    message: 'Self-test failed: word_count (wasm)',
+  });
+});
+
+// ============================================================================
+// Codec Tests
+// ============================================================================
+
+const hex = (bytes: Uint8Array) => Array.from(bytes, (b) => b.toString(16).padStart(2, '0')).join('');
+const unhex = (s: string) => new Uint8Array(s.match(/../g)!.map((b) => parseInt(b, 16)));
+
+Deno.test('encodeCBOR: RFC 8949 examples', () => {
+  assertEquals(hex(encodeCBOR(100)), '1864');
+  assertEquals(hex(encodeCBOR(-1000)), '3903e7');
+  assertEquals(hex(encodeCBOR(1000000000000)), '1b000000e8d4a51000');
+  assertEquals(hex(encodeCBOR(18446744073709551615n)), '1bffffffffffffffff');
+  assertEquals(hex(encodeCBOR(1.1)), 'fb3ff199999999999a');
+  assertEquals(hex(encodeCBOR('IETF')), '6449455446');
+  assertEquals(hex(encodeCBOR({ a: 1, b: [2, 3] })), 'a26161016162820203');
+  // Undefined fields are left out so the Rust defaults apply
+  assertEquals(hex(encodeCBOR({ a: 1, decrypt: undefined })), 'a1616101');
+});
+
+Deno.test('decodeCBOR: Floats, tags and malformed input', () => {
+  assertEquals(decodeCBOR(unhex('f93e00')), 1.5);
+  assertEquals(decodeCBOR(unhex('fa47c35000')), 100000);
+  assertEquals(decodeCBOR(unhex('c11a514b67b0')), 1363896240);
+  assertEquals(decodeCBOR(unhex('1bffffffffffffffff')), 18446744073709551615n);
+  assertThrows(() => decodeCBOR(unhex('5f42010243030405ff')), Error, 'indefinite');
+  assertThrows(() => decodeCBOR(unhex('0102')), Error, 'trailing');
+  assertThrows(() => decodeCBOR(unhex('62')), Error, 'unexpected end of input');
+});
+
+Deno.test('MessagePack: Smallest encodings round-trip', () => {
+  const cases: Array<[unknown, string]> = [
+    [127, '7f'],
+    [128, 'cc80'],
+    [65536, 'ce00010000'],
+    [-32, 'e0'],
+    [-33, 'd0df'],
+    [-32769, 'd2ffff7fff'],
+    [1.5, 'cb3ff8000000000000'],
+    ['a', 'a161'],
+    [new Uint8Array([1, 2]), 'c4020102'],
+    [{ items: [1, 2] }, '81a56974656d73920102'],
+  ];
+  for (const [value, expected] of cases) {
+    assertEquals(hex(encodeMessagePack(value)), expected);
+    assertEquals(decodeMessagePack(unhex(expected)), value);
+  }
+
+  const large = { s: 'é'.repeat(300), list: Array.from({ length: 20 }, (_, i) => i), big: 2n ** 63n };
+  assertEquals(decodeMessagePack(encodeMessagePack(large)), large);
+  assertThrows(() => decodeMessagePack(unhex('d40100')), Error, 'unsupported type 0xd4');
+});
+
+// ============================================================================
+// ABI Compatibility Tests
+// ============================================================================
+
+Deno.test('readWASMABI: wasm-bindgen exports and unversioned modules', () => {
+  const abi = readWASMABI({ abi_version: () => 1, supports: (name: string) => name === 'hash.djb2' });
+  assertExists(abi);
+  assertEquals(abi.version, 1);
+  assert(abi.supports('hash.djb2'));
+  assert(!abi.supports('hash.xxh3'));
+
+  assertEquals(readWASMABI({ add: () => 0 }), null);
+});
+
+Deno.test('checkWASMCompatibility: Version ranges and required contracts', () => {
+  const abi = { version: 2, supports: (name: string) => name === 'snapshot' };
+
+  assertEquals(checkWASMCompatibility(abi, { abiVersion: 2, requires: ['snapshot'] }), []);
+  assertEquals(checkWASMCompatibility(abi, { abiVersion: { min: 1, max: 3 } }), []);
+  assertEquals(checkWASMCompatibility(abi, { abiVersion: 1, requires: ['hash.djb2'] }), [
+    'ABI version 2 is not supported (expected 1)',
+    "missing 'hash.djb2'",
+  ]);
+  assertEquals(checkWASMCompatibility(null, {}), ['module does not export abi_version']);
+  assertEquals(checkWASMCompatibility(null, { allowUnversioned: true }), []);
+});
+
+Deno.test('NativeWASMRegistry: Refuses incompatible modules', async () => {
+  const module = (version: number) =>
+    'data:application/javascript,' +
+    encodeURIComponent(`export const abi_version = () => ${version}; export const supports = () => false;`);
+  const registry = new NativeWASMRegistry({ compatibility: { abiVersion: 1 } });
+
+  await registry.register('strings', module(1));
+  assertEquals(registry.getABIVersion('strings'), 1);
+
+  const error = await assertRejects(() => registry.register('strings', module(2)), WASMIncompatibleModuleError);
+  assertEquals(error.issues, ['ABI version 2 is not supported (expected 1)']);
+  assertEquals(registry.getABIVersion('strings'), 1);
+
+  await registry.register('strings', module(2), {
+    compatibility: { abiVersion: 2, requires: ['hash'], onIncompatible: 'warn' },
+  });
+  assertEquals(registry.getABIVersion('strings'), 2);
+  assert(!registry.supports('strings', 'hash'));
+});
+
+// ============================================================================
+// Shared Buffer Tests
+// ============================================================================
+
+Deno.test('WASMMemoryManager: Encode strings into a fixed region', () => {
+  const manager = new WASMMemoryManager(new EventEmitter());
+  const memory = new WebAssembly.Memory({ initial: 1 });
+
+  assertEquals(manager.writeStringInto(memory, 16, 8, 'héllo'), 6);
+  assertEquals(manager.readString(memory, 16, 6), 'héllo');
+  assertEquals(manager.writeStringInto(memory, 16, 4, 'héllo'), -1);
+  assertEquals([...manager.viewBytes(memory, 16, 2)], [0x68, 0xc3]);
+});
+
+Deno.test('EchelonSharedBuffers: Reuses the input and output regions', () => {
+  const memory = new WebAssembly.Memory({ initial: 1 });
+  const bytes = new Uint8Array(memory.buffer);
+  const INPUT = 1024;
+  const OUTPUT = 8192;
+  let capacity = 0;
+  const exports: WebAssembly.Exports = {
+    memory,
+    echelon_input_buffer: (size: number) => {
+      capacity = Math.max(capacity, size);
+      return INPUT;
+    },
+    echelon_input_capacity: () => capacity,
+    echelon_output_buffer: () => OUTPUT,
+    echelon_last_error_code: () => 0,
+    echelon_reverse_string_shared: (len: number) => {
+      bytes.set(bytes.slice(INPUT, INPUT + len).reverse(), OUTPUT);
+      return len;
+    },
+  };
+
+  const shared = new EchelonSharedBuffers(exports);
+  // ASCII-sized first try, then room for the UTF-8 worst case
+  assertEquals(shared.write('héllo'), 6);
+  assertEquals(shared.inputCapacity, 15);
+  assertEquals(shared.reverse_string(new TextEncoder().encode('abc')), 'cba');
+  assertEquals(shared.inputCapacity, 15);
+  assertThrows(() => shared.hash_string('abc'), Error, 'not available in this build');
+
+  assert(!EchelonSharedBuffers.isSupported({ memory }));
+});
+
+// ============================================================================
+// Host KV Tests
+// ============================================================================
+
+Deno.test('WASMKvHost: Serves kv imports from Deno.Kv behind sandbox capabilities', async () => {
+  const kv = await Deno.openKv(':memory:');
+  const events = new EventEmitter();
+  const sandbox = new WASMSandboxManager(events, new WASMMemoryManager(events));
+  sandbox.createSandbox({ id: 'read-only', memoryLimit: 1 << 20, capabilities: ['kv-read'] });
+  sandbox.assignModule('reader', 'read-only');
+  sandbox.createSandbox({ id: 'read-write', memoryLimit: 1 << 20, capabilities: ['kv-read', 'kv-write'] });
+  sandbox.assignModule('writer', 'read-write');
+  const violations: string[] = [];
+  events.on<{ message: string }>(WASMEvents.SANDBOX_VIOLATION, (violation) => {
+    violations.push(violation.message);
+  });
+
+  // Fake module: bump allocator and a kv_complete that records the outcome
+  const memory = new WebAssembly.Memory({ initial: 1 });
+  const bytes = new Uint8Array(memory.buffer);
+  let next = 1024;
+  let onComplete = (_status: number, _payload: Uint8Array) => {};
+  const alloc = (len: number) => (next += len) - len;
+  const exports: WebAssembly.Exports = {
+    memory,
+    echelon_alloc: alloc,
+    echelon_dealloc: () => {},
+    echelon_kv_complete: (_op: number, status: number, ptr: number, len: number) =>
+      onComplete(status, bytes.slice(ptr, ptr + len)),
+  };
+  const write = (text: string): [number, number] => {
+    const encoded = new TextEncoder().encode(text);
+    const ptr = alloc(encoded.length);
+    bytes.set(encoded, ptr);
+    return [ptr, encoded.length];
+  };
+
+  const host = new WASMKvHost(kv, { sandbox });
+  host.attach('reader', exports);
+  host.attach('writer', exports);
+  const call = (moduleId: string, name: string, ...args: number[]) =>
+    new Promise<[number, string]>((resolve) => {
+      onComplete = (status, payload) => resolve([status, new TextDecoder().decode(payload)]);
+      (host.imports(moduleId).env[name] as (...args: number[]) => void)(1, ...args);
+    });
+
+  assertEquals(await call('writer', 'kv_set', ...write('idx/fox'), ...write('doc-1')), [KvStatus.Ok, '']);
+  assertEquals(await call('writer', 'kv_get', ...write('idx/fox')), [KvStatus.Ok, 'doc-1']);
+  assertEquals(await call('writer', 'kv_get', ...write('idx/cat')), [KvStatus.NotFound, '']);
+  assertEquals((await kv.get(['wasm', 'writer', 'idx/fox'])).value, new TextEncoder().encode('doc-1'));
+
+  // u32 LE length-prefixed key, value pairs
+  const [status, listed] = await call('writer', 'kv_list', ...write('idx/'), 10);
+  assertEquals([status, listed], [KvStatus.Ok, '\x07\0\0\0idx/fox\x05\0\0\0doc-1']);
+
+  // Keys are per module, and writes need kv-write
+  assertEquals(await call('reader', 'kv_get', ...write('idx/fox')), [KvStatus.NotFound, '']);
+  const [denied, message] = await call('reader', 'kv_set', ...write('idx/fox'), ...write('doc-2'));
+  assertEquals(denied, KvStatus.Denied);
+  assert(message.includes('kv-write'));
+  assertEquals(violations.length, 1);
+
+  const registry = new WASMHostFunctionRegistry();
+  host.register(registry, 'writer');
+  assertEquals(Object.keys(registry.getImports('writer', memory).env).sort(), ['kv_get', 'kv_list', 'kv_set']);
+
+  kv.close();
+});
+
 // ============================================================================
 // Integration Tests
 // ============================================================================
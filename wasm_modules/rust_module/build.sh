@@ -3,18 +3,83 @@
 
 set -e
 
+# Optional export subset, e.g. FEATURES=text,hash ./build.sh
+CARGO_FEATURES=()
+if [ -n "$FEATURES" ]; then
+    CARGO_FEATURES=(--no-default-features --features "$FEATURES")
+fi
+
+# simd128 text/hash kernels for the wasm builds, e.g. SIMD=1 ./build.sh
+SIMD_FLAGS=""
+if [ "${SIMD:-0}" = "1" ]; then
+    SIMD_FLAGS="-C target-feature=+simd128"
+    export CARGO_TARGET_WASM32_UNKNOWN_UNKNOWN_RUSTFLAGS="$SIMD_FLAGS"
+fi
+
 echo "Building Rust WASM module..."
 
 # Build with wasm-pack (if available)
 if command -v wasm-pack &> /dev/null; then
-    wasm-pack build --target web --release
+    wasm-pack build --target web --release -- "${CARGO_FEATURES[@]}"
     cp pkg/echelon_wasm_bg.wasm ../string_utils.wasm
     echo "✓ Built with wasm-pack: string_utils.wasm"
 else
     # Fallback to cargo build
-    cargo build --target wasm32-unknown-unknown --release
+    cargo build --target wasm32-unknown-unknown --release "${CARGO_FEATURES[@]}"
     cp target/wasm32-unknown-unknown/release/echelon_wasm.wasm ../string_utils.wasm
     echo "✓ Built with cargo: string_utils.wasm"
 fi
 
+# Typed declarations generated by build/main.rs for the wasm32 build
+DTS=$(ls -t target/wasm32-unknown-unknown/release/build/echelon_wasm-*/out/string_utils.d.ts 2>/dev/null | head -1)
+if [ -n "$DTS" ]; then
+    cp "$DTS" ../string_utils.d.ts
+    echo "✓ Generated declarations: string_utils.d.ts"
+fi
+
+# WASI command build (JSON-RPC over stdio)
+if rustup target list --installed 2>/dev/null | grep -q wasm32-wasip1; then
+    cargo build --target wasm32-wasip1 --release --bin echelon_wasi
+    cp target/wasm32-wasip1/release/echelon_wasi.wasm ../string_utils_wasi.wasm
+    echo "✓ Built WASI command: string_utils_wasi.wasm"
+fi
+
+# Component-model build of the wit/echelon.wit world (COMPONENT=1, needs
+# wasm-tools and the wasi_snapshot_preview1 reactor adapter from a wasmtime
+# release, path in WASI_ADAPTER)
+if [ "${COMPONENT:-0}" = "1" ]; then
+    cargo build --lib --target wasm32-wasip1 --release --no-default-features --features component
+    CORE=target/wasm32-wasip1/release/echelon_wasm.wasm
+    cp "$CORE" ../string_utils_component_core.wasm
+    wasm-tools component embed wit "$CORE" -o target/wasm32-wasip1/release/echelon_wasm.embed.wasm
+    wasm-tools component new target/wasm32-wasip1/release/echelon_wasm.embed.wasm \
+        --adapt "wasi_snapshot_preview1=${WASI_ADAPTER:-wasi_snapshot_preview1.reactor.wasm}" \
+        -o ../string_utils_component.wasm
+    cp wit/echelon.wit ../string_utils.wit
+    echo "✓ Built component: string_utils_component.wasm (core module: string_utils_component_core.wasm)"
+fi
+
+# Shared-memory threads build for WASMThreadPool (THREADS=1, needs nightly + rust-src)
+if [ "${THREADS:-0}" = "1" ]; then
+    RUSTFLAGS="$SIMD_FLAGS -C target-feature=+atomics,+bulk-memory,+mutable-globals \
+-C link-arg=--shared-memory -C link-arg=--import-memory -C link-arg=--max-memory=1073741824 \
+-C link-arg=--export=__stack_pointer -C link-arg=--export=__wasm_init_tls \
+-C link-arg=--export=__tls_size -C link-arg=--export=__tls_align -C link-arg=--export=__tls_base" \
+    cargo +nightly build --target wasm32-unknown-unknown --release "${CARGO_FEATURES[@]}" \
+        -Z build-std=panic_abort,std --target-dir target/threads
+    cp target/threads/wasm32-unknown-unknown/release/echelon_wasm.wasm ../string_utils_threads.wasm
+    echo "✓ Built threads module: string_utils_threads.wasm"
+fi
+
+# Native cdylib for Deno FFI (NATIVE=0 to skip)
+if [ "${NATIVE:-1}" = "1" ]; then
+    cargo build --release "${CARGO_FEATURES[@]}"
+    for LIB in libechelon_wasm.so libechelon_wasm.dylib echelon_wasm.dll; do
+        if [ -f "target/release/$LIB" ]; then
+            cp "target/release/$LIB" ../
+            echo "✓ Built native library: $LIB"
+        fi
+    done
+fi
+
 echo "Done!"
@@ -0,0 +1,158 @@
+/*!
+ * Build script - export manifest and TypeScript declarations
+ *
+ * Walks the crate's module tree from `src/lib.rs`, collects every
+ * `#[wasm_bindgen]` export and every `#[no_mangle] extern "C"` export that is
+ * enabled for the current target and feature set, and writes into `$OUT_DIR`:
+ *
+ * - `echelon_manifest.json` - embedded by `src/manifest.rs` as the
+ *   `echelon.manifest` custom section.
+ * - `echelon_features.rs` - the enabled features, for `supports()` in
+ *   `src/version.rs`.
+ * - `string_utils.d.ts` - typed interfaces for the module, copied next to
+ *   `string_utils.wasm` by `build.sh`.
+ *
+ * With the `component` feature it also checks that `wit/echelon.wit` is
+ * versioned with the crate, since `src/component.rs` derives its export
+ * names from `CARGO_PKG_VERSION`. On wasm targets that build is the
+ * component core: the `component_core` cfg drops the C ABI and every
+ * wasm-bindgen export, so the core module exports only the canonical ABI.
+ */
+
+mod scan;
+mod typescript;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+use syn::{Expr, ExprLit, Item, Lit};
+
+use scan::{Export, Surface};
+
+/// Module name the wasm artifact is published under
+const MODULE_NAME: &str = "string_utils";
+
+fn main() {
+    let src = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("src");
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=build");
+    println!("cargo:rerun-if-changed=wit");
+
+    let surface = Surface::scan(&src);
+    let version = env::var("CARGO_PKG_VERSION").unwrap();
+    let features = features();
+    if env::var("CARGO_FEATURE_COMPONENT").is_ok() {
+        check_wit_version(&version);
+    }
+    println!("cargo:rustc-check-cfg=cfg(component_core)");
+    if component_core() {
+        check_component_features(&features);
+        println!("cargo:rustc-cfg=component_core");
+    }
+
+    let manifest = json!({
+        "name": env::var("CARGO_PKG_NAME").unwrap(),
+        "version": version,
+        "abi_version": abi_version(&src),
+        "features": features,
+        "kernels": kernels(),
+        "string_encoding": "utf-8",
+        "abis": {
+            "wasm-bindgen": "JS values through wasm-bindgen glue",
+            "c": "UTF-8 (ptr, len) inputs from echelon_alloc; string results packed as (ptr << 32) | len on wasm32 (block pointer + echelon_block_len on 64-bit), freed with echelon_dealloc",
+        },
+        "exports": surface.exports.iter().map(export_json).collect::<Vec<_>>(),
+    });
+
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out.join("echelon_manifest.json"), manifest.to_string()).unwrap();
+    fs::write(out.join("echelon_features.rs"), format!("{features:?}")).unwrap();
+    fs::write(
+        out.join(format!("{MODULE_NAME}.d.ts")),
+        typescript::render(&surface, MODULE_NAME, &version, &features),
+    )
+    .unwrap();
+}
+
+fn export_json(export: &Export) -> Value {
+    let mut value = json!({
+        "name": export.name,
+        "abi": export.abi,
+        "params": export.params.iter().map(|(name, ty)| json!({ "name": name, "type": ty })).collect::<Vec<_>>(),
+        "returns": export.returns,
+        "pure": export.pure,
+        "doc": export.doc,
+    });
+    if let Some(class) = &export.class {
+        value["class"] = json!(class);
+    }
+    if export.is_async {
+        value["async"] = json!(true);
+    }
+    value
+}
+
+/// Kernel set `src/kernels.rs` compiles for this target
+fn kernels() -> &'static str {
+    let wasm32 = env::var("CARGO_CFG_TARGET_ARCH").is_ok_and(|arch| arch == "wasm32");
+    let simd128 = env::var("CARGO_CFG_TARGET_FEATURE").is_ok_and(|f| f.split(',').any(|f| f == "simd128"));
+    if wasm32 && simd128 { "simd128" } else { "scalar" }
+}
+
+/// `ABI_VERSION` declared in `src/version.rs`
+fn abi_version(src: &Path) -> u32 {
+    let path = src.join("version.rs");
+    let source = fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+    let file = syn::parse_file(&source).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+    file.items
+        .iter()
+        .find_map(|item| match item {
+            Item::Const(item) if item.ident == "ABI_VERSION" => match &*item.expr {
+                Expr::Lit(ExprLit { lit: Lit::Int(value), .. }) => value.base10_parse().ok(),
+                _ => None,
+            },
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("{}: no `const ABI_VERSION: u32 = <integer>;`", path.display()))
+}
+
+/// Fail the build if the WIT package version differs from the crate's
+fn check_wit_version(version: &str) {
+    let path = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("wit/echelon.wit");
+    let wit = fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+    let package = wit
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("package echelon:wasm@"))
+        .and_then(|rest| rest.strip_suffix(';'))
+        .unwrap_or_else(|| panic!("{}: no `package echelon:wasm@<version>;`", path.display()));
+    assert_eq!(package, version, "{}: package version must match Cargo.toml", path.display());
+}
+
+/// Whether this is the component core build: the `component` feature on a wasm target
+pub fn component_core() -> bool {
+    env::var("CARGO_FEATURE_COMPONENT").is_ok() && env::var("CARGO_CFG_TARGET_FAMILY").is_ok_and(|f| f == "wasm")
+}
+
+/// Fail the component core build if it enables export groups outside the WIT world
+fn check_component_features(features: &[String]) {
+    let extra: Vec<&str> =
+        features.iter().map(String::as_str).filter(|f| !["component", "text", "hash", "cipher"].contains(f)).collect();
+    assert!(
+        extra.is_empty(),
+        "the component core carries only the WIT world, build it with `--no-default-features --features component` \
+         (also enabled: {})",
+        extra.join(", ")
+    );
+}
+
+/// Enabled cargo features, in Cargo.toml spelling
+fn features() -> Vec<String> {
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(|f| f.to_lowercase().replace('_', "-")))
+        .filter(|f| f != "default")
+        .collect();
+    features.sort();
+    features
+}
@@ -0,0 +1,293 @@
+/*!
+ * Source scanner
+ *
+ * Parses the crate's module tree with `syn` and collects the items that make
+ * up the module's public surface for the current target and feature set.
+ *
+ * Free functions are pure unless marked `#[doc(alias = "impure")]`, the tag
+ * for exports that read or change module state. Methods are pure unless they
+ * take `self`.
+ */
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use quote::ToTokens;
+use syn::{Attribute, Fields, FnArg, ImplItem, Item, Meta, ReturnType, Type, Visibility};
+
+/// One exported function or method
+pub struct Export {
+    pub name: String,
+    pub abi: &'static str,
+    pub class: Option<String>,
+    /// Method name as seen on the JS class (`None` for free functions)
+    pub method: Option<String>,
+    pub is_constructor: bool,
+    /// `async fn`, returned to JS as a `Promise`
+    pub is_async: bool,
+    pub params: Vec<(String, String)>,
+    pub returns: String,
+    pub pure: bool,
+    pub doc: String,
+}
+
+/// `#[wasm_bindgen]` struct with its public fields
+pub struct Class {
+    pub name: String,
+    pub fields: Vec<(String, String, String)>,
+    pub doc: String,
+}
+
+/// `#[wasm_bindgen]` C-like enum
+pub struct Enum {
+    pub name: String,
+    pub variants: Vec<(String, String)>,
+    pub doc: String,
+}
+
+/// Public surface of the crate
+#[derive(Default)]
+pub struct Surface {
+    pub exports: Vec<Export>,
+    pub classes: Vec<Class>,
+    pub enums: Vec<Enum>,
+}
+
+impl Surface {
+    /// Scan `lib.rs` in `src` and every module it declares
+    pub fn scan(src: &Path) -> Self {
+        let mut surface = Surface::default();
+        surface.collect_module(&src.join("lib.rs"), src);
+        surface.exports.sort_by(|a, b| a.name.cmp(&b.name));
+        surface.classes.sort_by(|a, b| a.name.cmp(&b.name));
+        surface.enums.sort_by(|a, b| a.name.cmp(&b.name));
+        surface
+    }
+
+    fn collect_module(&mut self, path: &Path, dir: &Path) {
+        let source = fs::read_to_string(path).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+        let file = syn::parse_file(&source).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+        self.collect_items(&file.items, dir);
+    }
+
+    fn collect_items(&mut self, items: &[Item], dir: &Path) {
+        for item in items {
+            match item {
+                Item::Mod(module) if cfg_enabled(&module.attrs) => match &module.content {
+                    Some((_, items)) => self.collect_items(items, dir),
+                    None => self.collect_module(&dir.join(format!("{}.rs", module.ident)), dir),
+                },
+                Item::Fn(func) if cfg_enabled(&func.attrs) && is_pub(&func.vis) => {
+                    let abi = if has_attr(&func.attrs, "wasm_bindgen") {
+                        "wasm-bindgen"
+                    } else if has_attr(&func.attrs, "no_mangle") && func.sig.abi.is_some() {
+                        "c"
+                    } else {
+                        continue;
+                    };
+
+                    let name = func.sig.ident.to_string();
+                    self.exports.push(Export {
+                        pure: !is_impure(&func.attrs),
+                        name,
+                        abi,
+                        class: None,
+                        method: None,
+                        is_constructor: false,
+                        is_async: func.sig.asyncness.is_some(),
+                        params: params(func.sig.inputs.iter()),
+                        returns: return_type(&func.sig.output),
+                        doc: doc(&func.attrs),
+                    });
+                }
+                Item::Impl(block) if cfg_enabled(&block.attrs) && has_attr(&block.attrs, "wasm_bindgen") => {
+                    let class = block.self_ty.to_token_stream().to_string();
+                    for item in &block.items {
+                        let ImplItem::Fn(method) = item else { continue };
+                        if !is_pub(&method.vis) || !cfg_enabled(&method.attrs) {
+                            continue;
+                        }
+
+                        let is_constructor = method.attrs.iter().any(|attr| {
+                            attr.path().is_ident("wasm_bindgen")
+                                && attr.to_token_stream().to_string().contains("constructor")
+                        });
+                        let method_name = if is_constructor { "new".to_string() } else { method.sig.ident.to_string() };
+                        let stateful = matches!(method.sig.inputs.first(), Some(FnArg::Receiver(_)));
+
+                        self.exports.push(Export {
+                            name: format!("{}_{}", class.to_lowercase(), method_name),
+                            abi: "wasm-bindgen",
+                            class: Some(class.clone()),
+                            method: Some(method.sig.ident.to_string()),
+                            is_constructor,
+                            is_async: method.sig.asyncness.is_some(),
+                            params: params(method.sig.inputs.iter()),
+                            returns: return_type(&method.sig.output),
+                            pure: !stateful,
+                            doc: doc(&method.attrs),
+                        });
+                    }
+                }
+                Item::Struct(item) if cfg_enabled(&item.attrs) && has_attr(&item.attrs, "wasm_bindgen") => {
+                    let fields = match &item.fields {
+                        Fields::Named(named) => named
+                            .named
+                            .iter()
+                            .filter(|field| is_pub(&field.vis))
+                            .map(|field| {
+                                let name = field.ident.as_ref().map(ToString::to_string).unwrap_or_default();
+                                (name, type_name(&field.ty), doc(&field.attrs))
+                            })
+                            .collect(),
+                        _ => Vec::new(),
+                    };
+                    self.classes.push(Class {
+                        name: item.ident.to_string(),
+                        fields,
+                        doc: doc(&item.attrs),
+                    });
+                }
+                Item::Enum(item) if cfg_enabled(&item.attrs) && has_attr(&item.attrs, "wasm_bindgen") => {
+                    let variants = item
+                        .variants
+                        .iter()
+                        .map(|variant| {
+                            let value = variant
+                                .discriminant
+                                .as_ref()
+                                .map(|(_, expr)| expr.to_token_stream().to_string())
+                                .unwrap_or_default();
+                            (variant.ident.to_string(), value)
+                        })
+                        .collect();
+                    self.enums.push(Enum {
+                        name: item.ident.to_string(),
+                        variants,
+                        doc: doc(&item.attrs),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn is_pub(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+fn params<'a>(inputs: impl Iterator<Item = &'a FnArg>) -> Vec<(String, String)> {
+    inputs
+        .filter_map(|arg| match arg {
+            FnArg::Typed(pat) => Some((pat.pat.to_token_stream().to_string(), type_name(&pat.ty))),
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
+
+fn return_type(output: &ReturnType) -> String {
+    match output {
+        ReturnType::Default => "()".to_string(),
+        ReturnType::Type(_, ty) => type_name(ty),
+    }
+}
+
+/// Render a type the way it is written in source (`&str`, `Vec<String>`, `*const u8`)
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Reference(r) => {
+            let mutability = if r.mutability.is_some() { "mut " } else { "" };
+            format!("&{mutability}{}", type_name(&r.elem))
+        }
+        Type::Ptr(p) => {
+            let kind = if p.mutability.is_some() { "mut" } else { "const" };
+            format!("*{kind} {}", type_name(&p.elem))
+        }
+        Type::Slice(s) => format!("[{}]", type_name(&s.elem)),
+        _ => ty.to_token_stream().to_string().replace(' ', ""),
+    }
+}
+
+fn doc(attrs: &[Attribute]) -> String {
+    attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(nv) if nv.path.is_ident("doc") => match &nv.value {
+                syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(s), .. }) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .next()
+        .unwrap_or_default()
+}
+
+/// `#[doc(alias = "impure")]`
+fn is_impure(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| match &attr.meta {
+        Meta::List(list) if list.path.is_ident("doc") => {
+            list.tokens.to_string().replace(' ', "") == r#"alias="impure""#
+        }
+        _ => false,
+    })
+}
+
+/// `#[name]`, or `#[cfg_attr(<predicate>, name)]` with the predicate holding
+fn has_attr(attrs: &[Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| {
+        if attr.path().is_ident(name) {
+            return true;
+        }
+        attr.path().is_ident("cfg_attr")
+            && attr
+                .parse_args_with(syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated)
+                .is_ok_and(|args| {
+                    let mut args = args.iter();
+                    args.next().is_some_and(eval_cfg) && args.any(|meta| meta.path().is_ident(name))
+                })
+    })
+}
+
+/// Evaluate `#[cfg(...)]` attributes against the current build
+fn cfg_enabled(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cfg"))
+        .all(|attr| attr.parse_args::<Meta>().map(|meta| eval_cfg(&meta)).unwrap_or(true))
+}
+
+fn eval_cfg(meta: &Meta) -> bool {
+    match meta {
+        Meta::NameValue(nv) => {
+            let key = nv.path.to_token_stream().to_string();
+            let value = nv.value.to_token_stream().to_string().trim_matches('"').to_string();
+            match key.as_str() {
+                "feature" => env::var(format!("CARGO_FEATURE_{}", value.to_uppercase().replace('-', "_"))).is_ok(),
+                _ => env::var(format!("CARGO_CFG_{}", key.to_uppercase()))
+                    .map(|actual| actual.split(',').any(|v| v == value))
+                    .unwrap_or(false),
+            }
+        }
+        Meta::List(list) => {
+            let nested = list
+                .parse_args_with(syn::punctuated::Punctuated::<Meta, syn::Token![,]>::parse_terminated)
+                .map(|items| items.into_iter().collect::<Vec<_>>())
+                .unwrap_or_default();
+            if list.path.is_ident("all") {
+                nested.iter().all(eval_cfg)
+            } else if list.path.is_ident("any") {
+                nested.iter().any(eval_cfg)
+            } else if list.path.is_ident("not") {
+                !nested.iter().all(eval_cfg)
+            } else {
+                true
+            }
+        }
+        Meta::Path(path) if path.is_ident("component_core") => crate::component_core(),
+        Meta::Path(path) => {
+            env::var(format!("CARGO_CFG_{}", path.to_token_stream().to_string().to_uppercase())).is_ok()
+        }
+    }
+}
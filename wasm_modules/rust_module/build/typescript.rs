@@ -0,0 +1,189 @@
+/*!
+ * TypeScript declaration generator
+ *
+ * Renders `string_utils.d.ts` from the scanned surface:
+ *
+ * - `StringUtilsExports` - the raw WebAssembly exports (C ABI and scalar
+ *   functions), for `createTypedWASMModule` and `NativeWASMRegistry`.
+ * - `StringUtilsBindings` - the wasm-bindgen JS glue API, with the exported
+ *   classes and enums declared alongside.
+ */
+
+use std::fmt::Write;
+
+use crate::scan::{Export, Surface};
+
+/// Render the declaration file for `module` (e.g. `string_utils`)
+pub fn render(surface: &Surface, module: &str, version: &str, features: &[String]) -> String {
+    let prefix = pascal_case(module);
+    let mut out = String::new();
+
+    let features = if features.is_empty() { "none".to_string() } else { features.join(", ") };
+    writeln!(out, "// Generated by echelon_wasm build.rs from the Rust signatures. Do not edit.").unwrap();
+    writeln!(out, "// echelon_wasm {version} (features: {features})").unwrap();
+    writeln!(out).unwrap();
+
+    // Raw exports
+    writeln!(out, "/** Raw exports of {module}.wasm */").unwrap();
+    writeln!(out, "export type {prefix}Exports = {{").unwrap();
+    writeln!(out, "  memory: WebAssembly.Memory;").unwrap();
+    for export in surface.exports.iter().filter(|e| e.class.is_none()) {
+        if let Some(signature) = raw_signature(export) {
+            write_doc(&mut out, "  ", &export.doc);
+            writeln!(out, "  {}{signature};", export.name).unwrap();
+        }
+    }
+    writeln!(out, "}};").unwrap();
+
+    // Enums
+    for item in &surface.enums {
+        writeln!(out).unwrap();
+        write_doc(&mut out, "", &item.doc);
+        writeln!(out, "export declare enum {} {{", item.name).unwrap();
+        for (name, value) in &item.variants {
+            if value.is_empty() {
+                writeln!(out, "  {name},").unwrap();
+            } else {
+                writeln!(out, "  {name} = {value},").unwrap();
+            }
+        }
+        writeln!(out, "}}").unwrap();
+    }
+
+    // Classes
+    for class in &surface.classes {
+        let methods: Vec<&Export> =
+            surface.exports.iter().filter(|e| e.class.as_deref() == Some(class.name.as_str())).collect();
+
+        writeln!(out).unwrap();
+        write_doc(&mut out, "", &class.doc);
+        writeln!(out, "export declare class {} {{", class.name).unwrap();
+        match methods.iter().find(|m| m.is_constructor) {
+            Some(ctor) => {
+                write_doc(&mut out, "  ", &ctor.doc);
+                writeln!(out, "  constructor({});", glue_params(&ctor.params)).unwrap();
+            }
+            None => writeln!(out, "  private constructor();").unwrap(),
+        }
+        writeln!(out, "  /** Release the Rust-side value */").unwrap();
+        writeln!(out, "  free(): void;").unwrap();
+        for (name, ty, doc) in &class.fields {
+            write_doc(&mut out, "  ", doc);
+            writeln!(out, "  {name}: {};", glue_type(ty)).unwrap();
+        }
+        for method in methods.iter().filter(|m| !m.is_constructor) {
+            write_doc(&mut out, "  ", &method.doc);
+            let name = method.method.as_deref().unwrap_or(&method.name);
+            writeln!(out, "  {name}({}): {};", glue_params(&method.params), glue_return(method)).unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+    }
+
+    // wasm-bindgen glue
+    writeln!(out).unwrap();
+    writeln!(out, "/** wasm-bindgen JS glue API of {module}.wasm */").unwrap();
+    writeln!(out, "export type {prefix}Bindings = {{").unwrap();
+    for export in surface.exports.iter().filter(|e| e.abi == "wasm-bindgen" && e.class.is_none()) {
+        write_doc(&mut out, "  ", &export.doc);
+        writeln!(out, "  {}({}): {};", export.name, glue_params(&export.params), glue_return(export)).unwrap();
+    }
+    for name in surface.classes.iter().map(|c| &c.name).chain(surface.enums.iter().map(|e| &e.name)) {
+        writeln!(out, "  {name}: typeof {name};").unwrap();
+    }
+    writeln!(out, "}};").unwrap();
+
+    out
+}
+
+fn write_doc(out: &mut String, indent: &str, doc: &str) {
+    if !doc.is_empty() {
+        writeln!(out, "{indent}/** {} */", doc.replace("*/", "*\\/")).unwrap();
+    }
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map(|c| c.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Signature of an export callable without glue, `None` if it needs glue
+fn raw_signature(export: &Export) -> Option<String> {
+    let params = export
+        .params
+        .iter()
+        .map(|(name, ty)| raw_type(ty).map(|ty| format!("{name}: {ty}")))
+        .collect::<Option<Vec<_>>>()?;
+    let returns = raw_type(&export.returns)?;
+    Some(format!("({}): {returns}", params.join(", ")))
+}
+
+/// WebAssembly value type of a Rust scalar as seen from JS
+fn raw_type(ty: &str) -> Option<&'static str> {
+    match ty {
+        "()" => Some("void"),
+        "i64" | "u64" => Some("bigint"),
+        "i8" | "i16" | "i32" | "u8" | "u16" | "u32" | "isize" | "usize" | "f32" | "f64" | "bool" => Some("number"),
+        _ if ty.starts_with('*') => Some("number"),
+        _ => None,
+    }
+}
+
+fn glue_params(params: &[(String, String)]) -> String {
+    params
+        .iter()
+        .map(|(name, ty)| format!("{name}: {}", glue_type(ty)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Glue return type, a `Promise` for `async fn`
+fn glue_return(export: &Export) -> String {
+    let ty = glue_type(&export.returns);
+    if export.is_async { format!("Promise<{ty}>") } else { ty }
+}
+
+/// TypeScript type wasm-bindgen uses for a Rust type
+fn glue_type(ty: &str) -> String {
+    let inner = |prefix: &str| ty.strip_prefix(prefix).and_then(|rest| rest.strip_suffix('>'));
+
+    match ty {
+        "()" => "void".to_string(),
+        "bool" => "boolean".to_string(),
+        "char" | "&str" | "String" | "&String" => "string".to_string(),
+        "i64" | "u64" | "i128" | "u128" => "bigint".to_string(),
+        "i8" | "i16" | "i32" | "u8" | "u16" | "u32" | "isize" | "usize" | "f32" | "f64" => "number".to_string(),
+        "JsValue" | "&JsValue" => "any".to_string(),
+        _ => {
+            if let Some(elem) = inner("Option<") {
+                return format!("{} | undefined", glue_type(elem));
+            }
+            if let Some(ok) = inner("Result<").or_else(|| inner("std::result::Result<")) {
+                let ok = ok.split(',').next().unwrap_or(ok);
+                return glue_type(ok);
+            }
+
+            let elem = inner("Vec<")
+                .or_else(|| inner("Box<[").map(|e| e.trim_end_matches(']')))
+                .or_else(|| ty.strip_prefix("&[").and_then(|e| e.strip_suffix(']')))
+                .or_else(|| ty.strip_prefix("&mut [").and_then(|e| e.strip_suffix(']')));
+            match elem {
+                Some("u8") => "Uint8Array".to_string(),
+                Some("i8") => "Int8Array".to_string(),
+                Some("u16") => "Uint16Array".to_string(),
+                Some("i16") => "Int16Array".to_string(),
+                Some("u32") | Some("usize") => "Uint32Array".to_string(),
+                Some("i32") | Some("isize") => "Int32Array".to_string(),
+                Some("u64") => "BigUint64Array".to_string(),
+                Some("i64") => "BigInt64Array".to_string(),
+                Some("f32") => "Float32Array".to_string(),
+                Some("f64") => "Float64Array".to_string(),
+                Some(elem) => format!("{}[]", glue_type(elem)),
+                None => ty.trim_start_matches('&').to_string(),
+            }
+        }
+    }
+}
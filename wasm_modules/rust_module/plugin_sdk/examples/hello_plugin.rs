@@ -0,0 +1,60 @@
+/*!
+ * Sample plugin
+ *
+ * Counts `user.created` events and emits a greeting for each one, using a
+ * custom `greeting` host import when the host provides it.
+ *
+ * ```bash
+ * cargo build -p echelon_plugin_sdk --example hello_plugin --target wasm32-unknown-unknown --release
+ * ```
+ */
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use echelon_plugin_sdk::{host, host_import, on_destroy, on_init, plugin, subscribe, Event, PluginResult};
+
+plugin! {
+    name: "hello",
+    version: "0.1.0",
+    description: "Greets new users",
+}
+
+on_init!(init);
+on_destroy!(destroy);
+
+subscribe! {
+    "user.created" => greet,
+}
+
+host_import! {
+    /// Greeting text for a user name (provided by the host)
+    fn greeting(name: &str) -> String;
+}
+
+static GREETED: AtomicU32 = AtomicU32::new(0);
+
+fn init() -> PluginResult {
+    let prefix = host::config("hello.prefix").unwrap_or_else(|| "Hello".to_string());
+    host::info(&format!("hello plugin ready (prefix: {prefix})"));
+    Ok(())
+}
+
+fn destroy() -> PluginResult {
+    host::info(&format!("hello plugin greeted {} users", GREETED.load(Ordering::Relaxed)));
+    Ok(())
+}
+
+/// `payload` is `[{"name": "..."}]`; pull the name out without a JSON parser
+fn greet(event: &Event) -> PluginResult {
+    let name = event
+        .payload
+        .split("\"name\":\"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .ok_or("user.created payload has no name")?;
+
+    let count = GREETED.fetch_add(1, Ordering::Relaxed) + 1;
+    let text = greeting(name);
+    host::emit("hello.greeted", &format!("{{\"name\":\"{name}\",\"text\":\"{text}\",\"count\":{count}}}"));
+    Ok(())
+}
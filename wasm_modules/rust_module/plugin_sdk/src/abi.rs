@@ -0,0 +1,407 @@
+/*!
+ * Block protocol and export plumbing
+ *
+ * Hosts pass strings into a module through blocks from `echelon_alloc`,
+ * which keep their length in an 8-byte header, and get byte results back
+ * packed into a `u64` as `(ptr << 32) | len`. On 64-bit native builds the
+ * `u64` is the block pointer and the length is read with `block_len`.
+ *
+ * The `#[doc(hidden)]` items are called by the code the macros expand to.
+ */
+
+use std::alloc::{alloc, dealloc, Layout};
+use std::cell::RefCell;
+
+use crate::{Event, EventHandler, Hook, PluginError};
+
+/// Size header stored in front of every block
+const HEADER: usize = 8;
+
+fn block_layout(size: usize) -> Option<Layout> {
+    Layout::from_size_align(size.checked_add(HEADER)?, HEADER).ok()
+}
+
+/// Allocate a `size` byte block (null on failure)
+pub fn alloc_block(size: usize) -> *mut u8 {
+    let Some(layout) = block_layout(size) else {
+        return std::ptr::null_mut();
+    };
+
+    unsafe {
+        let base = alloc(layout);
+        if base.is_null() {
+            return base;
+        }
+        (base as *mut usize).write(size);
+        base.add(HEADER)
+    }
+}
+
+/// Free a block from `alloc_block`
+///
+/// # Safety
+///
+/// `ptr` must be null or a live pointer returned by `alloc_block`.
+pub unsafe fn free_block(ptr: *mut u8) {
+    if ptr.is_null() {
+        return;
+    }
+
+    let base = ptr.sub(HEADER);
+    let size = (base as *const usize).read();
+    if let Some(layout) = block_layout(size) {
+        dealloc(base, layout);
+    }
+}
+
+/// Length of a block from `alloc_block`
+///
+/// # Safety
+///
+/// `ptr` must be null or a live pointer returned by `alloc_block`.
+pub unsafe fn block_len(ptr: *const u8) -> usize {
+    if ptr.is_null() {
+        return 0;
+    }
+    (ptr.sub(HEADER) as *const usize).read()
+}
+
+/// Pack a block as `(ptr << 32) | len`
+#[cfg(target_pointer_width = "32")]
+pub fn pack(ptr: *mut u8, len: usize) -> u64 {
+    ((ptr as usize as u64) << 32) | len as u64
+}
+
+/// Pack a block as its address (length via `block_len`)
+#[cfg(not(target_pointer_width = "32"))]
+pub fn pack(ptr: *mut u8, _len: usize) -> u64 {
+    ptr as usize as u64
+}
+
+/// Copy bytes into a fresh host-owned block and pack it (`0` on failure)
+pub fn pack_bytes(bytes: &[u8]) -> u64 {
+    let ptr = alloc_block(bytes.len());
+    if ptr.is_null() {
+        return 0;
+    }
+    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len()) };
+    pack(ptr, bytes.len())
+}
+
+/// Take ownership of a packed block written by the host
+///
+/// # Safety
+///
+/// `packed` must be `0` or a packed block from `alloc_block` not freed yet.
+pub unsafe fn take_block(packed: u64) -> Option<Vec<u8>> {
+    #[cfg(target_pointer_width = "32")]
+    let (ptr, len) = ((packed >> 32) as usize as *mut u8, (packed & 0xffff_ffff) as usize);
+    #[cfg(not(target_pointer_width = "32"))]
+    let (ptr, len) = (packed as usize as *mut u8, block_len(packed as usize as *const u8));
+
+    if ptr.is_null() {
+        return None;
+    }
+    let bytes = std::slice::from_raw_parts(ptr, len).to_vec();
+    free_block(ptr);
+    Some(bytes)
+}
+
+/// Borrow a byte buffer from host memory
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes for the duration of the call.
+pub unsafe fn read_bytes<'a>(ptr: *const u8, len: usize) -> &'a [u8] {
+    if ptr.is_null() || len == 0 {
+        return &[];
+    }
+    std::slice::from_raw_parts(ptr, len)
+}
+
+/// Copy a string into a fixed-size array (for custom sections)
+pub const fn const_bytes<const N: usize>(s: &str) -> [u8; N] {
+    let bytes = s.as_bytes();
+    let mut out = [0u8; N];
+    let mut index = 0;
+    while index < N {
+        out[index] = bytes[index];
+        index += 1;
+    }
+    out
+}
+
+/// Host import argument, lowered to a wasm value
+///
+/// Strings and byte slices are passed as `(ptr << 32) | len` into the
+/// plugin's memory, so they only lower on 32-bit targets, where host imports
+/// exist.
+pub trait HostArg {
+    type Abi;
+    fn to_abi(&self) -> Self::Abi;
+}
+
+/// Host import result, lifted from a wasm value
+///
+/// `String`, `Option<String>` and `Vec<u8>` results are packed blocks the
+/// host wrote with the plugin's `echelon_alloc` (`0` for none).
+pub trait HostRet {
+    type Abi: Default;
+    fn from_abi(abi: Self::Abi) -> Self;
+}
+
+macro_rules! scalar_abi {
+    ($($ty:ty),*) => {$(
+        impl HostArg for $ty {
+            type Abi = $ty;
+            fn to_abi(&self) -> $ty {
+                *self
+            }
+        }
+
+        impl HostRet for $ty {
+            type Abi = $ty;
+            fn from_abi(abi: $ty) -> $ty {
+                abi
+            }
+        }
+    )*};
+}
+
+scalar_abi!(u32, i32, u64, i64, f32, f64);
+
+impl HostArg for bool {
+    type Abi = u32;
+    fn to_abi(&self) -> u32 {
+        *self as u32
+    }
+}
+
+impl HostRet for bool {
+    type Abi = u32;
+    fn from_abi(abi: u32) -> bool {
+        abi != 0
+    }
+}
+
+#[cfg(target_pointer_width = "32")]
+impl HostArg for &[u8] {
+    type Abi = u64;
+    fn to_abi(&self) -> u64 {
+        ((self.as_ptr() as usize as u64) << 32) | self.len() as u64
+    }
+}
+
+#[cfg(target_pointer_width = "32")]
+impl HostArg for &str {
+    type Abi = u64;
+    fn to_abi(&self) -> u64 {
+        self.as_bytes().to_abi()
+    }
+}
+
+impl HostRet for () {
+    type Abi = ();
+    fn from_abi(_: ()) {}
+}
+
+impl HostRet for Vec<u8> {
+    type Abi = u64;
+    fn from_abi(abi: u64) -> Vec<u8> {
+        unsafe { take_block(abi) }.unwrap_or_default()
+    }
+}
+
+impl HostRet for Option<String> {
+    type Abi = u64;
+    fn from_abi(abi: u64) -> Option<String> {
+        unsafe { take_block(abi) }.map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+impl HostRet for String {
+    type Abi = u64;
+    fn from_abi(abi: u64) -> String {
+        Option::<String>::from_abi(abi).unwrap_or_default()
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<PluginError>> = const { RefCell::new(None) };
+}
+
+/// Record the outcome of a hook or handler, `0` on success
+fn status(result: Result<(), PluginError>) -> u32 {
+    LAST_ERROR.with(|slot| {
+        let mut slot = slot.borrow_mut();
+        match result {
+            Ok(()) => {
+                *slot = None;
+                0
+            }
+            Err(err) => {
+                *slot = Some(err);
+                1
+            }
+        }
+    })
+}
+
+#[doc(hidden)]
+pub fn run_hook(hook: Hook) -> u32 {
+    status(hook())
+}
+
+/// Deliver an event to the handler subscribed to its name
+///
+/// # Safety
+///
+/// `name` and `payload` must be valid for reads of their lengths.
+#[doc(hidden)]
+pub unsafe fn dispatch_event(
+    name: *const u8,
+    name_len: usize,
+    payload: *const u8,
+    payload_len: usize,
+    handlers: &[(&str, EventHandler)],
+) -> u32 {
+    let text = |ptr, len, what| {
+        std::str::from_utf8(read_bytes(ptr, len))
+            .map_err(|_| PluginError::new(format!("invalid UTF-8 in event {what}")))
+    };
+
+    status((|| {
+        let event = Event {
+            name: text(name, name_len, "name")?,
+            payload: text(payload, payload_len, "payload")?,
+        };
+        let (_, handler) = handlers
+            .iter()
+            .find(|(subscribed, _)| *subscribed == event.name)
+            .ok_or_else(|| PluginError::new(format!("not subscribed to `{}`", event.name)))?;
+        handler(&event)
+    })())
+}
+
+/// Packed message of the last failed hook or handler (`0` if none)
+#[doc(hidden)]
+pub fn last_error() -> u64 {
+    LAST_ERROR.with(|slot| slot.borrow().as_ref().map_or(0, |err| pack_bytes(err.message.as_bytes())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Split a packed result the way the host does for this pointer width
+    unsafe fn unpack(packed: u64) -> (*mut u8, usize) {
+        if cfg!(target_pointer_width = "32") {
+            ((packed >> 32) as usize as *mut u8, (packed & 0xffff_ffff) as usize)
+        } else {
+            let ptr = packed as usize as *mut u8;
+            (ptr, block_len(ptr))
+        }
+    }
+
+    #[test]
+    fn blocks_record_their_length() {
+        for size in [0, 1, 7, 8, 9, 4096] {
+            let ptr = alloc_block(size);
+            assert!(!ptr.is_null());
+            assert_eq!(ptr as usize % HEADER, 0, "block of {size} bytes is misaligned");
+            unsafe {
+                assert_eq!(block_len(ptr), size);
+                std::ptr::write_bytes(ptr, 0xaa, size);
+                free_block(ptr);
+            }
+        }
+        assert!(alloc_block(usize::MAX).is_null());
+        unsafe {
+            assert_eq!(block_len(std::ptr::null()), 0);
+            free_block(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn packed_results_round_trip() {
+        for bytes in [&b""[..], b"x", b"hello world", &[0xff; 300]] {
+            let packed = pack_bytes(bytes);
+            assert_ne!(packed, 0);
+            unsafe {
+                let (ptr, len) = unpack(packed);
+                assert_eq!(std::slice::from_raw_parts(ptr, len), bytes);
+                assert_eq!(block_len(ptr), bytes.len());
+                assert_eq!(take_block(packed).as_deref(), Some(bytes));
+            }
+        }
+        assert_eq!(unsafe { take_block(0) }, None);
+    }
+
+    #[cfg(not(target_pointer_width = "32"))]
+    #[test]
+    fn native_packing_is_the_block_address() {
+        let ptr = alloc_block(5);
+        assert_eq!(pack(ptr, 5), ptr as usize as u64);
+        unsafe { free_block(ptr) };
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn wasm_packing_is_pointer_and_length() {
+        let ptr = alloc_block(5);
+        assert_eq!(pack(ptr, 5), ((ptr as usize as u64) << 32) | 5);
+        unsafe { free_block(ptr) };
+    }
+
+    /// Copy `bytes` into a block, as the host does for a result
+    #[cfg(target_pointer_width = "32")]
+    fn block_with(bytes: &[u8]) -> &'static [u8] {
+        let ptr = alloc_block(bytes.len());
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+            std::slice::from_raw_parts(ptr, bytes.len())
+        }
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn host_args_round_trip_through_results() {
+        for bytes in [&b""[..], b"x", b"hello world", &[0xff; 300]] {
+            // `from_abi` takes the block back and frees it
+            assert_eq!(Vec::<u8>::from_abi(block_with(bytes).to_abi()), bytes);
+        }
+        let s = std::str::from_utf8(block_with("héllo".as_bytes())).unwrap();
+        assert_eq!(String::from_abi(s.to_abi()), "héllo");
+        let s = std::str::from_utf8(block_with(b"cfg")).unwrap();
+        assert_eq!(Option::<String>::from_abi(s.to_abi()).as_deref(), Some("cfg"));
+    }
+
+    #[test]
+    fn host_scalars_round_trip() {
+        for value in [true, false] {
+            assert_eq!(bool::from_abi(value.to_abi()), value);
+        }
+        assert!(bool::from_abi(7));
+        assert_eq!(u32::from_abi(u32::MAX.to_abi()), u32::MAX);
+        assert_eq!(i64::from_abi((-5i64).to_abi()), -5);
+        assert_eq!(f64::from_abi(1.5f64.to_abi()), 1.5);
+    }
+
+    #[test]
+    fn host_results_lift_packed_blocks() {
+        assert_eq!(String::from_abi(pack_bytes(b"out")), "out");
+        assert_eq!(Vec::<u8>::from_abi(pack_bytes(&[1, 2, 3])), [1, 2, 3]);
+        // `0` is "no value"
+        assert_eq!(String::from_abi(0), "");
+        assert_eq!(Option::<String>::from_abi(0), None);
+        assert_eq!(Vec::<u8>::from_abi(0), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn status_records_and_clears_errors() {
+        assert_eq!(run_hook(|| Err(PluginError::new("boom"))), 1);
+        assert_eq!(String::from_abi(last_error()), "boom");
+        assert_eq!(run_hook(|| Ok(())), 0);
+        assert_eq!(last_error(), 0);
+    }
+}
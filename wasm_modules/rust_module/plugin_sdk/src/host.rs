@@ -0,0 +1,61 @@
+/*!
+ * Built-in host functions
+ *
+ * Every plugin host provides these under the `echelon` import module,
+ * backed by the `PluginContext` the plugin was installed with:
+ *
+ * - `log(level: u32, message)` - `context.log`
+ * - `emit(event, payload)` - `context.emit(event, JSON.parse(payload))`
+ * - `config_get(key) -> Option<String>` - `WASMPluginOptions.config`
+ *
+ * These names are reserved for `host_import!` declarations.
+ */
+
+/// Log level passed to `log`
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+}
+
+mod imports {
+    crate::host_import! {
+        pub fn log(level: u32, message: &str);
+        pub fn emit(event: &str, payload: &str);
+        pub fn config_get(key: &str) -> Option<String>;
+    }
+}
+
+/// Write a message to the host log
+pub fn log(level: Level, message: &str) {
+    imports::log(level as u32, message)
+}
+
+pub fn error(message: &str) {
+    log(Level::Error, message)
+}
+
+pub fn warn(message: &str) {
+    log(Level::Warn, message)
+}
+
+pub fn info(message: &str) {
+    log(Level::Info, message)
+}
+
+pub fn debug(message: &str) {
+    log(Level::Debug, message)
+}
+
+/// Emit a host event; `payload` is JSON (delivered parsed to listeners)
+pub fn emit(event: &str, payload: &str) {
+    imports::emit(event, payload)
+}
+
+/// Configuration value for `key`, if the host has one
+pub fn config(key: &str) -> Option<String> {
+    imports::config_get(key)
+}
@@ -0,0 +1,96 @@
+/*!
+ * Echelon plugin SDK
+ *
+ * Write plugins for the framework's `PluginManager` in Rust and ship them as
+ * WASM. A plugin crate is a `cdylib` that declares itself with the macros
+ * below. `loadWASMPlugin` / `PluginManager.registerWASM` in
+ * `framework/plugin/wasm_plugin.ts` do the rest:
+ *
+ * - `plugin!` - name, version, description and dependencies. Stored in the
+ *   `echelon.plugin` custom section so the host can read them without
+ *   instantiating, and emits the `echelon_alloc` block protocol exports.
+ * - `on_init!` / `on_destroy!` - lifecycle hooks, run on install / uninstall.
+ * - `subscribe!` - event names and handlers; the host forwards each
+ *   `context.emit(name, ...args)` with the arguments as a JSON array.
+ * - `host_import!` - typed bindings to functions the host provides under
+ *   the `echelon` import module. `host` wraps the built-in ones (logging,
+ *   emitting events, reading config).
+ *
+ * ```ignore
+ * use echelon_plugin_sdk::{host, on_init, plugin, subscribe, Event, PluginResult};
+ *
+ * plugin! { name: "greeter", version: "0.1.0", dependencies: ["auth"] }
+ * on_init!(init);
+ * subscribe! { "user.created" => greet }
+ *
+ * fn init() -> PluginResult {
+ *     host::info("greeter ready");
+ *     Ok(())
+ * }
+ *
+ * fn greet(event: &Event) -> PluginResult {
+ *     host::emit("greeter.sent", event.payload);
+ *     Ok(())
+ * }
+ * ```
+ *
+ * `echelon_wasm` shares the block protocol in `abi` (`(ptr << 32) | len`
+ * packed results, `echelon_alloc` blocks with a length header).
+ */
+
+pub mod abi;
+pub mod host;
+mod macros;
+
+use std::fmt;
+
+/// Plugin ABI version reported in the metadata
+pub const ABI_VERSION: u32 = 1;
+
+/// Error returned by hooks and event handlers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PluginError {
+    pub message: String,
+}
+
+impl PluginError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { message: message.into() }
+    }
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for PluginError {}
+
+impl From<&str> for PluginError {
+    fn from(message: &str) -> Self {
+        Self::new(message)
+    }
+}
+
+impl From<String> for PluginError {
+    fn from(message: String) -> Self {
+        Self::new(message)
+    }
+}
+
+pub type PluginResult = Result<(), PluginError>;
+
+/// Lifecycle hook
+pub type Hook = fn() -> PluginResult;
+
+/// Event delivered to a `subscribe!` handler
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Event<'a> {
+    pub name: &'a str,
+    /// Emit arguments as a JSON array
+    pub payload: &'a str,
+}
+
+/// Event handler
+pub type EventHandler = fn(&Event) -> PluginResult;
@@ -0,0 +1,216 @@
+/*!
+ * Declaration macros
+ *
+ * Each macro expands to `#[no_mangle]` exports that make up the plugin ABI
+ * read by `framework/plugin/wasm_plugin.ts`:
+ *
+ * | Export | Macro |
+ * |--------|-------|
+ * | `echelon_plugin_metadata() -> u64` (+ `echelon.plugin` section) | `plugin!` |
+ * | `echelon_alloc`, `echelon_dealloc`, `echelon_block_len` | `plugin!` |
+ * | `echelon_plugin_error() -> u64` | `plugin!` |
+ * | `echelon_plugin_init() -> u32` | `on_init!` |
+ * | `echelon_plugin_destroy() -> u32` | `on_destroy!` |
+ * | `echelon_plugin_subscriptions() -> u64` | `subscribe!` |
+ * | `echelon_plugin_event(name, len, payload, len) -> u32` | `subscribe!` |
+ *
+ * Hooks and handlers return `0` on success and `1` on failure, with the
+ * message in `echelon_plugin_error()`. Strings in metadata and event names
+ * are embedded in JSON as written, so keep them free of quotes and
+ * backslashes.
+ */
+
+/// JSON array of string literals
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __json_strings {
+    () => {
+        "[]"
+    };
+    ($first:literal $(, $rest:literal)*) => {
+        concat!("[\"", $first, "\"" $(, ",\"", $rest, "\"")*, "]")
+    };
+}
+
+/// Declare the plugin's metadata and the block protocol exports
+///
+/// ```ignore
+/// plugin! {
+///     name: "greeter",
+///     version: "0.1.0",
+///     description: "Greets new users",
+///     dependencies: ["auth"],
+/// }
+/// ```
+#[macro_export]
+macro_rules! plugin {
+    (
+        name: $name:literal,
+        version: $version:literal
+        $(, description: $description:literal)?
+        $(, dependencies: [$($dependency:literal),* $(,)?])?
+        $(,)?
+    ) => {
+        /// Plugin metadata JSON
+        pub const PLUGIN_METADATA: &str = concat!(
+            "{\"name\":\"", $name, "\",\"version\":\"", $version, "\""
+            $(, ",\"description\":\"", $description, "\"")?
+            $(, ",\"dependencies\":", $crate::__json_strings!($($dependency),*))?
+            , ",\"abi\":1}"
+        );
+
+        #[cfg(target_arch = "wasm32")]
+        #[link_section = "echelon.plugin"]
+        #[used]
+        static __ECHELON_PLUGIN: [u8; PLUGIN_METADATA.len()] = $crate::abi::const_bytes(PLUGIN_METADATA);
+
+        /// Packed plugin metadata JSON
+        #[no_mangle]
+        pub extern "C" fn echelon_plugin_metadata() -> u64 {
+            $crate::abi::pack_bytes(PLUGIN_METADATA.as_bytes())
+        }
+
+        /// Packed message of the last failed hook or handler (`0` if none)
+        #[no_mangle]
+        pub extern "C" fn echelon_plugin_error() -> u64 {
+            $crate::abi::last_error()
+        }
+
+        /// Allocate `size` bytes for the host to write into (null on failure)
+        #[no_mangle]
+        pub extern "C" fn echelon_alloc(size: usize) -> *mut u8 {
+            $crate::abi::alloc_block(size)
+        }
+
+        /// Free a block from `echelon_alloc` or a packed result
+        ///
+        /// # Safety
+        ///
+        /// `ptr` must be null or a live block from `echelon_alloc`.
+        #[no_mangle]
+        pub unsafe extern "C" fn echelon_dealloc(ptr: *mut u8) {
+            $crate::abi::free_block(ptr)
+        }
+
+        /// Length of a block from `echelon_alloc`
+        ///
+        /// # Safety
+        ///
+        /// `ptr` must be null or a live block from `echelon_alloc`.
+        #[no_mangle]
+        pub unsafe extern "C" fn echelon_block_len(ptr: *const u8) -> usize {
+            $crate::abi::block_len(ptr)
+        }
+    };
+}
+
+/// Run `hook: fn() -> PluginResult` when the plugin is installed
+#[macro_export]
+macro_rules! on_init {
+    ($hook:path) => {
+        #[no_mangle]
+        pub extern "C" fn echelon_plugin_init() -> u32 {
+            $crate::abi::run_hook($hook)
+        }
+    };
+}
+
+/// Run `hook: fn() -> PluginResult` when the plugin is uninstalled
+#[macro_export]
+macro_rules! on_destroy {
+    ($hook:path) => {
+        #[no_mangle]
+        pub extern "C" fn echelon_plugin_destroy() -> u32 {
+            $crate::abi::run_hook($hook)
+        }
+    };
+}
+
+/// Subscribe handlers `fn(&Event) -> PluginResult` to host events
+///
+/// ```ignore
+/// subscribe! {
+///     "user.created" => on_user_created,
+///     "user.deleted" => on_user_deleted,
+/// }
+/// ```
+#[macro_export]
+macro_rules! subscribe {
+    ($($event:literal => $handler:path),* $(,)?) => {
+        /// Packed JSON array of the subscribed event names
+        #[no_mangle]
+        pub extern "C" fn echelon_plugin_subscriptions() -> u64 {
+            $crate::abi::pack_bytes($crate::__json_strings!($($event),*).as_bytes())
+        }
+
+        /// Deliver an event to its handler
+        ///
+        /// # Safety
+        ///
+        /// `name` and `payload` must be valid for reads of their lengths.
+        #[no_mangle]
+        pub unsafe extern "C" fn echelon_plugin_event(
+            name: *const u8,
+            name_len: usize,
+            payload: *const u8,
+            payload_len: usize,
+        ) -> u32 {
+            $crate::abi::dispatch_event(
+                name,
+                name_len,
+                payload,
+                payload_len,
+                &[$(($event, $handler as $crate::EventHandler)),*],
+            )
+        }
+    };
+}
+
+/// Declare functions the host provides under the `echelon` import module
+///
+/// Arguments may be `u32`, `i32`, `u64`, `i64`, `f32`, `f64`, `bool`,
+/// `&str` or `&[u8]`; results any of the scalars, `String`,
+/// `Option<String>` or `Vec<u8>` (see `abi::HostArg` / `abi::HostRet`).
+/// Outside wasm32 the bindings return zero values, so plugin code can be
+/// checked and unit-tested natively.
+///
+/// ```ignore
+/// host_import! {
+///     /// Look up a user's display name
+///     pub fn user_name(id: u32) -> Option<String>;
+/// }
+/// ```
+#[macro_export]
+macro_rules! host_import {
+    ($(
+        $(#[$meta:meta])*
+        $vis:vis fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)?;
+    )*) => {$(
+        $(#[$meta])*
+        #[allow(unused_variables)]
+        $vis fn $name($($arg: $ty),*) $(-> $ret)? {
+            #[cfg(target_arch = "wasm32")]
+            {
+                #[link(wasm_import_module = "echelon")]
+                extern "C" {
+                    fn $name($($arg: <$ty as $crate::abi::HostArg>::Abi),*)
+                        -> <$crate::__host_ret!($($ret)?) as $crate::abi::HostRet>::Abi;
+                }
+                let abi = unsafe { $name($($crate::abi::HostArg::to_abi(&$arg)),*) };
+                <$crate::__host_ret!($($ret)?) as $crate::abi::HostRet>::from_abi(abi)
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                <$crate::__host_ret!($($ret)?) as $crate::abi::HostRet>::from_abi(Default::default())
+            }
+        }
+    )*};
+}
+
+/// Result type of a `host_import!` declaration
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __host_ret {
+    () => { () };
+    ($ret:ty) => { $ret };
+}
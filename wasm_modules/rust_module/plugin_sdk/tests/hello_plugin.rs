@@ -0,0 +1,66 @@
+//! What the declaration macros expand to, checked on the sample plugin
+//!
+//! Host imports return zero values outside wasm32, so the example runs
+//! natively without a host.
+
+#[path = "../examples/hello_plugin.rs"]
+mod hello_plugin;
+
+use echelon_plugin_sdk::abi::take_block;
+
+fn take_string(packed: u64) -> String {
+    String::from_utf8(unsafe { take_block(packed) }.expect("a packed block")).unwrap()
+}
+
+/// Send an event the way the host does, returns the status and error message
+fn send(name: &[u8], payload: &[u8]) -> (u32, Option<String>) {
+    let status =
+        unsafe { hello_plugin::echelon_plugin_event(name.as_ptr(), name.len(), payload.as_ptr(), payload.len()) };
+    let error = unsafe { take_block(hello_plugin::echelon_plugin_error()) };
+    (status, error.map(|bytes| String::from_utf8(bytes).unwrap()))
+}
+
+fn failed(message: &str) -> (u32, Option<String>) {
+    (1, Some(message.to_string()))
+}
+
+#[test]
+fn plugin_metadata() {
+    let expected = r#"{"name":"hello","version":"0.1.0","description":"Greets new users","abi":1}"#;
+    assert_eq!(hello_plugin::PLUGIN_METADATA, expected);
+    assert_eq!(take_string(hello_plugin::echelon_plugin_metadata()), expected);
+    assert_eq!(take_string(hello_plugin::echelon_plugin_subscriptions()), r#"["user.created"]"#);
+}
+
+#[test]
+fn lifecycle_hooks() {
+    assert_eq!(hello_plugin::echelon_plugin_init(), 0);
+    assert_eq!(hello_plugin::echelon_plugin_destroy(), 0);
+    assert_eq!(hello_plugin::echelon_plugin_error(), 0);
+}
+
+#[test]
+fn events_reach_their_handler() {
+    assert_eq!(send(b"user.created", br#"[{"name":"Ada"}]"#), (0, None));
+    // The handler's own error shows the event got to `greet`
+    assert_eq!(send(b"user.created", b"[{}]"), failed("user.created payload has no name"));
+    // A success clears the message again
+    assert_eq!(send(b"user.created", br#"[{"name":"Bob"}]"#), (0, None));
+}
+
+#[test]
+fn events_without_handler_fail() {
+    assert_eq!(send(b"user.deleted", b"[]"), failed("not subscribed to `user.deleted`"));
+    assert_eq!(send(b"user.\xff", b"[]"), failed("invalid UTF-8 in event name"));
+    assert_eq!(send(b"user.created", b"\xff"), failed("invalid UTF-8 in event payload"));
+}
+
+#[test]
+fn block_protocol_exports() {
+    let ptr = hello_plugin::echelon_alloc(12);
+    assert!(!ptr.is_null());
+    unsafe {
+        assert_eq!(hello_plugin::echelon_block_len(ptr), 12);
+        hello_plugin::echelon_dealloc(ptr);
+    }
+}
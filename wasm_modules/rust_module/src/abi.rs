@@ -0,0 +1,940 @@
+/*!
+ * Raw C-ABI exports
+ *
+ * Pointer/length entry points for hosts that drive the module without the
+ * wasm-bindgen JS glue (`WASMModuleLoader`, `WASMSandboxManager`,
+ * `NativeWASMRegistry`). Follows the `memory-allocator` template protocol:
+ *
+ * - The host reserves input buffers with `echelon_alloc(size)`, writes UTF-8
+ *   bytes into them and releases them with `echelon_dealloc(ptr)`.
+ * - String results are returned packed into a `u64` as `(ptr << 32) | len`.
+ *   The block is owned by the host and must be released with `echelon_dealloc`.
+ *   On 64-bit native builds (Deno FFI) the `u64` is the block pointer itself
+ *   and the length is read with `echelon_block_len(ptr)`.
+ * - Booleans are returned as `0`/`1`, counts as `u32`.
+ * - On failure a zero value is returned and the error is available through
+ *   `echelon_last_error_code()` / `echelon_last_error_message()`.
+ *
+ * The block protocol itself lives in `echelon_plugin_sdk::abi`, shared with
+ * plugins built on the SDK.
+ */
+
+use echelon_plugin_sdk::abi as block;
+
+#[cfg(all(feature = "batch", any(feature = "text", feature = "hash")))]
+use crate::batch;
+use crate::error::{self, guard, EchelonError, ErrorCode, Result};
+#[cfg(feature = "parallel")]
+use crate::parallel::{BatchOp, ParallelJob};
+
+/// Allocate `size` bytes for the host to write into (null on failure)
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_alloc(size: usize) -> *mut u8 {
+    block::alloc_block(size)
+}
+
+/// Free a block returned by `echelon_alloc` or by a packed string result
+///
+/// # Safety
+///
+/// `ptr` must be null or a pointer previously returned by `echelon_alloc`
+/// (directly or inside a packed result) that has not been freed yet.
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_dealloc(ptr: *mut u8) {
+    block::free_block(ptr)
+}
+
+/// Length of a block returned by `echelon_alloc` or by a packed string result
+///
+/// # Safety
+///
+/// `ptr` must be null or a live pointer previously returned by `echelon_alloc`.
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_block_len(ptr: *const u8) -> usize {
+    block::block_len(ptr)
+}
+
+/// Copy bytes into a fresh host-owned block and pack it
+pub(crate) fn pack_bytes(bytes: &[u8]) -> Result<u64> {
+    let ptr = echelon_alloc(bytes.len());
+    if ptr.is_null() {
+        return Err(EchelonError::new(
+            ErrorCode::OutOfMemory,
+            format!("cannot allocate {} byte result", bytes.len()),
+        ));
+    }
+
+    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len()) };
+    Ok(block::pack(ptr, bytes.len()))
+}
+
+/// Borrow a byte buffer from host memory
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes for the duration of the call.
+pub(crate) unsafe fn read_bytes<'a>(ptr: *const u8, len: usize) -> &'a [u8] {
+    block::read_bytes(ptr, len)
+}
+
+/// Borrow a UTF-8 string from host memory
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes for the duration of the call.
+pub(crate) unsafe fn read_str<'a>(ptr: *const u8, len: usize) -> Result<&'a str> {
+    std::str::from_utf8(read_bytes(ptr, len)).map_err(|err| {
+        EchelonError::new(
+            ErrorCode::InvalidUtf8,
+            format!("invalid UTF-8 at byte {}", err.valid_up_to()),
+        )
+    })
+}
+
+/// Error code of the last failed call (`0` when the last call succeeded)
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_last_error_code() -> u32 {
+    error::last_error_code()
+}
+
+/// Message of the last failed call (packed result, `0` when there is none)
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_last_error_message() -> u64 {
+    match error::last_error() {
+        Some(err) => pack_bytes(err.message.as_bytes()).unwrap_or(0),
+        None => 0,
+    }
+}
+
+/// Install the host logger (`0` = off ... `5` = trace), `1` if logging is active
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_init_logging(level: u32) -> u32 {
+    crate::logging::init_logging(level) as u32
+}
+
+/// Manifest JSON describing every export of this build (packed result)
+#[no_mangle]
+pub extern "C" fn echelon_manifest() -> u64 {
+    guard(|| pack_bytes(crate::manifest::MANIFEST_JSON.as_bytes()))
+}
+
+/// Call a method with a CBOR (`0`) or MessagePack (`1`) argument map (packed result, same encoding)
+///
+/// # Safety
+///
+/// `method_ptr` / `args_ptr` must be valid for reads of `method_len` / `args_len` bytes.
+#[cfg(feature = "codec")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_call_encoded(
+    method_ptr: *const u8,
+    method_len: usize,
+    args_ptr: *const u8,
+    args_len: usize,
+    encoding: u32,
+) -> u64 {
+    guard(|| {
+        let encoding = crate::codec::Encoding::from_u32(encoding)?;
+        let method = read_str(method_ptr, method_len)?;
+        pack_bytes(&crate::codec::call(encoding, method, read_bytes(args_ptr, args_len))?)
+    })
+}
+
+/// ABI contract version of this build
+#[no_mangle]
+pub extern "C" fn echelon_abi_version() -> u32 {
+    crate::version::ABI_VERSION
+}
+
+/// Whether this build enables a cargo feature or contract (`0`/`1`)
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn echelon_supports(ptr: *const u8, len: usize) -> u32 {
+    guard(|| Ok(crate::version::supports(read_str(ptr, len)?) as u32))
+}
+
+/// Bytes currently allocated inside the module
+#[cfg(feature = "heap-stats")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_heap_live_bytes() -> usize {
+    crate::heap::heap_stats().live_bytes
+}
+
+/// Highest live bytes since start or `echelon_heap_reset_peak`
+#[cfg(feature = "heap-stats")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_heap_peak_bytes() -> usize {
+    crate::heap::heap_stats().peak_bytes
+}
+
+/// Successful allocations since start
+#[cfg(feature = "heap-stats")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_heap_allocations() -> usize {
+    crate::heap::heap_stats().allocations
+}
+
+/// Deallocations since start
+#[cfg(feature = "heap-stats")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_heap_deallocations() -> usize {
+    crate::heap::heap_stats().deallocations
+}
+
+/// Successful reallocations since start
+#[cfg(feature = "heap-stats")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_heap_reallocations() -> usize {
+    crate::heap::heap_stats().reallocations
+}
+
+/// Bytes claimed by the allocator
+#[cfg(feature = "heap-stats")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_heap_size() -> usize {
+    crate::heap::heap_size()
+}
+
+/// Share of the heap not holding live data (0.0 - 1.0)
+#[cfg(feature = "heap-stats")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_heap_fragmentation() -> f64 {
+    crate::heap::fragmentation()
+}
+
+/// Reset the peak to the current live bytes
+#[cfg(feature = "heap-stats")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_heap_reset_peak() {
+    crate::heap::reset_heap_peak()
+}
+
+/// Start a request scope (see `crate::arena`), returns the nesting depth
+#[cfg(feature = "arena")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_begin_scope() -> u32 {
+    crate::arena::begin_scope()
+}
+
+/// Free every allocation made since the outermost `echelon_begin_scope`
+#[cfg(feature = "arena")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_end_scope() -> usize {
+    crate::arena::end_scope()
+}
+
+/// Bytes held in reserve by the arena chunks
+#[cfg(feature = "arena")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_arena_capacity() -> usize {
+    crate::arena::arena_capacity()
+}
+
+/// Set the fuel budget for the following calls (`0` = unlimited)
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_set_fuel(units: u32) {
+    crate::fuel::set_fuel(units)
+}
+
+/// Fuel units left (`u32::MAX` when unlimited)
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_fuel_remaining() -> u32 {
+    crate::fuel::fuel_remaining()
+}
+
+/// Fuel units drawn since the last `echelon_set_fuel`
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_fuel_used() -> u32 {
+    crate::fuel::fuel_used()
+}
+
+/// `1` if a call suspended with `BudgetExhausted` can be resumed
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_is_suspended() -> u32 {
+    crate::fuel::is_suspended() as u32
+}
+
+/// Drop the progress of a suspended call
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_cancel_suspended() {
+    crate::fuel::cancel_suspended()
+}
+
+/// Switch to the seeded generator and a virtual clock at `start_ms`
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_set_deterministic(seed: u64, start_ms: f64) {
+    crate::entropy::set_deterministic(seed, start_ms)
+}
+
+/// Return to the host entropy source
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_clear_deterministic() {
+    crate::entropy::clear_deterministic()
+}
+
+/// `1` in deterministic mode
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_is_deterministic() -> u32 {
+    crate::entropy::is_deterministic() as u32
+}
+
+/// Move the virtual clock forward by `ms`
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_advance_clock(ms: f64) {
+    crate::entropy::advance_clock(ms)
+}
+
+/// Current time in milliseconds (virtual in deterministic mode)
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_now_ms() -> f64 {
+    crate::entropy::now_ms()
+}
+
+/// Random `u32`
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_random_u32() -> u32 {
+    crate::entropy::random_u32()
+}
+
+/// Random version 4 UUID (packed result)
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_uuid_v4() -> u64 {
+    guard(|| pack_bytes(crate::entropy::uuid_v4().as_bytes()))
+}
+
+/// Count vowels in a string
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(feature = "text")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_count_vowels(ptr: *const u8, len: usize) -> u32 {
+    guard(|| Ok(crate::count_vowels(read_str(ptr, len)?) as u32))
+}
+
+/// Reverse a string (packed result)
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(feature = "text")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_reverse_string(ptr: *const u8, len: usize) -> u64 {
+    guard(|| pack_bytes(crate::reverse_string(read_str(ptr, len)?).as_bytes()))
+}
+
+/// Check if string is palindrome (`1` or `0`)
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(feature = "text")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_is_palindrome(ptr: *const u8, len: usize) -> u32 {
+    guard(|| Ok(crate::is_palindrome(read_str(ptr, len)?) as u32))
+}
+
+/// Calculate DJB2 hash of string
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(feature = "hash")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_hash_string(ptr: *const u8, len: usize) -> u32 {
+    guard(|| Ok(crate::hash_string(read_str(ptr, len)?)))
+}
+
+/// Find longest word in a string
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(feature = "text")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_longest_word_length(ptr: *const u8, len: usize) -> u32 {
+    guard(|| Ok(crate::longest_word_length(read_str(ptr, len)?) as u32))
+}
+
+/// Count words in a string
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(feature = "text")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_word_count(ptr: *const u8, len: usize) -> u32 {
+    guard(|| Ok(crate::word_count(read_str(ptr, len)?) as u32))
+}
+
+/// Caesar cipher encryption (packed result)
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(feature = "cipher")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_caesar_encrypt(ptr: *const u8, len: usize, shift: u8) -> u64 {
+    guard(|| pack_bytes(crate::caesar_encrypt(read_str(ptr, len)?, shift).as_bytes()))
+}
+
+/// Add two numbers (reports `Overflow`)
+#[cfg(feature = "math")]
+#[no_mangle]
+pub extern "C" fn echelon_add(a: i32, b: i32) -> i32 {
+    crate::add(a, b)
+}
+
+/// Multiply two numbers (reports `Overflow`)
+#[cfg(feature = "math")]
+#[no_mangle]
+pub extern "C" fn echelon_multiply(a: i32, b: i32) -> i32 {
+    crate::multiply(a, b)
+}
+
+/// Memory allocation test - creates a vector and sums it (wrapping sum)
+#[cfg(feature = "math")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_memory_intensive(size: usize) -> i32 {
+    crate::memory_intensive(size)
+}
+
+/// Hash every string of a length-prefixed batch (packed `u32` LE array)
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(all(feature = "batch", feature = "hash"))]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_hash_strings(ptr: *const u8, len: usize) -> u64 {
+    batch_u32(ptr, len, crate::hash_string)
+}
+
+/// Count vowels in every string of a length-prefixed batch (packed `u32` LE array)
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(all(feature = "batch", feature = "text"))]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_vowel_counts(ptr: *const u8, len: usize) -> u64 {
+    batch_u32(ptr, len, |s| crate::count_vowels(s) as u32)
+}
+
+/// Count words in every string of a length-prefixed batch (packed `u32` LE array)
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(all(feature = "batch", feature = "text"))]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_word_counts(ptr: *const u8, len: usize) -> u64 {
+    batch_u32(ptr, len, |s| crate::word_count(s) as u32)
+}
+
+/// Longest word length of every string of a length-prefixed batch (packed `u32` LE array)
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(all(feature = "batch", feature = "text"))]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_longest_word_lengths(ptr: *const u8, len: usize) -> u64 {
+    batch_u32(ptr, len, |s| crate::longest_word_length(s) as u32)
+}
+
+/// Palindrome flags of a length-prefixed batch (packed byte array of `0`/`1`)
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(all(feature = "batch", feature = "text"))]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_palindrome_flags(ptr: *const u8, len: usize) -> u64 {
+    guard(|| {
+        let items = batch::decode_strings(read_bytes(ptr, len))?;
+        pack_bytes(&batch::map_batch(&items, |s| crate::is_palindrome(s) as u8))
+    })
+}
+
+#[cfg(all(feature = "batch", any(feature = "text", feature = "hash")))]
+unsafe fn batch_u32(ptr: *const u8, len: usize, f: impl Fn(&str) -> u32) -> u64 {
+    guard(|| {
+        let items = batch::decode_strings(read_bytes(ptr, len))?;
+        pack_bytes(&batch::encode_u32s(&batch::map_batch(&items, f)))
+    })
+}
+
+/// Start a parallel batch job (`BatchOp` value, length-prefixed batch), null on error
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(feature = "parallel")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_par_begin(op: u32, ptr: *const u8, len: usize) -> *mut ParallelJob {
+    let job = guard(|| {
+        let op = BatchOp::from_u32(op)
+            .ok_or_else(|| EchelonError::new(ErrorCode::InvalidArgument, format!("unknown batch op {op}")))?;
+        Ok(Some(Box::new(ParallelJob::new(op, read_bytes(ptr, len))?)))
+    });
+    job.map_or(std::ptr::null_mut(), Box::into_raw)
+}
+
+/// Work on a job until no items are left to claim, returns the items processed
+///
+/// Call from every worker sharing the memory and from the thread that began it.
+///
+/// # Safety
+///
+/// `job` must come from `echelon_par_begin` and not be finished yet.
+#[cfg(feature = "parallel")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_par_work(job: *const ParallelJob) -> u32 {
+    job.as_ref().map_or(0, |job| job.work() as u32)
+}
+
+/// Items of a job not finished yet
+///
+/// # Safety
+///
+/// `job` must come from `echelon_par_begin` and not be finished yet.
+#[cfg(feature = "parallel")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_par_pending(job: *const ParallelJob) -> u32 {
+    job.as_ref().map_or(0, |job| job.pending() as u32)
+}
+
+/// Packed results of a completed job (as the matching batch export); frees the job
+///
+/// Fails with `InvalidArgument` and keeps the job while items are pending.
+///
+/// # Safety
+///
+/// `job` must come from `echelon_par_begin`, with no worker still inside
+/// `echelon_par_work`.
+#[cfg(feature = "parallel")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_par_finish(job: *mut ParallelJob) -> u64 {
+    let Some(job_ref) = job.as_ref() else {
+        return 0;
+    };
+    let packed = guard(|| pack_bytes(&job_ref.encode()?));
+    if error::last_error().is_none() {
+        drop(Box::from_raw(job));
+    }
+    packed
+}
+
+/// Free a job without reading its results
+///
+/// # Safety
+///
+/// `job` must come from `echelon_par_begin`, with no worker still inside
+/// `echelon_par_work`.
+#[cfg(feature = "parallel")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_par_cancel(job: *mut ParallelJob) {
+    if !job.is_null() {
+        drop(Box::from_raw(job));
+    }
+}
+
+/// Handle one serialized HTTP request (see `crate::http`), packed response
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(feature = "http")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_handle_request(ptr: *const u8, len: usize) -> u64 {
+    guard(|| pack_bytes(&crate::http::handle(read_bytes(ptr, len))?))
+}
+
+/// Serialize the module state (see `crate::snapshot`), packed result
+#[cfg(feature = "snapshot")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_snapshot() -> u64 {
+    guard(|| pack_bytes(&crate::snapshot::encode()))
+}
+
+/// Restore state from `echelon_snapshot` bytes, `1` on success
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(feature = "snapshot")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_restore(ptr: *const u8, len: usize) -> u32 {
+    guard(|| crate::snapshot::decode(read_bytes(ptr, len)).map(|()| 1))
+}
+
+/// Known-answer self-test report (JSON, see `crate::selftest`), packed result
+#[cfg(feature = "selftest")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_run_self_test() -> u64 {
+    guard(|| pack_bytes(crate::selftest::self_test().as_bytes()))
+}
+
+/// Benchmark report over `iterations` calls per export (JSON), packed result
+#[cfg(feature = "selftest")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_run_benchmarks(iterations: u32) -> u64 {
+    guard(|| pack_bytes(crate::selftest::benchmarks(iterations)?.as_bytes()))
+}
+
+/// Input region grown to at least `capacity` bytes (see `crate::shared`), null on failure
+#[cfg(feature = "shared-buffers")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_input_buffer(capacity: usize) -> *mut u8 {
+    crate::shared::input_buffer(capacity) as *mut u8
+}
+
+/// Bytes the input region can hold
+#[cfg(feature = "shared-buffers")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_input_capacity() -> usize {
+    crate::shared::input_capacity()
+}
+
+/// Output region holding the last `*_shared` string result
+#[cfg(feature = "shared-buffers")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_output_buffer() -> *const u8 {
+    crate::shared::output_buffer() as *const u8
+}
+
+/// Free the input and output regions
+#[cfg(feature = "shared-buffers")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_release_shared_buffers() {
+    crate::shared::release_shared_buffers()
+}
+
+/// Count vowels in the first `len` input region bytes
+#[cfg(all(feature = "shared-buffers", feature = "text"))]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_count_vowels_shared(len: usize) -> u32 {
+    crate::shared::count_vowels_shared(len) as u32
+}
+
+/// Reverse the first `len` input region bytes into the output region, returns its length
+#[cfg(all(feature = "shared-buffers", feature = "text"))]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_reverse_string_shared(len: usize) -> usize {
+    crate::shared::reverse_string_shared(len)
+}
+
+/// Check if the first `len` input region bytes are a palindrome (`1` or `0`)
+#[cfg(all(feature = "shared-buffers", feature = "text"))]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_is_palindrome_shared(len: usize) -> u32 {
+    crate::shared::is_palindrome_shared(len) as u32
+}
+
+/// DJB2 hash of the first `len` input region bytes
+#[cfg(all(feature = "shared-buffers", feature = "hash"))]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_hash_string_shared(len: usize) -> u32 {
+    crate::shared::hash_string_shared(len)
+}
+
+/// Longest word in the first `len` input region bytes
+#[cfg(all(feature = "shared-buffers", feature = "text"))]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_longest_word_length_shared(len: usize) -> u32 {
+    crate::shared::longest_word_length_shared(len) as u32
+}
+
+/// Count words in the first `len` input region bytes
+#[cfg(all(feature = "shared-buffers", feature = "text"))]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_word_count_shared(len: usize) -> u32 {
+    crate::shared::word_count_shared(len) as u32
+}
+
+/// Caesar cipher of the first `len` input region bytes into the output region, returns its length
+#[cfg(all(feature = "shared-buffers", feature = "cipher"))]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_caesar_encrypt_shared(len: usize, shift: u8) -> usize {
+    crate::shared::caesar_encrypt_shared(len, shift)
+}
+
+/// Report the outcome of an `env.kv_*` import call (see `crate::kv`)
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(feature = "host-kv")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_kv_complete(op: u32, status: u32, ptr: *const u8, len: usize) {
+    crate::kv::complete(op, status, read_bytes(ptr, len));
+    crate::kv::run_ready()
+}
+
+/// Start indexing `text` under a document id, returns the task (`0` on failure)
+///
+/// The task result is the number of distinct words as a `u32` LE.
+///
+/// # Safety
+///
+/// `id_ptr` / `text_ptr` must be valid for reads of `id_len` / `text_len` bytes.
+#[cfg(feature = "host-kv")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_index_document(
+    id_ptr: *const u8,
+    id_len: usize,
+    text_ptr: *const u8,
+    text_len: usize,
+) -> u32 {
+    guard(|| {
+        // Owned by the task, which outlives any request scope
+        let doc_id = crate::persistent(|| read_str(id_ptr, id_len).map(str::to_string))?;
+        let text = crate::persistent(|| read_str(text_ptr, text_len).map(str::to_string))?;
+        Ok(crate::kv::spawn(async move {
+            Ok(crate::kv::index(&doc_id, &text).await?.to_le_bytes().to_vec())
+        }))
+    })
+}
+
+/// Start a search for documents containing every word, returns the task (`0` on failure)
+///
+/// The task result is the newline-separated document ids.
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(feature = "host-kv")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_search_index(ptr: *const u8, len: usize) -> u32 {
+    guard(|| {
+        let query = crate::persistent(|| read_str(ptr, len).map(str::to_string))?;
+        Ok(crate::kv::spawn(async move { Ok(crate::kv::search(&query).await?.join("\n").into_bytes()) }))
+    })
+}
+
+/// Start listing indexed words with a prefix, returns the task (`0` on failure)
+///
+/// The task result is the newline-separated words.
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of `len` bytes.
+#[cfg(feature = "host-kv")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub unsafe extern "C" fn echelon_indexed_words(ptr: *const u8, len: usize, limit: u32) -> u32 {
+    guard(|| {
+        let prefix = crate::persistent(|| read_str(ptr, len).map(str::to_string))?;
+        Ok(crate::kv::spawn(async move { Ok(crate::kv::indexed(&prefix, limit).await?.join("\n").into_bytes()) }))
+    })
+}
+
+/// `0` unknown task, `1` waiting on the host, `2` finished
+#[cfg(feature = "host-kv")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_task_state(task: u32) -> u32 {
+    crate::kv::task_state(task)
+}
+
+/// Result of a finished task (packed result); forgets the task
+#[cfg(feature = "host-kv")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_task_take(task: u32) -> u64 {
+    guard(|| pack_bytes(&crate::kv::take_task(task)?))
+}
+
+/// Drop a task and ignore its outstanding host operations
+#[cfg(feature = "host-kv")]
+#[doc(alias = "impure")]
+#[no_mangle]
+pub extern "C" fn echelon_task_cancel(task: u32) {
+    crate::kv::cancel_task(task)
+}
+
+/// The C ABI driven the way `wasm_ffi.ts` drives it, against the Rust functions
+#[cfg(test)]
+#[cfg_attr(not(all(feature = "text", feature = "hash", feature = "cipher")), allow(dead_code))]
+mod tests {
+    use super::*;
+
+    const INPUTS: &[&str] = &[
+        "",
+        "Hello, World!",
+        "A man, a plan, a canal: Panama",
+        "héllo wörld",
+        "日本語 😀 text",
+    ];
+
+    /// Copy `bytes` into a host-written block, as the host does for arguments
+    fn host_block(bytes: &[u8]) -> *mut u8 {
+        let ptr = echelon_alloc(bytes.len());
+        assert!(!ptr.is_null());
+        unsafe {
+            assert_eq!(echelon_block_len(ptr), bytes.len());
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+        }
+        ptr
+    }
+
+    /// Read and free a packed result: `(ptr << 32) | len` on wasm32, the block
+    /// address plus `echelon_block_len` on 64-bit
+    fn take(packed: u64) -> Option<Vec<u8>> {
+        let (ptr, len) = if cfg!(target_pointer_width = "32") {
+            ((packed >> 32) as usize as *mut u8, (packed & 0xffff_ffff) as usize)
+        } else {
+            let ptr = packed as usize as *mut u8;
+            (ptr, unsafe { echelon_block_len(ptr) })
+        };
+        if ptr.is_null() {
+            return None;
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(ptr, len).to_vec() };
+        unsafe { echelon_dealloc(ptr) };
+        Some(bytes)
+    }
+
+    /// Call a pointer/length export on `bytes`
+    fn call<T>(bytes: &[u8], f: unsafe extern "C" fn(*const u8, usize) -> T) -> T {
+        let ptr = host_block(bytes);
+        let out = unsafe { f(ptr, bytes.len()) };
+        unsafe { echelon_dealloc(ptr) };
+        out
+    }
+
+    fn last_message() -> String {
+        String::from_utf8(take(echelon_last_error_message()).unwrap_or_default()).unwrap()
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn packed_result_is_block_address_on_64_bit() {
+        let packed = pack_bytes(b"hello").unwrap();
+        assert_eq!(unsafe { echelon_block_len(packed as usize as *const u8) }, 5);
+        assert_eq!(take(packed).as_deref(), Some(&b"hello"[..]));
+    }
+
+    #[test]
+    fn empty_results_are_live_blocks() {
+        let packed = pack_bytes(b"").unwrap();
+        assert_ne!(packed, 0);
+        assert_eq!(take(packed), Some(Vec::new()));
+        assert_eq!(unsafe { echelon_block_len(std::ptr::null()) }, 0);
+    }
+
+    #[cfg(all(feature = "text", feature = "hash", feature = "cipher"))]
+    #[test]
+    fn c_abi_matches_rust_api() {
+        for input in INPUTS {
+            let bytes = input.as_bytes();
+            assert_eq!(call(bytes, echelon_count_vowels), crate::count_vowels(input) as u32);
+            assert_eq!(call(bytes, echelon_is_palindrome), crate::is_palindrome(input) as u32);
+            assert_eq!(call(bytes, echelon_hash_string), crate::hash_string(input));
+            assert_eq!(call(bytes, echelon_longest_word_length), crate::longest_word_length(input) as u32);
+            assert_eq!(call(bytes, echelon_word_count), crate::word_count(input) as u32);
+
+            let reversed = take(call(bytes, echelon_reverse_string)).unwrap();
+            assert_eq!(reversed, crate::reverse_string(input).into_bytes());
+            let ptr = host_block(bytes);
+            let encrypted = take(unsafe { echelon_caesar_encrypt(ptr, bytes.len(), 3) }).unwrap();
+            unsafe { echelon_dealloc(ptr) };
+            assert_eq!(encrypted, crate::caesar_encrypt(input, 3).into_bytes());
+            assert_eq!(echelon_last_error_code(), 0);
+        }
+    }
+
+    #[cfg(all(feature = "batch", feature = "text", feature = "hash"))]
+    #[test]
+    fn batch_abi_matches_rust_api() {
+        let items: Vec<String> = INPUTS.iter().map(|s| s.to_string()).collect();
+        let buf = batch::encode_strings(&items);
+        let hashes = take(call(&buf, echelon_hash_strings)).unwrap();
+        assert_eq!(hashes, batch::encode_u32s(&batch::hash_strings(items.clone())));
+        let flags = take(call(&buf, echelon_palindrome_flags)).unwrap();
+        assert_eq!(flags, batch::palindrome_flags(items));
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn invalid_input_reports_errors() {
+        assert_eq!(call(b"ab\xffcd", echelon_count_vowels), 0);
+        assert_eq!(echelon_last_error_code(), ErrorCode::InvalidUtf8 as u32);
+        assert_eq!(last_message(), "invalid UTF-8 at byte 2");
+        assert_eq!(call(b"ab\xffcd", echelon_reverse_string), 0);
+
+        // The next good call clears the slot
+        assert_eq!(call(b"aeiou", echelon_count_vowels), 5);
+        assert_eq!(echelon_last_error_code(), 0);
+        assert_eq!(echelon_last_error_message(), 0);
+    }
+
+    #[cfg(feature = "math")]
+    #[test]
+    fn math_reports_overflow() {
+        assert_eq!(echelon_add(i32::MAX, 1), 0);
+        assert_eq!(echelon_last_error_code(), ErrorCode::Overflow as u32);
+        assert_eq!(last_message(), "add: integer overflow");
+        assert_eq!(echelon_multiply(6, 7), 42);
+        assert_eq!(echelon_last_error_code(), 0);
+    }
+}
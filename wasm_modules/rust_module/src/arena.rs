@@ -0,0 +1,229 @@
+/*!
+ * Request-scoped bump arena
+ *
+ * Between `begin_scope()` and `end_scope()` the global allocator hands out
+ * memory by bumping a pointer through arena chunks instead of calling the
+ * system allocator. `end_scope()` frees everything allocated in the scope at
+ * once and keeps the chunks for the next scope, so a long-lived instance
+ * (one per `wasmMiddleware` worker) stops creeping up between requests.
+ *
+ * Rules for hosts:
+ *
+ * - Read every result (packed strings, `last_error_message`) before
+ *   `end_scope()`; anything allocated inside the scope is invalid after it.
+ * - Scopes nest; only the outermost `end_scope()` releases memory.
+ * - The arena is module-global. Allocations of every thread go to it while
+ *   a scope is open, so only use scopes on single-threaded instances.
+ *
+ * Blocks that existed before the scope keep using the system allocator,
+ * including when they are reallocated inside it.
+ *
+ * Rule for module code: state that outlives a call (caches, lazily built
+ * tables, buffers kept between calls, pending operations) must be allocated
+ * and grown inside `persistent(|| ...)`. It may be touched for the first time
+ * inside a scope, and arena memory is handed out again after `end_scope()`.
+ * `persistent` bypasses the arena for its duration; arena blocks reallocated
+ * inside it move to the system allocator.
+ */
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use wasm_bindgen::prelude::*;
+
+/// Size of the first chunk; each new chunk doubles
+const FIRST_CHUNK: usize = 64 * 1024;
+/// Chunks kept per arena (later allocations fall back to the system allocator)
+const MAX_CHUNKS: usize = 16;
+const CHUNK_ALIGN: usize = 16;
+
+#[derive(Clone, Copy)]
+struct Chunk {
+    start: usize,
+    size: usize,
+}
+
+struct State {
+    depth: u32,
+    chunks: [Chunk; MAX_CHUNKS],
+    /// Chunk currently bumped into
+    current: usize,
+    /// Bump offset inside the current chunk
+    offset: usize,
+    /// Bytes handed out since the outermost `begin_scope`
+    used: usize,
+}
+
+struct Arena {
+    lock: AtomicBool,
+    state: UnsafeCell<State>,
+}
+
+// SAFETY: `state` is only accessed while holding `lock`
+unsafe impl Sync for Arena {}
+
+static ARENA: Arena = Arena {
+    lock: AtomicBool::new(false),
+    state: UnsafeCell::new(State {
+        depth: 0,
+        chunks: [Chunk { start: 0, size: 0 }; MAX_CHUNKS],
+        current: 0,
+        offset: 0,
+        used: 0,
+    }),
+};
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static CHUNKS: AtomicUsize = AtomicUsize::new(0);
+/// Open `persistent` calls
+static PERSISTENT: AtomicUsize = AtomicUsize::new(0);
+
+fn with_state<T>(f: impl FnOnce(&mut State) -> T) -> T {
+    while ARENA
+        .lock
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        std::hint::spin_loop();
+    }
+    let result = f(unsafe { &mut *ARENA.state.get() });
+    ARENA.lock.store(false, Ordering::Release);
+    result
+}
+
+/// Whether allocations go to the arena (a scope is open and not bypassed)
+pub(crate) fn active() -> bool {
+    ACTIVE.load(Ordering::Relaxed) && PERSISTENT.load(Ordering::Relaxed) == 0
+}
+
+/// Run `f` with the arena bypassed, so what it allocates outlives the scope
+pub fn persistent<T>(f: impl FnOnce() -> T) -> T {
+    struct Resume;
+
+    impl Drop for Resume {
+        fn drop(&mut self) {
+            PERSISTENT.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    PERSISTENT.fetch_add(1, Ordering::Relaxed);
+    let _resume = Resume;
+    f()
+}
+
+/// Whether `ptr` points into an arena chunk
+pub(crate) fn contains(ptr: *mut u8) -> bool {
+    let count = CHUNKS.load(Ordering::Acquire);
+    if count == 0 {
+        return false;
+    }
+    let addr = ptr as usize;
+    with_state(|state| state.chunks[..count].iter().any(|c| addr >= c.start && addr < c.start + c.size))
+}
+
+/// Bump-allocate `layout`, `None` if the arena cannot serve it
+pub(crate) fn alloc(layout: Layout) -> Option<*mut u8> {
+    with_state(|state| {
+        if state.depth == 0 {
+            return None;
+        }
+
+        loop {
+            let count = CHUNKS.load(Ordering::Relaxed);
+            if state.current < count {
+                let chunk = state.chunks[state.current];
+                let start = (chunk.start + state.offset).checked_add(layout.align() - 1)? & !(layout.align() - 1);
+                let end = start.checked_add(layout.size())?;
+                if end <= chunk.start + chunk.size {
+                    state.offset = end - chunk.start;
+                    state.used += layout.size();
+                    return Some(start as *mut u8);
+                }
+                if state.current + 1 < count {
+                    state.current += 1;
+                    state.offset = 0;
+                    continue;
+                }
+            }
+
+            // Out of chunks: add one big enough for this allocation
+            if count == MAX_CHUNKS {
+                return None;
+            }
+            let size = (FIRST_CHUNK << count).max(layout.size().checked_add(layout.align())?);
+            let chunk_layout = Layout::from_size_align(size, CHUNK_ALIGN).ok()?;
+            let start = unsafe { System.alloc(chunk_layout) };
+            if start.is_null() {
+                return None;
+            }
+            state.chunks[count] = Chunk { start: start as usize, size };
+            CHUNKS.store(count + 1, Ordering::Release);
+            state.current = count;
+            state.offset = 0;
+        }
+    })
+}
+
+/// Open a scope, returns the new nesting depth
+pub fn enter() -> u32 {
+    with_state(|state| {
+        state.depth += 1;
+        ACTIVE.store(true, Ordering::Relaxed);
+        state.depth
+    })
+}
+
+/// Current nesting depth (0 outside any scope)
+pub fn depth() -> u32 {
+    with_state(|state| state.depth)
+}
+
+/// Close a scope, returns the bytes released (0 unless outermost)
+pub fn exit() -> usize {
+    let released = with_state(|state| {
+        match state.depth {
+            0 => return 0,
+            1 => {}
+            _ => {
+                state.depth -= 1;
+                return 0;
+            }
+        }
+        state.depth = 0;
+        ACTIVE.store(false, Ordering::Relaxed);
+        state.current = 0;
+        state.offset = 0;
+        std::mem::take(&mut state.used)
+    });
+
+    crate::heap::release(released);
+    released
+}
+
+/// Start a request scope: allocations until `end_scope` are freed together
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn begin_scope() -> u32 {
+    enter()
+}
+
+/// End the request scope and free its allocations, returns bytes released
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn end_scope() -> usize {
+    if depth() == 1 {
+        // The last error and parked progress may live in the scope
+        crate::error::clear_last_error();
+        crate::fuel::cancel_suspended();
+    }
+    exit()
+}
+
+/// Bytes held in reserve by the arena chunks
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn arena_capacity() -> usize {
+    let count = CHUNKS.load(Ordering::Acquire);
+    with_state(|state| state.chunks[..count].iter().map(|c| c.size).sum())
+}
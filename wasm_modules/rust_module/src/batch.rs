@@ -0,0 +1,176 @@
+/*!
+ * Batch entry points
+ *
+ * Process many strings per call to amortize the JS↔WASM boundary crossing.
+ * wasm-bindgen callers pass a JS `string[]` and get a typed array back; raw
+ * ABI callers pass a length-prefixed buffer:
+ *
+ * ```text
+ * [count: u32 LE] ([len: u32 LE] [len bytes of UTF-8])*count
+ * ```
+ *
+ * and get a packed result holding `count` little-endian `u32`s (or `count`
+ * bytes for `palindrome_flags`).
+ *
+ * Only the variants of the enabled `text` / `hash` groups are built.
+ */
+
+#[cfg(any(feature = "text", feature = "hash"))]
+use wasm_bindgen::prelude::*;
+
+use crate::error::{clear_last_error, EchelonError, ErrorCode, Result};
+
+/// Apply `f` to every item
+pub fn map_batch<S: AsRef<str>, T>(items: &[S], f: impl Fn(&str) -> T) -> Vec<T> {
+    // Empty batches never reach the per-item export
+    clear_last_error();
+    items.iter().map(|item| f(item.as_ref())).collect()
+}
+
+/// Hash every string (DJB2)
+#[cfg(feature = "hash")]
+#[wasm_bindgen]
+pub fn hash_strings(items: Vec<String>) -> Vec<u32> {
+    map_batch(&items, crate::hash_string)
+}
+
+/// Count vowels in every string
+#[cfg(feature = "text")]
+#[wasm_bindgen]
+pub fn vowel_counts(items: Vec<String>) -> Vec<u32> {
+    map_batch(&items, |s| crate::count_vowels(s) as u32)
+}
+
+/// Count words in every string
+#[cfg(feature = "text")]
+#[wasm_bindgen]
+pub fn word_counts(items: Vec<String>) -> Vec<u32> {
+    map_batch(&items, |s| crate::word_count(s) as u32)
+}
+
+/// Longest word length of every string
+#[cfg(feature = "text")]
+#[wasm_bindgen]
+pub fn longest_word_lengths(items: Vec<String>) -> Vec<u32> {
+    map_batch(&items, |s| crate::longest_word_length(s) as u32)
+}
+
+/// Palindrome check for every string (`1` or `0` per item)
+#[cfg(feature = "text")]
+#[wasm_bindgen]
+pub fn palindrome_flags(items: Vec<String>) -> Vec<u8> {
+    map_batch(&items, |s| crate::is_palindrome(s) as u8)
+}
+
+fn truncated() -> EchelonError {
+    EchelonError::new(ErrorCode::InvalidArgument, "truncated batch buffer")
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> Result<u32> {
+    buf.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(truncated)
+}
+
+/// Split a length-prefixed buffer into strings
+pub fn decode_strings(buf: &[u8]) -> Result<Vec<&str>> {
+    let count = read_u32(buf, 0)? as usize;
+    let mut offset = 4;
+    let mut items = Vec::with_capacity(count.min(buf.len() / 4));
+
+    for index in 0..count {
+        let len = read_u32(buf, offset)? as usize;
+        offset += 4;
+        let end = offset.checked_add(len).ok_or_else(truncated)?;
+        let bytes = buf.get(offset..end).ok_or_else(truncated)?;
+        offset = end;
+
+        let item = std::str::from_utf8(bytes).map_err(|_| {
+            EchelonError::new(ErrorCode::InvalidUtf8, format!("invalid UTF-8 in batch item {index}"))
+        })?;
+        items.push(item);
+    }
+
+    Ok(items)
+}
+
+/// Build a length-prefixed buffer from strings
+pub fn encode_strings<S: AsRef<str>>(items: &[S]) -> Vec<u8> {
+    let total: usize = items.iter().map(|s| 4 + s.as_ref().len()).sum();
+    let mut buf = Vec::with_capacity(4 + total);
+    buf.extend_from_slice(&(items.len() as u32).to_le_bytes());
+    for item in items {
+        let item = item.as_ref().as_bytes();
+        buf.extend_from_slice(&(item.len() as u32).to_le_bytes());
+        buf.extend_from_slice(item);
+    }
+    buf
+}
+
+/// Serialize `u32` results as little-endian bytes
+pub fn encode_u32s(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(buf: &[u8]) -> ErrorCode {
+        decode_strings(buf).expect_err("decode should fail").code
+    }
+
+    /// `[count]` followed by `(len, bytes)` items, lengths taken as given
+    fn raw(count: u32, items: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut buf = count.to_le_bytes().to_vec();
+        for (len, bytes) in items {
+            buf.extend_from_slice(&len.to_le_bytes());
+            buf.extend_from_slice(bytes);
+        }
+        buf
+    }
+
+    #[test]
+    fn round_trip() {
+        let items = ["racecar", "", "héllo wörld", "日本語😀"];
+        let buf = encode_strings(&items);
+        assert_eq!(buf.len(), 4 + items.iter().map(|s| 4 + s.len()).sum::<usize>());
+        assert_eq!(decode_strings(&buf).unwrap(), items);
+    }
+
+    #[test]
+    fn empty_batch() {
+        let buf = encode_strings::<&str>(&[]);
+        assert_eq!(buf, [0, 0, 0, 0]);
+        assert_eq!(decode_strings(&buf).unwrap(), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn truncated_prefix() {
+        for buf in [&[][..], &[1], &[1, 0, 0]] {
+            assert_eq!(code(buf), ErrorCode::InvalidArgument, "{buf:?}");
+        }
+        // Count says one item, its length prefix is cut short
+        assert_eq!(code(&[1, 0, 0, 0, 3, 0]), ErrorCode::InvalidArgument);
+        // Count says more items than the buffer holds
+        assert_eq!(code(&raw(2, &[(1, b"a")])), ErrorCode::InvalidArgument);
+        assert_eq!(code(&raw(u32::MAX, &[])), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn length_beyond_buffer() {
+        assert_eq!(code(&raw(1, &[(4, b"abc")])), ErrorCode::InvalidArgument);
+        assert_eq!(code(&raw(2, &[(1, b"a"), (10, b"b")])), ErrorCode::InvalidArgument);
+        assert_eq!(code(&raw(1, &[(u32::MAX, b"abc")])), ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let err = decode_strings(&raw(2, &[(2, b"ok"), (2, b"\xc3(")])).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidUtf8);
+        assert!(err.message.contains("item 1"), "{}", err.message);
+        // A multi-byte char split by a length prefix is invalid too
+        let split = "é".as_bytes();
+        assert_eq!(code(&raw(2, &[(1, &split[..1]), (1, &split[1..])])), ErrorCode::InvalidUtf8);
+    }
+}
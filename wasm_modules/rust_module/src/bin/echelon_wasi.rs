@@ -0,0 +1,26 @@
+/*!
+ * WASI command entry point
+ *
+ * Reads newline-delimited JSON-RPC requests from stdin and writes one
+ * response line per request to stdout until stdin closes. Built for
+ * `wasm32-wasip1` so `wasm_wasi.ts` can run it as a sandboxed worker.
+ */
+
+use std::io::{self, BufRead, Write};
+
+fn main() {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout().lock();
+
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        if let Some(reply) = echelon_wasm::rpc::handle_line(&line) {
+            if writeln!(stdout, "{reply}").and_then(|_| stdout.flush()).is_err() {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,29 @@
+/*!
+ * Cipher functions (`cipher` feature)
+ */
+
+#[cfg(not(component_core))]
+use wasm_bindgen::prelude::*;
+
+use crate::error::clear_last_error;
+
+/// Shift an ASCII letter by `shift` places, other bytes are returned unchanged
+pub fn shift_ascii(byte: u8, shift: u8) -> u8 {
+    let shift = shift % 26;
+    if byte.is_ascii_lowercase() {
+        (((byte - b'a') + shift) % 26) + b'a'
+    } else if byte.is_ascii_uppercase() {
+        (((byte - b'A') + shift) % 26) + b'A'
+    } else {
+        byte
+    }
+}
+
+/// Simple encryption (Caesar cipher)
+#[cfg_attr(not(component_core), wasm_bindgen)]
+pub fn caesar_encrypt(s: &str, shift: u8) -> String {
+    clear_last_error();
+    s.chars()
+        .map(|c| if c.is_ascii() { shift_ascii(c as u8, shift) as char } else { c })
+        .collect()
+}
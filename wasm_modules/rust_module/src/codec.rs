@@ -0,0 +1,344 @@
+/*!
+ * Structured calls (`codec` feature)
+ *
+ * `call_encoded(encoding, method, args)` takes a method's arguments as one
+ * CBOR or MessagePack map keyed by the Rust parameter names, decodes it
+ * with serde into that method's argument struct and returns the result in
+ * the same encoding:
+ *
+ * ```text
+ * call_encoded(Cbor, "caesar_encrypt", {"s": "abc", "shift": 3})  -> "def"
+ * call_encoded(MessagePack, "word_frequencies",
+ *              {"s": "a b a", "limit": 1})                       -> [{"word": "a", "count": 2}]
+ * ```
+ *
+ * Options are optional fields with defaults, so richer APIs only add a
+ * method or a field here and the ABI stays the same. Unknown methods,
+ * unknown fields, malformed input and input nested deeper than `MAX_DEPTH`
+ * record `InvalidArgument`; errors from the method itself keep their code.
+ */
+
+use serde::de::DeserializeOwned;
+#[cfg(any(feature = "text", feature = "hash", feature = "cipher", feature = "math"))]
+use serde::Deserialize;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+#[cfg(all(feature = "batch", any(feature = "text", feature = "hash")))]
+use crate::batch::map_batch;
+use crate::error::{self, guard, EchelonError, ErrorCode, Result};
+
+/// Wire format of `call_encoded` arguments and results
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// RFC 8949
+    Cbor = 0,
+    /// Structs are encoded as maps
+    MessagePack = 1,
+}
+
+impl Encoding {
+    pub fn from_u32(value: u32) -> Result<Self> {
+        match value {
+            0 => Ok(Self::Cbor),
+            1 => Ok(Self::MessagePack),
+            _ => Err(invalid(format!("unknown encoding {value}"))),
+        }
+    }
+}
+
+/// Deepest nesting of arrays and maps accepted; the decoders recurse per level
+pub const MAX_DEPTH: usize = 128;
+
+/// Decode an argument map
+pub fn decode<T: DeserializeOwned>(encoding: Encoding, bytes: &[u8]) -> Result<T> {
+    match encoding {
+        Encoding::Cbor => ciborium::de::from_reader_with_recursion_limit(bytes, MAX_DEPTH).map_err(|err| {
+            let reason = match err {
+                ciborium::de::Error::Io(_) => "unexpected end of input".to_string(),
+                ciborium::de::Error::Syntax(offset) => format!("syntax error at byte {offset}"),
+                ciborium::de::Error::Semantic(_, message) => message,
+                ciborium::de::Error::RecursionLimitExceeded => "nesting too deep".to_string(),
+            };
+            invalid(format!("invalid CBOR arguments: {reason}"))
+        }),
+        Encoding::MessagePack => {
+            // The default limit of 1024 levels overflows the stack first
+            let mut decoder = rmp_serde::Deserializer::from_read_ref(bytes);
+            decoder.set_max_depth(MAX_DEPTH);
+            serde::Deserialize::deserialize(&mut decoder)
+                .map_err(|err| invalid(format!("invalid MessagePack arguments: {err}")))
+        }
+    }
+}
+
+/// Encode a result
+pub fn encode<T: Serialize>(encoding: Encoding, value: &T) -> Result<Vec<u8>> {
+    match encoding {
+        Encoding::Cbor => {
+            let mut out = Vec::new();
+            ciborium::into_writer(value, &mut out).map_err(|err| invalid(format!("cannot encode result: {err}")))?;
+            Ok(out)
+        }
+        Encoding::MessagePack => {
+            rmp_serde::to_vec_named(value).map_err(|err| invalid(format!("cannot encode result: {err}")))
+        }
+    }
+}
+
+#[cfg(any(feature = "text", feature = "hash"))]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Text {
+    s: String,
+}
+
+#[cfg(all(feature = "batch", any(feature = "text", feature = "hash")))]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Items {
+    items: Vec<String>,
+}
+
+#[cfg(feature = "math")]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Operands {
+    a: i32,
+    b: i32,
+}
+
+#[cfg(feature = "math")]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Size {
+    size: usize,
+}
+
+#[cfg(feature = "cipher")]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Caesar {
+    s: String,
+    shift: u8,
+    /// Shift backwards instead
+    #[serde(default)]
+    decrypt: bool,
+}
+
+#[cfg(feature = "text")]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WordFrequencies {
+    s: String,
+    #[serde(default = "one")]
+    min_length: usize,
+    /// Keep only the most frequent words
+    limit: Option<usize>,
+    #[serde(default)]
+    case_sensitive: bool,
+}
+
+#[cfg(feature = "text")]
+fn one() -> usize {
+    1
+}
+
+#[cfg(feature = "text")]
+#[derive(Serialize)]
+struct WordFrequency {
+    word: String,
+    count: usize,
+}
+
+/// Decode the arguments, run `f` and encode its result
+#[cfg_attr(not(any(feature = "text", feature = "hash", feature = "cipher", feature = "math")), allow(dead_code))]
+fn run<A, R>(encoding: Encoding, args: &[u8], f: impl FnOnce(A) -> R) -> Result<Vec<u8>>
+where
+    A: DeserializeOwned,
+    R: Serialize,
+{
+    let result = f(decode(encoding, args)?);
+    if let Some(err) = error::last_error() {
+        return Err(err);
+    }
+    encode(encoding, &result)
+}
+
+/// Call `method` with an encoded argument map (only the enabled export groups are reachable)
+#[cfg_attr(
+    not(any(feature = "text", feature = "hash", feature = "cipher", feature = "math")),
+    allow(unused_variables)
+)]
+pub fn call(encoding: Encoding, method: &str, args: &[u8]) -> Result<Vec<u8>> {
+    match method {
+        #[cfg(feature = "text")]
+        "count_vowels" => run(encoding, args, |Text { s }| crate::count_vowels(&s)),
+        #[cfg(feature = "text")]
+        "reverse_string" => run(encoding, args, |Text { s }| crate::reverse_string(&s)),
+        #[cfg(feature = "text")]
+        "is_palindrome" => run(encoding, args, |Text { s }| crate::is_palindrome(&s)),
+        #[cfg(feature = "hash")]
+        "hash_string" => run(encoding, args, |Text { s }| crate::hash_string(&s)),
+        #[cfg(feature = "text")]
+        "longest_word_length" => run(encoding, args, |Text { s }| crate::longest_word_length(&s)),
+        #[cfg(feature = "text")]
+        "word_count" => run(encoding, args, |Text { s }| crate::word_count(&s)),
+        #[cfg(feature = "text")]
+        "word_frequencies" => run(encoding, args, |args: WordFrequencies| {
+            let mut frequencies = crate::text::word_frequencies(&args.s, args.min_length, args.case_sensitive);
+            frequencies.truncate(args.limit.unwrap_or(usize::MAX));
+            frequencies
+                .into_iter()
+                .map(|(word, count)| WordFrequency { word, count })
+                .collect::<Vec<_>>()
+        }),
+        #[cfg(feature = "cipher")]
+        "caesar_encrypt" => run(encoding, args, |args: Caesar| {
+            let shift = if args.decrypt { 26 - args.shift % 26 } else { args.shift };
+            crate::caesar_encrypt(&args.s, shift)
+        }),
+        #[cfg(feature = "math")]
+        "add" => run(encoding, args, |Operands { a, b }| crate::add(a, b)),
+        #[cfg(feature = "math")]
+        "multiply" => run(encoding, args, |Operands { a, b }| crate::multiply(a, b)),
+        #[cfg(feature = "math")]
+        "memory_intensive" => run(encoding, args, |Size { size }| crate::memory_intensive(size)),
+        #[cfg(all(feature = "batch", feature = "hash"))]
+        "hash_strings" => run(encoding, args, |Items { items }| map_batch(&items, crate::hash_string)),
+        #[cfg(all(feature = "batch", feature = "text"))]
+        "vowel_counts" => run(encoding, args, |Items { items }| map_batch(&items, crate::count_vowels)),
+        #[cfg(all(feature = "batch", feature = "text"))]
+        "word_counts" => run(encoding, args, |Items { items }| map_batch(&items, crate::word_count)),
+        #[cfg(all(feature = "batch", feature = "text"))]
+        "longest_word_lengths" => run(encoding, args, |Items { items }| map_batch(&items, crate::longest_word_length)),
+        #[cfg(all(feature = "batch", feature = "text"))]
+        "palindrome_flags" => run(encoding, args, |Items { items }| map_batch(&items, crate::is_palindrome)),
+        _ => Err(invalid(format!("unknown method: {method}"))),
+    }
+}
+
+/// Call `method` with a CBOR or MessagePack argument map; the result uses the same encoding
+///
+/// Returns an empty array and records the error on failure.
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn call_encoded(encoding: Encoding, method: &str, args: &[u8]) -> Vec<u8> {
+    guard(|| call(encoding, method, args))
+}
+
+fn invalid(message: impl Into<String>) -> EchelonError {
+    EchelonError::new(ErrorCode::InvalidArgument, message)
+}
+
+// Calls go through `caesar_encrypt`, which has a `u8` field and an option
+#[cfg(all(test, feature = "cipher"))]
+mod tests {
+    use serde::de::IgnoredAny;
+
+    use super::*;
+
+    /// `{"s": "abc", "shift": <shift>}` with `shift` given as raw encoded bytes
+    fn caesar_args(encoding: Encoding, shift: &[u8]) -> Vec<u8> {
+        let mut out = match encoding {
+            Encoding::Cbor => vec![0xa2, 0x61, b's', 0x63, b'a', b'b', b'c', 0x65],
+            Encoding::MessagePack => vec![0x82, 0xa1, b's', 0xa3, b'a', b'b', b'c', 0xa5],
+        };
+        out.extend_from_slice(b"shift");
+        out.extend_from_slice(shift);
+        out
+    }
+
+    fn rejected(result: Result<impl std::fmt::Debug>, message: &str) {
+        let err = result.expect_err("input accepted");
+        assert_eq!(err.code, ErrorCode::InvalidArgument);
+        assert!(err.message.contains(message), "{err}");
+    }
+
+    #[test]
+    fn decodes_definite_length_maps_and_strings() {
+        let cbor = call(Encoding::Cbor, "caesar_encrypt", &caesar_args(Encoding::Cbor, &[0x03]));
+        assert_eq!(cbor.unwrap(), [0x63, b'd', b'e', b'f']);
+        let msgpack = call(Encoding::MessagePack, "caesar_encrypt", &caesar_args(Encoding::MessagePack, &[0x03]));
+        assert_eq!(msgpack.unwrap(), [0xa3, b'd', b'e', b'f']);
+    }
+
+    #[test]
+    fn round_trips_through_encode() {
+        #[derive(Serialize)]
+        struct Args<'a> {
+            s: &'a str,
+            shift: u8,
+            decrypt: bool,
+        }
+        for encoding in [Encoding::Cbor, Encoding::MessagePack] {
+            let args = encode(encoding, &Args { s: "Khoor", shift: 3, decrypt: true }).unwrap();
+            let result = call(encoding, "caesar_encrypt", &args).unwrap();
+            assert_eq!(decode::<String>(encoding, &result).unwrap(), "Hello");
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_integer_widths() {
+        // 256 as a u16, and 2^32 as a u64, for a u8 field
+        rejected(call(Encoding::Cbor, "caesar_encrypt", &caesar_args(Encoding::Cbor, &[0x19, 0x01, 0x00])), "CBOR");
+        let wide = [0x1b, 0, 0, 0, 1, 0, 0, 0, 0];
+        rejected(call(Encoding::Cbor, "caesar_encrypt", &caesar_args(Encoding::Cbor, &wide)), "CBOR");
+        let args = caesar_args(Encoding::MessagePack, &[0xcd, 0x01, 0x00]);
+        rejected(call(Encoding::MessagePack, "caesar_encrypt", &args), "MessagePack");
+        // Negative shift
+        rejected(call(Encoding::Cbor, "caesar_encrypt", &caesar_args(Encoding::Cbor, &[0x20])), "CBOR");
+        rejected(call(Encoding::MessagePack, "caesar_encrypt", &caesar_args(Encoding::MessagePack, &[0xff])), "MessagePack");
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        for encoding in [Encoding::Cbor, Encoding::MessagePack] {
+            let args = caesar_args(encoding, &[0x03]);
+            for len in 0..args.len() {
+                assert!(call(encoding, "caesar_encrypt", &args[..len]).is_err(), "prefix of {len} bytes accepted");
+            }
+        }
+        rejected(decode::<String>(Encoding::Cbor, &[0x63, b'a']), "unexpected end of input");
+        // Length prefix far past the end of the input
+        rejected(decode::<String>(Encoding::Cbor, &[0x7a, 0xff, 0xff, 0xff, 0xff]), "CBOR");
+        rejected(decode::<String>(Encoding::MessagePack, &[0xdb, 0xff, 0xff, 0xff, 0xff]), "MessagePack");
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        // One-element arrays nested far deeper than either decoder allows
+        let cbor = [vec![0x81; 100_000], vec![0x00]].concat();
+        rejected(decode::<IgnoredAny>(Encoding::Cbor, &cbor), "nesting too deep");
+        let msgpack = [vec![0x91; 100_000], vec![0x00]].concat();
+        rejected(decode::<IgnoredAny>(Encoding::MessagePack, &msgpack), "depth limit exceeded");
+
+        // Nesting up to the limit is fine
+        let cbor = [vec![0x81; MAX_DEPTH - 1], vec![0x00]].concat();
+        decode::<IgnoredAny>(Encoding::Cbor, &cbor).unwrap();
+        let msgpack = [vec![0x91; MAX_DEPTH - 1], vec![0x00]].concat();
+        decode::<IgnoredAny>(Encoding::MessagePack, &msgpack).unwrap();
+    }
+
+    #[test]
+    fn rejects_unknown_major_types() {
+        // Reserved additional info 28..=30, and break outside an indefinite item
+        for byte in [0x1c, 0x3d, 0x5e, 0xfc, 0xfd, 0xfe, 0xff] {
+            assert!(decode::<IgnoredAny>(Encoding::Cbor, &[byte]).is_err(), "CBOR {byte:#04x} accepted");
+        }
+        // 0xc1 is never used
+        rejected(decode::<IgnoredAny>(Encoding::MessagePack, &[0xc1]), "MessagePack");
+    }
+
+    #[test]
+    fn rejects_unknown_methods_fields_and_encodings() {
+        rejected(call(Encoding::Cbor, "no_such_method", &[0xa0]), "unknown method");
+        let mut args = caesar_args(Encoding::Cbor, &[0x03]);
+        args[0] = 0xa3;
+        args.extend_from_slice(&[0x63, b'x', b'y', b'z', 0xf5]);
+        rejected(call(Encoding::Cbor, "caesar_encrypt", &args), "unknown field");
+        rejected(Encoding::from_u32(2), "unknown encoding 2");
+    }
+}
@@ -0,0 +1,246 @@
+/*!
+ * Component-model exports (`component` feature)
+ *
+ * Canonical ABI bindings for the `echelon:wasm` world in `wit/echelon.wit`,
+ * written out by hand so the crate needs no binding generator. Build the
+ * core module for `wasm32-wasip1` (the `component_core` cfg leaves out the
+ * C ABI and every wasm-bindgen export) and wrap it with
+ * `wasm-tools component new` - `COMPONENT=1 ./build.sh` does both.
+ *
+ * Lowering (`string-encoding=utf8`):
+ *
+ * - string params arrive as `(ptr, len)` in memory from `cabi_realloc` and
+ *   are owned by the callee
+ * - string results go in a `(ptr, len)` return area whose address is
+ *   returned; the host copies the string out, then calls the matching
+ *   `cabi_post_*` export to free both
+ */
+
+use std::alloc::{alloc, dealloc, handle_alloc_error, realloc, Layout};
+
+/// Canonical ABI allocator used by the host to lower params
+///
+/// # Safety
+///
+/// `old` must be null or a live allocation of `old_size` bytes at `align`
+/// from this function.
+#[export_name = "cabi_realloc"]
+pub unsafe extern "C" fn cabi_realloc(old: *mut u8, old_size: usize, align: usize, new_size: usize) -> *mut u8 {
+    if new_size == 0 {
+        if old_size != 0 {
+            dealloc(old, Layout::from_size_align_unchecked(old_size, align));
+        }
+        return align as *mut u8;
+    }
+
+    let layout = Layout::from_size_align_unchecked(new_size, align);
+    let ptr = if old_size == 0 {
+        alloc(layout)
+    } else {
+        realloc(old, Layout::from_size_align_unchecked(old_size, align), new_size)
+    };
+    if ptr.is_null() {
+        handle_alloc_error(layout);
+    }
+    ptr
+}
+
+/// Lift an owned string param
+///
+/// # Safety
+///
+/// `(ptr, len)` must come from `cabi_realloc` with align 1; the canonical
+/// ABI only lowers valid UTF-8.
+unsafe fn lift_string(ptr: *mut u8, len: usize) -> String {
+    if len == 0 {
+        return String::new();
+    }
+    String::from_utf8_unchecked(Vec::from_raw_parts(ptr, len, len))
+}
+
+/// Lower a string result into a fresh return area
+fn lower_string(s: String) -> *mut [usize; 2] {
+    let bytes = Box::into_raw(s.into_bytes().into_boxed_slice());
+    Box::into_raw(Box::new([bytes as *mut u8 as usize, bytes.len()]))
+}
+
+/// Free a return area from `lower_string` and its string
+///
+/// # Safety
+///
+/// `area` must come from `lower_string` and not be freed yet.
+unsafe fn free_string(area: *mut [usize; 2]) {
+    let [ptr, len] = *Box::from_raw(area);
+    drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr as *mut u8, len)));
+}
+
+/// Core export name of `func` in `interface`
+macro_rules! export_name {
+    ($interface:literal, $func:literal) => {
+        concat!("echelon:wasm/", $interface, "@", env!("CARGO_PKG_VERSION"), "#", $func)
+    };
+}
+
+/// `text.count-vowels`
+///
+/// # Safety
+///
+/// `(ptr, len)` must be a lowered string param.
+#[export_name = export_name!("text", "count-vowels")]
+pub unsafe extern "C" fn text_count_vowels(ptr: *mut u8, len: usize) -> u32 {
+    crate::count_vowels(&lift_string(ptr, len)) as u32
+}
+
+/// `text.reverse-string`
+///
+/// # Safety
+///
+/// `(ptr, len)` must be a lowered string param.
+#[export_name = export_name!("text", "reverse-string")]
+pub unsafe extern "C" fn text_reverse_string(ptr: *mut u8, len: usize) -> *mut [usize; 2] {
+    lower_string(crate::reverse_string(&lift_string(ptr, len)))
+}
+
+/// Free the result of `text.reverse-string`
+///
+/// # Safety
+///
+/// `area` must be the result of `text_reverse_string`.
+#[export_name = concat!("cabi_post_", export_name!("text", "reverse-string"))]
+pub unsafe extern "C" fn text_reverse_string_post(area: *mut [usize; 2]) {
+    free_string(area)
+}
+
+/// `text.is-palindrome`
+///
+/// # Safety
+///
+/// `(ptr, len)` must be a lowered string param.
+#[export_name = export_name!("text", "is-palindrome")]
+pub unsafe extern "C" fn text_is_palindrome(ptr: *mut u8, len: usize) -> u32 {
+    crate::is_palindrome(&lift_string(ptr, len)) as u32
+}
+
+/// `text.longest-word-length`
+///
+/// # Safety
+///
+/// `(ptr, len)` must be a lowered string param.
+#[export_name = export_name!("text", "longest-word-length")]
+pub unsafe extern "C" fn text_longest_word_length(ptr: *mut u8, len: usize) -> u32 {
+    crate::longest_word_length(&lift_string(ptr, len)) as u32
+}
+
+/// `text.word-count`
+///
+/// # Safety
+///
+/// `(ptr, len)` must be a lowered string param.
+#[export_name = export_name!("text", "word-count")]
+pub unsafe extern "C" fn text_word_count(ptr: *mut u8, len: usize) -> u32 {
+    crate::word_count(&lift_string(ptr, len)) as u32
+}
+
+/// `hash.hash-string`
+///
+/// # Safety
+///
+/// `(ptr, len)` must be a lowered string param.
+#[export_name = export_name!("hash", "hash-string")]
+pub unsafe extern "C" fn hash_hash_string(ptr: *mut u8, len: usize) -> u32 {
+    crate::hash_string(&lift_string(ptr, len))
+}
+
+/// `cipher.caesar-encrypt`; `shift` is a lowered `u8`
+///
+/// # Safety
+///
+/// `(ptr, len)` must be a lowered string param.
+#[export_name = export_name!("cipher", "caesar-encrypt")]
+pub unsafe extern "C" fn cipher_caesar_encrypt(ptr: *mut u8, len: usize, shift: u32) -> *mut [usize; 2] {
+    lower_string(crate::caesar_encrypt(&lift_string(ptr, len), shift as u8))
+}
+
+/// Free the result of `cipher.caesar-encrypt`
+///
+/// # Safety
+///
+/// `area` must be the result of `cipher_caesar_encrypt`.
+#[export_name = concat!("cabi_post_", export_name!("cipher", "caesar-encrypt"))]
+pub unsafe extern "C" fn cipher_caesar_encrypt_post(area: *mut [usize; 2]) {
+    free_string(area)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lower a string param the way the host does, through `cabi_realloc`
+    fn lower_param(s: &str) -> (*mut u8, usize) {
+        unsafe {
+            let ptr = cabi_realloc(std::ptr::null_mut(), 0, 1, s.len());
+            ptr.copy_from_nonoverlapping(s.as_ptr(), s.len());
+            (ptr, s.len())
+        }
+    }
+
+    /// Copy a string result out of its return area, as the host does before `cabi_post_*`
+    unsafe fn read_result(area: *mut [usize; 2]) -> String {
+        let [ptr, len] = *area;
+        std::str::from_utf8(std::slice::from_raw_parts(ptr as *const u8, len)).unwrap().to_string()
+    }
+
+    #[test]
+    fn realloc_keeps_contents_and_frees() {
+        unsafe {
+            let ptr = cabi_realloc(std::ptr::null_mut(), 0, 1, 4);
+            ptr.copy_from_nonoverlapping(b"abcd".as_ptr(), 4);
+            let ptr = cabi_realloc(ptr, 4, 1, 4096);
+            assert_eq!(std::slice::from_raw_parts(ptr, 4), b"abcd");
+            // Freeing hands back a dangling, aligned pointer like an empty allocation
+            assert_eq!(cabi_realloc(ptr, 4096, 1, 0) as usize, 1);
+            assert_eq!(cabi_realloc(std::ptr::null_mut(), 0, 8, 0) as usize, 8);
+        }
+    }
+
+    #[test]
+    fn string_params_round_trip() {
+        for s in ["", "hello", "héllo wörld 🦀"] {
+            let (ptr, len) = lower_param(s);
+            assert_eq!(unsafe { lift_string(ptr, len) }, s);
+        }
+    }
+
+    #[test]
+    fn string_results_round_trip() {
+        for s in ["", "hello", "héllo wörld 🦀"] {
+            let area = lower_string(s.to_string());
+            unsafe {
+                assert_eq!(read_result(area), s);
+                free_string(area);
+            }
+        }
+    }
+
+    #[test]
+    fn exports_lift_params_and_lower_results() {
+        unsafe {
+            let (ptr, len) = lower_param("héllo 🦀");
+            let area = text_reverse_string(ptr, len);
+            assert_eq!(read_result(area), "🦀 olléh");
+            text_reverse_string_post(area);
+
+            let (ptr, len) = lower_param("abc xyz");
+            let area = cipher_caesar_encrypt(ptr, len, 3);
+            assert_eq!(read_result(area), "def abc");
+            cipher_caesar_encrypt_post(area);
+
+            let (ptr, len) = lower_param("a bb ccc");
+            assert_eq!(text_word_count(ptr, len), 3);
+            let (ptr, len) = lower_param("a bb ccc");
+            assert_eq!(text_longest_word_length(ptr, len), 3);
+            let (ptr, len) = lower_param("hello");
+            assert_eq!(hash_hash_string(ptr, len), crate::hash_string("hello"));
+        }
+    }
+}
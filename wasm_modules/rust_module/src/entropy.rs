@@ -0,0 +1,242 @@
+/*!
+ * Randomness and time
+ *
+ * Code in the crate that needs entropy or the current time reads it from
+ * `random_f64()` / `now_ms()` / `monotonic_ms()` here, never from `std`
+ * directly, so the host decides where they come from:
+ *
+ * - Host mode (default): wasm32 builds with the `host-entropy` feature (on
+ *   by default) import `env."Math.random"`, `env."Date.now"` and
+ *   `env."performance.now"`, registered by `registerStandardHostFunctions`
+ *   in `wasm_host_functions.ts` and supplied by every loader in
+ *   `framework/runtime`. Native and WASI builds use the OS. wasm32 builds
+ *   without the feature have no source and start out seeded with `0` at
+ *   time `0`.
+ * - Deterministic mode: `set_deterministic(seed, start_ms)` switches to a
+ *   SplitMix64 generator and a virtual clock frozen at `start_ms` that only
+ *   `advance_clock(ms)` moves. The same seed and inputs give the same
+ *   outputs on every build and host.
+ *
+ * `DeterministicHostSource` in `wasm_host_functions.ts` runs the same
+ * generator, so a host-mode module fed by it with seed `s` sees exactly the
+ * values of a module given `set_deterministic(s, ...)`.
+ */
+
+use std::cell::Cell;
+
+use wasm_bindgen::prelude::*;
+
+/// Whether this build has a host (or OS) entropy source
+pub const HOST_SOURCE: bool = cfg!(any(
+    not(target_arch = "wasm32"),
+    target_os = "wasi",
+    feature = "host-entropy"
+));
+
+#[cfg(all(target_arch = "wasm32", not(target_os = "wasi"), feature = "host-entropy"))]
+mod host {
+    #[link(wasm_import_module = "env")]
+    extern "C" {
+        #[link_name = "Math.random"]
+        fn math_random() -> f64;
+        #[link_name = "Date.now"]
+        fn date_now() -> f64;
+        #[link_name = "performance.now"]
+        fn performance_now() -> f64;
+    }
+
+    pub fn random() -> f64 {
+        unsafe { math_random() }
+    }
+
+    pub fn now() -> f64 {
+        unsafe { date_now() }
+    }
+
+    pub fn monotonic() -> f64 {
+        unsafe { performance_now() }
+    }
+}
+
+#[cfg(any(not(target_arch = "wasm32"), target_os = "wasi"))]
+mod host {
+    use std::cell::Cell;
+    use std::hash::{BuildHasher, RandomState};
+    use std::sync::OnceLock;
+    use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+    thread_local! {
+        /// Generator state seeded once per thread from the OS
+        static STATE: Cell<u64> = Cell::new(RandomState::new().hash_one(0u64));
+    }
+
+    pub fn random() -> f64 {
+        let mut state = STATE.get();
+        let value = super::unit_f64(super::splitmix64(&mut state));
+        STATE.set(state);
+        value
+    }
+
+    pub fn now() -> f64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |elapsed| elapsed.as_millis() as f64)
+    }
+
+    pub fn monotonic() -> f64 {
+        EPOCH.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+    }
+}
+
+#[cfg(not(any(
+    not(target_arch = "wasm32"),
+    target_os = "wasi",
+    feature = "host-entropy"
+)))]
+mod host {
+    // Never called: without a source the module stays in deterministic mode
+    pub fn random() -> f64 {
+        0.0
+    }
+
+    pub fn now() -> f64 {
+        0.0
+    }
+
+    pub fn monotonic() -> f64 {
+        0.0
+    }
+}
+
+thread_local! {
+    /// Generator state and clock in deterministic mode, `None` in host mode
+    static SEEDED: Cell<Option<(u64, f64)>> = const {
+        Cell::new(if HOST_SOURCE { None } else { Some((0, 0.0)) })
+    };
+}
+
+/// Advance a SplitMix64 state and return the next output
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Top 53 bits of `bits` as a float in `[0, 1)`, as `Math.random` returns
+fn unit_f64(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Next random float in `[0, 1)`
+pub fn random_f64() -> f64 {
+    SEEDED.with(|seeded| match seeded.get() {
+        Some((mut state, clock)) => {
+            let value = unit_f64(splitmix64(&mut state));
+            seeded.set(Some((state, clock)));
+            value
+        }
+        None => host::random(),
+    })
+}
+
+/// Generator state and clock in deterministic mode, `None` in host mode
+pub fn state() -> Option<(u64, f64)> {
+    SEEDED.get()
+}
+
+/// Restore a value from `state()` (`None` switches back to the host source)
+pub fn set_state(seeded: Option<(u64, f64)>) {
+    match seeded {
+        Some(_) => SEEDED.set(seeded),
+        None => clear_deterministic(),
+    }
+}
+
+/// Fill `out` with random bytes (one `random_f64` draw per 4 bytes)
+pub fn fill_random(out: &mut [u8]) {
+    for chunk in out.chunks_mut(4) {
+        let word = random_u32().to_le_bytes();
+        chunk.copy_from_slice(&word[..chunk.len()]);
+    }
+}
+
+/// Switch to deterministic mode: seeded generator, clock frozen at `start_ms`
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn set_deterministic(seed: u64, start_ms: f64) {
+    SEEDED.set(Some((seed, start_ms)));
+}
+
+/// Return to the host source (no-op in builds without one, which reseed with `0`)
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn clear_deterministic() {
+    SEEDED.set(if HOST_SOURCE { None } else { Some((0, 0.0)) });
+}
+
+/// Whether randomness and time come from the seeded generator
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn is_deterministic() -> bool {
+    SEEDED.get().is_some()
+}
+
+/// Move the virtual clock forward (ignored in host mode)
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn advance_clock(ms: f64) {
+    if let Some((state, clock)) = SEEDED.get() {
+        SEEDED.set(Some((state, clock + ms.max(0.0))));
+    }
+}
+
+/// Current time in milliseconds since the Unix epoch (virtual in deterministic mode)
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn now_ms() -> f64 {
+    crate::error::clear_last_error();
+    match SEEDED.get() {
+        Some((_, clock)) => clock,
+        None => host::now(),
+    }
+}
+
+/// Monotonic milliseconds for timing (the virtual clock in deterministic mode)
+pub fn monotonic_ms() -> f64 {
+    match SEEDED.get() {
+        Some((_, clock)) => clock,
+        None => host::monotonic(),
+    }
+}
+
+/// Random `u32`
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn random_u32() -> u32 {
+    crate::error::clear_last_error();
+    (random_f64() * 4_294_967_296.0) as u32
+}
+
+/// Random RFC 4122 version 4 UUID
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn uuid_v4() -> String {
+    crate::error::clear_last_error();
+    let mut bytes = [0u8; 16];
+    fill_random(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let mut out = String::with_capacity(36);
+    for (index, byte) in bytes.iter().enumerate() {
+        if matches!(index, 4 | 6 | 8 | 10) {
+            out.push('-');
+        }
+        out.push_str(&format!("{byte:02x}"));
+    }
+    out
+}
@@ -0,0 +1,113 @@
+/*!
+ * Structured error reporting
+ *
+ * Exports never panic: the release profile aborts on panic, which the host
+ * only sees as an opaque `unreachable` trap. Fallible exports instead record
+ * an error code and message on failure and return a zero value. The host
+ * reads the slot back through `last_error_code()` / `last_error_message()`
+ * after the call.
+ *
+ * Every export that does work resets the slot on entry (`guard` does it for
+ * fallible ones), so a success after a failure reads back `Ok`. Exports that
+ * only read or adjust module state (the slot itself, fuel, heap stats,
+ * scopes, buffer addresses, deterministic mode, the ABI version, `supports`
+ * and the manifest) leave it alone, so the host can query them between a
+ * call and its error check.
+ */
+
+use std::cell::RefCell;
+use std::fmt;
+
+#[cfg(not(component_core))]
+use wasm_bindgen::prelude::*;
+
+/// Error codes reported through `last_error_code()`
+#[cfg_attr(not(component_core), wasm_bindgen)]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    Ok = 0,
+    InvalidUtf8 = 1,
+    Overflow = 2,
+    InvalidArgument = 3,
+    OutOfMemory = 4,
+    /// Fuel budget ran out; call again to resume (see `crate::fuel`)
+    BudgetExhausted = 5,
+    /// A host import refused or failed the operation (see `crate::kv`)
+    HostError = 6,
+}
+
+/// Error raised by a fallible export
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EchelonError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl EchelonError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn overflow(op: &str) -> Self {
+        Self::new(ErrorCode::Overflow, format!("{op}: integer overflow"))
+    }
+}
+
+impl fmt::Display for EchelonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for EchelonError {}
+
+pub type Result<T> = std::result::Result<T, EchelonError>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<EchelonError>> = const { RefCell::new(None) };
+}
+
+/// Record an error for the host to read back
+pub fn set_last_error(err: EchelonError) {
+    LAST_ERROR.with(|slot| *slot.borrow_mut() = Some(err));
+}
+
+/// Reset the error slot
+pub fn clear_last_error() {
+    LAST_ERROR.with(|slot| *slot.borrow_mut() = None);
+}
+
+/// Get a copy of the last recorded error
+pub fn last_error() -> Option<EchelonError> {
+    LAST_ERROR.with(|slot| slot.borrow().clone())
+}
+
+/// Run a fallible export body, recording any error and returning `T::default()`
+#[cfg(not(component_core))]
+pub(crate) fn guard<T: Default>(f: impl FnOnce() -> Result<T>) -> T {
+    clear_last_error();
+    f().unwrap_or_else(|err| {
+        let code = err.code as u32;
+        log::debug!(code; "{}", err.message);
+        set_last_error(err);
+        T::default()
+    })
+}
+
+/// Error code of the last failed call (`0` when the last call succeeded)
+#[doc(alias = "impure")]
+#[cfg_attr(not(component_core), wasm_bindgen)]
+pub fn last_error_code() -> u32 {
+    last_error().map_or(ErrorCode::Ok as u32, |err| err.code as u32)
+}
+
+/// Message of the last failed call (empty when the last call succeeded)
+#[doc(alias = "impure")]
+#[cfg_attr(not(component_core), wasm_bindgen)]
+pub fn last_error_message() -> String {
+    last_error().map(|err| err.message).unwrap_or_default()
+}
@@ -0,0 +1,221 @@
+/*!
+ * Cooperative fuel budget
+ *
+ * The host cannot interrupt a synchronous wasm call from outside, so long
+ * loops meter themselves instead. The host sets a budget with
+ * `set_fuel(units)` before a call (`0` = unlimited, the default). Loops draw
+ * units with `take()`, roughly one unit per element processed.
+ *
+ * Only `memory_intensive` is metered so far. The batch, stream and codec
+ * loops are bounded by their input size and run unmetered, whatever the
+ * budget.
+ *
+ * When the budget runs out, the export parks its progress with `suspend()`.
+ * It then fails with `ErrorCode::BudgetExhausted` instead of running on.
+ * Calling the same export again with the same arguments, after topping the
+ * budget up, resumes from the parked state. `cancel_suspended()` drops the
+ * state, and so does the outermost `end_scope()`.
+ */
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{EchelonError, ErrorCode};
+
+/// Largest grant handed out by one `take()`, so loops check the budget often
+pub const FUEL_CHUNK: usize = 4096;
+
+thread_local! {
+    /// Remaining units, `None` when unlimited
+    static FUEL: Cell<Option<u32>> = const { Cell::new(None) };
+    /// Units drawn since the last `set_fuel`
+    static USED: Cell<u32> = const { Cell::new(0) };
+    static SUSPENDED: RefCell<Option<(&'static str, Box<dyn Any>)>> = const { RefCell::new(None) };
+}
+
+/// Draw up to `wanted` units, returns the units granted (`0` when exhausted)
+pub fn take(wanted: usize) -> usize {
+    let wanted = wanted.min(FUEL_CHUNK) as u32;
+    let granted = match FUEL.get() {
+        None => wanted,
+        Some(left) => {
+            let granted = wanted.min(left);
+            FUEL.set(Some(left - granted));
+            granted
+        }
+    };
+    USED.set(USED.get().saturating_add(granted));
+    granted as usize
+}
+
+/// Park the progress of `op` and return the `BudgetExhausted` error to raise
+pub fn suspend<T: 'static>(op: &'static str, state: T) -> EchelonError {
+    SUSPENDED.with(|slot| *slot.borrow_mut() = Some((op, Box::new(state))));
+    EchelonError::new(
+        ErrorCode::BudgetExhausted,
+        format!("{op}: fuel budget exhausted, call again to resume"),
+    )
+}
+
+/// Take the parked progress of `op`, if any
+pub fn resume<T: 'static>(op: &'static str) -> Option<T> {
+    SUSPENDED.with(|slot| {
+        let mut slot = slot.borrow_mut();
+        match slot.take() {
+            Some((name, state)) if name == op => state.downcast::<T>().ok().map(|state| *state),
+            other => {
+                *slot = other;
+                None
+            }
+        }
+    })
+}
+
+/// Whether some export has parked progress
+pub fn is_suspended() -> bool {
+    SUSPENDED.with(|slot| slot.borrow().is_some())
+}
+
+/// Remaining units (`None` when unlimited) and units used, without parked progress
+pub fn state() -> (Option<u32>, u32) {
+    (FUEL.get(), USED.get())
+}
+
+/// Restore a value from `state()`, dropping any parked progress
+pub fn set_state((fuel, used): (Option<u32>, u32)) {
+    FUEL.set(fuel);
+    USED.set(used);
+    cancel_suspended();
+}
+
+/// Run `f` without a budget, then put back the budget, usage and parked progress
+pub fn unmetered<T>(f: impl FnOnce() -> T) -> T {
+    let (fuel, used) = state();
+    let parked = SUSPENDED.with(|slot| slot.borrow_mut().take());
+    FUEL.set(None);
+    let out = f();
+    set_state((fuel, used));
+    SUSPENDED.with(|slot| *slot.borrow_mut() = parked);
+    out
+}
+
+/// Set the budget for the following calls (`0` = unlimited)
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn set_fuel(units: u32) {
+    FUEL.set((units > 0).then_some(units));
+    USED.set(0);
+}
+
+/// Units left (`u32::MAX` when unlimited)
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn fuel_remaining() -> u32 {
+    FUEL.get().unwrap_or(u32::MAX)
+}
+
+/// Units drawn since the last `set_fuel`
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn fuel_used() -> u32 {
+    USED.get()
+}
+
+/// Name of the export with parked progress (empty if none)
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn suspended_call() -> String {
+    SUSPENDED.with(|slot| slot.borrow().as_ref().map(|(op, _)| op.to_string()).unwrap_or_default())
+}
+
+/// Drop any parked progress
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn cancel_suspended() {
+    SUSPENDED.with(|slot| slot.borrow_mut().take());
+}
+
+#[cfg(all(test, feature = "math"))]
+mod tests {
+    use super::*;
+    use crate::error::last_error_code;
+    use crate::math::memory_intensive;
+
+    const SIZE: usize = 10_000;
+
+    fn unmetered_result() -> i32 {
+        set_fuel(0);
+        memory_intensive(SIZE)
+    }
+
+    #[test]
+    fn exhausted_budget_records_error_and_parks_progress() {
+        set_fuel(100);
+        assert_eq!(memory_intensive(SIZE), 0);
+        assert_eq!(last_error_code(), ErrorCode::BudgetExhausted as u32);
+        assert_eq!((fuel_remaining(), fuel_used()), (0, 100));
+        assert_eq!(suspended_call(), "memory_intensive");
+        cancel_suspended();
+    }
+
+    #[test]
+    fn resuming_matches_an_unmetered_run() {
+        let expected = unmetered_result();
+        assert_eq!(last_error_code(), 0);
+
+        // Fill and sum both need `SIZE` units, so this takes several calls
+        let mut calls = 0;
+        let result = loop {
+            calls += 1;
+            set_fuel(3_000);
+            let result = memory_intensive(SIZE);
+            if last_error_code() != ErrorCode::BudgetExhausted as u32 {
+                break result;
+            }
+            assert!(is_suspended());
+        };
+        assert_eq!(last_error_code(), 0);
+        assert_eq!(result, expected);
+        assert_eq!(calls, 7);
+        assert!(!is_suspended());
+    }
+
+    #[test]
+    fn cancel_drops_parked_progress() {
+        set_fuel(SIZE as u32 + 10);
+        memory_intensive(SIZE);
+        assert!(is_suspended());
+        cancel_suspended();
+        assert!(!is_suspended());
+        assert_eq!(suspended_call(), "");
+
+        // Starts over: the units granted only cover the fill again
+        set_fuel(SIZE as u32 + 10);
+        assert_eq!(memory_intensive(SIZE), 0);
+        assert_eq!(last_error_code(), ErrorCode::BudgetExhausted as u32);
+        cancel_suspended();
+    }
+
+    #[test]
+    fn progress_for_other_arguments_is_not_resumed() {
+        set_fuel(SIZE as u32);
+        memory_intensive(SIZE);
+        set_fuel(0);
+        assert_eq!(memory_intensive(4), 6);
+        assert_eq!(last_error_code(), 0);
+        // The mismatched call started fresh and dropped the parked state
+        assert_eq!(suspended_call(), "");
+    }
+
+    #[test]
+    fn unmetered_restores_budget_and_parked_progress() {
+        set_fuel(50);
+        memory_intensive(SIZE);
+        unmetered(|| assert_eq!(memory_intensive(4), 6));
+        assert_eq!(state(), (Some(0), 50));
+        assert_eq!(suspended_call(), "memory_intensive");
+        cancel_suspended();
+    }
+}
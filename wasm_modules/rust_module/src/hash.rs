@@ -0,0 +1,26 @@
+/*!
+ * Hash functions (`hash` feature)
+ *
+ * Hosts persist these hashes, so the output is part of the `hash.djb2`
+ * contract: changing it bumps `version::ABI_VERSION`.
+ */
+
+#[cfg(not(component_core))]
+use wasm_bindgen::prelude::*;
+
+use crate::error::clear_last_error;
+
+/// Initial DJB2 state
+pub const DJB2_SEED: u32 = 5381;
+
+/// Fold bytes into a DJB2 state
+pub fn djb2_update(hash: u32, bytes: &[u8]) -> u32 {
+    crate::kernels::djb2_update(hash, bytes)
+}
+
+/// Calculate hash of string (simple DJB2 hash)
+#[cfg_attr(not(component_core), wasm_bindgen)]
+pub fn hash_string(s: &str) -> u32 {
+    clear_last_error();
+    djb2_update(DJB2_SEED, s.as_bytes())
+}
@@ -0,0 +1,201 @@
+/*!
+ * Instrumented global allocator
+ *
+ * Wraps the system allocator (dlmalloc on wasm32) and counts every
+ * allocation, so hosts can see real heap usage inside the module instead of
+ * whole 64KB pages. `WASMMemoryManager.sampleHeap()` reads the
+ * `echelon_heap_*` C exports after each call to spot leaks.
+ *
+ * With the `arena` feature, allocations inside a `begin_scope()` /
+ * `end_scope()` pair are served by `crate::arena` and counted here too.
+ *
+ * `heap_size` is the linear memory above `__heap_base` on wasm32 (what the
+ * allocator has claimed with `memory.grow`) and the peak live bytes
+ * elsewhere. `fragmentation` is the share of that heap not holding live data.
+ */
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+use wasm_bindgen::prelude::*;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static DEALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static REALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// System allocator that records live/peak bytes and call counts
+pub struct TrackingAllocator;
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+fn grow(bytes: usize) {
+    let live = LIVE.fetch_add(bytes, Relaxed) + bytes;
+    PEAK.fetch_max(live, Relaxed);
+}
+
+/// Serve `layout` from an open arena scope
+#[cfg(feature = "arena")]
+fn arena_alloc(layout: Layout) -> Option<*mut u8> {
+    if !crate::arena::active() {
+        return None;
+    }
+    let ptr = crate::arena::alloc(layout)?;
+    ALLOCATIONS.fetch_add(1, Relaxed);
+    grow(layout.size());
+    Some(ptr)
+}
+
+#[cfg(not(feature = "arena"))]
+fn arena_alloc(_layout: Layout) -> Option<*mut u8> {
+    None
+}
+
+#[cfg(feature = "arena")]
+fn in_arena(ptr: *mut u8) -> bool {
+    crate::arena::contains(ptr)
+}
+
+#[cfg(not(feature = "arena"))]
+fn in_arena(_ptr: *mut u8) -> bool {
+    false
+}
+
+/// Account for arena bytes freed by `end_scope`
+#[cfg(feature = "arena")]
+pub(crate) fn release(bytes: usize) {
+    LIVE.fetch_sub(bytes, Relaxed);
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if let Some(ptr) = arena_alloc(layout) {
+            return ptr;
+        }
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            ALLOCATIONS.fetch_add(1, Relaxed);
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if let Some(ptr) = arena_alloc(layout) {
+            // Arena chunks are reused across scopes
+            std::ptr::write_bytes(ptr, 0, layout.size());
+            return ptr;
+        }
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            ALLOCATIONS.fetch_add(1, Relaxed);
+            grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if in_arena(ptr) {
+            // Released all at once by `end_scope`
+            DEALLOCATIONS.fetch_add(1, Relaxed);
+            return;
+        }
+        System.dealloc(ptr, layout);
+        DEALLOCATIONS.fetch_add(1, Relaxed);
+        LIVE.fetch_sub(layout.size(), Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if in_arena(ptr) {
+            // Bump blocks cannot grow in place: copy into a new block
+            let Ok(new_layout) = Layout::from_size_align(new_size, layout.align()) else {
+                return std::ptr::null_mut();
+            };
+            let new = self.alloc(new_layout);
+            if !new.is_null() {
+                std::ptr::copy_nonoverlapping(ptr, new, layout.size().min(new_size));
+                REALLOCATIONS.fetch_add(1, Relaxed);
+            }
+            return new;
+        }
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            REALLOCATIONS.fetch_add(1, Relaxed);
+            if new_size >= layout.size() {
+                grow(new_size - layout.size());
+            } else {
+                LIVE.fetch_sub(layout.size() - new_size, Relaxed);
+            }
+        }
+        new
+    }
+}
+
+/// Snapshot of the allocator counters
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HeapStats {
+    /// Bytes currently allocated
+    pub live_bytes: usize,
+    /// Highest `live_bytes` since start or `reset_heap_peak`
+    pub peak_bytes: usize,
+    /// Successful `alloc` calls
+    pub allocations: usize,
+    /// `dealloc` calls
+    pub deallocations: usize,
+    /// Successful `realloc` calls
+    pub reallocations: usize,
+    /// Bytes claimed by the allocator
+    pub heap_size: usize,
+    /// Share of `heap_size` not holding live data (0.0 - 1.0)
+    pub fragmentation: f64,
+}
+
+/// Bytes of linear memory the allocator has claimed above `__heap_base`
+#[cfg(target_arch = "wasm32")]
+pub fn heap_size() -> usize {
+    extern "C" {
+        static __heap_base: u8;
+    }
+    let base = std::ptr::addr_of!(__heap_base) as usize;
+    (core::arch::wasm32::memory_size(0) * 65536).saturating_sub(base)
+}
+
+/// Peak live bytes (no separate heap region outside wasm32)
+#[cfg(not(target_arch = "wasm32"))]
+pub fn heap_size() -> usize {
+    PEAK.load(Relaxed)
+}
+
+/// Share of the heap not holding live data
+pub fn fragmentation() -> f64 {
+    let heap = heap_size();
+    if heap == 0 {
+        return 0.0;
+    }
+    heap.saturating_sub(LIVE.load(Relaxed)) as f64 / heap as f64
+}
+
+/// Current allocator counters
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn heap_stats() -> HeapStats {
+    HeapStats {
+        live_bytes: LIVE.load(Relaxed),
+        peak_bytes: PEAK.load(Relaxed),
+        allocations: ALLOCATIONS.load(Relaxed),
+        deallocations: DEALLOCATIONS.load(Relaxed),
+        reallocations: REALLOCATIONS.load(Relaxed),
+        heap_size: heap_size(),
+        fragmentation: fragmentation(),
+    }
+}
+
+/// Reset `peak_bytes` to the current live bytes
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn reset_heap_peak() {
+    PEAK.store(LIVE.load(Relaxed), Relaxed);
+}
@@ -0,0 +1,626 @@
+/*!
+ * HTTP handler ABI (`http` feature)
+ *
+ * `handle_request` / `echelon_handle_request` take one serialized request and
+ * return one serialized response, so `wasmRequestHandler` in
+ * `framework/middleware/wasm.ts` can serve whole endpoints from the module:
+ *
+ * ```text
+ * request:  str(method) str(url) u32(count) (str(name) str(value))*count bytes(body)
+ * response: u32(status) u32(count) (str(name) str(value))*count bytes(body)
+ *
+ * u32 = 4 bytes LE, bytes = [len: u32] [len bytes], str = UTF-8 bytes
+ * ```
+ *
+ * `url` is the path plus optional query (`/text/word_count?s=a+b`); the
+ * origin of a full URL is ignored. Requests are dispatched by a `Router`;
+ * the module's own endpoints are registered in `routes()`:
+ *
+ * - `GET /health`
+ * - `POST /text/:op` - `count_vowels`, `word_count`, ... on the body (`text`)
+ * - `POST /hash` - `hash_string` of the body (`hash`)
+ * - `POST /caesar?shift=3` - `caesar_encrypt` of the body (`cipher`)
+ * - `GET /math/:op/:a/:b` - `add` / `multiply` (`math`)
+ * - `POST /rpc` - one JSON-RPC line, as the WASI command (`rpc`)
+ *
+ * Text endpoints also take their input from `?s=` when the body is empty.
+ * Results are JSON `{"result": ...}`; handler errors become
+ * `{"error": ..., "code": ...}` with a 4xx/5xx status.
+ */
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{self, guard, EchelonError, ErrorCode, Result};
+use crate::logging::push_json_str;
+
+/// Decoded request
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /// Path and query, without origin
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Path without the query string
+    pub fn path(&self) -> &str {
+        self.url.split(['?', '#']).next().unwrap_or("")
+    }
+
+    /// First query parameter named `name`, percent-decoded
+    pub fn query(&self, name: &str) -> Option<String> {
+        let query = self.url.split('#').next()?.split_once('?')?.1;
+        query.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key, true) == name).then(|| percent_decode(value, true))
+        })
+    }
+
+    /// First header named `name` (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Body as UTF-8
+    pub fn text(&self) -> Result<&str> {
+        std::str::from_utf8(&self.body).map_err(|err| {
+            EchelonError::new(
+                ErrorCode::InvalidUtf8,
+                format!("invalid UTF-8 in body at byte {}", err.valid_up_to()),
+            )
+        })
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self> {
+        let mut reader = Reader { buf, offset: 0 };
+        let method = reader.string()?;
+        let url = reader.string()?;
+        let count = reader.u32()? as usize;
+        let mut headers = Vec::with_capacity(count.min(buf.len() / 8));
+        for _ in 0..count {
+            headers.push((reader.string()?, reader.string()?));
+        }
+        let body = reader.bytes()?.to_vec();
+
+        // Accept absolute URLs by dropping the origin
+        let url = match url.split_once("://") {
+            Some((_, rest)) => rest.find('/').map_or_else(|| "/".to_string(), |at| rest[at..].to_string()),
+            None => url,
+        };
+
+        Ok(Self { method: method.to_ascii_uppercase(), url, headers, body })
+    }
+
+    /// Serialize in the request layout (for hosts written in Rust)
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(16 + self.url.len() + self.body.len());
+        put_bytes(&mut out, self.method.as_bytes());
+        put_bytes(&mut out, self.url.as_bytes());
+        put_headers(&mut out, &self.headers);
+        put_bytes(&mut out, &self.body);
+        out
+    }
+}
+
+/// Response built by a handler
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Self { status, headers: Vec::new(), body: Vec::new() }
+    }
+
+    pub fn text(status: u16, body: impl Into<String>) -> Self {
+        Self::new(status)
+            .with_header("content-type", "text/plain; charset=utf-8")
+            .with_body(body.into().into_bytes())
+    }
+
+    /// Response with an already serialized JSON body
+    pub fn json(status: u16, body: impl Into<String>) -> Self {
+        Self::new(status)
+            .with_header("content-type", "application/json")
+            .with_body(body.into().into_bytes())
+    }
+
+    /// `200 {"result": <json>}`
+    pub fn result(json: impl Into<String>) -> Self {
+        Self::json(200, format!("{{\"result\":{}}}", json.into()))
+    }
+
+    /// Error response with a status matching the error code
+    pub fn error(err: &EchelonError) -> Self {
+        let status = match err.code {
+            ErrorCode::InvalidUtf8 | ErrorCode::InvalidArgument | ErrorCode::Overflow => 400,
+            ErrorCode::BudgetExhausted => 503,
+            ErrorCode::HostError => 502,
+            ErrorCode::Ok | ErrorCode::OutOfMemory => 500,
+        };
+        let mut body = String::from("{\"error\":");
+        push_json_str(&mut body, &err.message);
+        body.push_str(&format!(",\"code\":{}}}", err.code as u32));
+        Self::json(status, body)
+    }
+
+    /// `404 {"error": message}`
+    pub fn not_found(message: &str) -> Self {
+        let mut body = String::from("{\"error\":");
+        push_json_str(&mut body, message);
+        body.push('}');
+        Self::json(404, body)
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_body(mut self, body: Vec<u8>) -> Self {
+        self.body = body;
+        self
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(12 + self.body.len());
+        out.extend_from_slice(&(self.status as u32).to_le_bytes());
+        put_headers(&mut out, &self.headers);
+        put_bytes(&mut out, &self.body);
+        out
+    }
+}
+
+/// Path parameters captured by `:name` and `*name` segments
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Params(Vec<(String, String)>);
+
+impl Params {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// Parameter that the route pattern guarantees
+    pub fn require(&self, name: &str) -> Result<&str> {
+        self.get(name)
+            .ok_or_else(|| EchelonError::new(ErrorCode::InvalidArgument, format!("missing path parameter `{name}`")))
+    }
+}
+
+/// Endpoint function
+pub type Handler = fn(&Request, &Params) -> Result<Response>;
+
+struct Route {
+    method: &'static str,
+    segments: Vec<&'static str>,
+    handler: Handler,
+}
+
+/// Method and path pattern dispatch
+///
+/// Patterns are `/`-separated literals, `:name` (one segment) and a final
+/// `*name` (rest of the path). Routes are tried in registration order.
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn route(mut self, method: &'static str, pattern: &'static str, handler: Handler) -> Self {
+        let segments = pattern.split('/').filter(|s| !s.is_empty()).collect();
+        self.routes.push(Route { method, segments, handler });
+        self
+    }
+
+    pub fn get(self, pattern: &'static str, handler: Handler) -> Self {
+        self.route("GET", pattern, handler)
+    }
+
+    pub fn post(self, pattern: &'static str, handler: Handler) -> Self {
+        self.route("POST", pattern, handler)
+    }
+
+    /// Run the matching handler; 404 / 405 when nothing matches
+    pub fn dispatch(&self, request: &Request) -> Response {
+        let path: Vec<&str> = request.path().split('/').filter(|s| !s.is_empty()).collect();
+        let mut allowed: Vec<&str> = Vec::new();
+
+        for route in &self.routes {
+            let Some(params) = match_path(&route.segments, &path) else {
+                continue;
+            };
+            let head = request.method == "HEAD" && route.method == "GET";
+            if route.method != request.method && !head {
+                allowed.push(route.method);
+                continue;
+            }
+
+            let mut response = (route.handler)(request, &params).unwrap_or_else(|err| Response::error(&err));
+            if head {
+                response.body.clear();
+            }
+            return response;
+        }
+
+        if allowed.is_empty() {
+            Response::not_found(&format!("no route for {}", request.path()))
+        } else {
+            allowed.dedup();
+            Response::json(405, "{\"error\":\"method not allowed\"}").with_header("allow", allowed.join(", "))
+        }
+    }
+}
+
+fn match_path(pattern: &[&str], path: &[&str]) -> Option<Params> {
+    let mut params = Vec::new();
+    for (index, segment) in pattern.iter().enumerate() {
+        if let Some(name) = segment.strip_prefix('*') {
+            params.push((name.to_string(), path.get(index..)?.join("/")));
+            return Some(Params(params));
+        }
+        let value = path.get(index)?;
+        match segment.strip_prefix(':') {
+            Some(name) => params.push((name.to_string(), percent_decode(value, false))),
+            None if segment == value => {}
+            None => return None,
+        }
+    }
+    (pattern.len() == path.len()).then_some(Params(params))
+}
+
+/// The module's endpoints (only the enabled export groups are routed)
+pub fn routes() -> Router {
+    let router = Router::new().get("/health", |_, _| {
+        Ok(Response::json(
+            200,
+            format!(
+                "{{\"status\":\"ok\",\"version\":\"{}\",\"abi_version\":{}}}",
+                env!("CARGO_PKG_VERSION"),
+                crate::version::ABI_VERSION
+            ),
+        ))
+    });
+    #[cfg(feature = "text")]
+    let router = router.post("/text/:op", handlers::text);
+    #[cfg(feature = "hash")]
+    let router = router.post("/hash", handlers::hash);
+    #[cfg(feature = "cipher")]
+    let router = router.post("/caesar", handlers::caesar);
+    #[cfg(feature = "math")]
+    let router = router.get("/math/:op/:a/:b", handlers::math);
+    #[cfg(feature = "rpc")]
+    let router = router.post("/rpc", handlers::rpc);
+    router
+}
+
+#[cfg(any(feature = "text", feature = "hash", feature = "cipher", feature = "math", feature = "rpc"))]
+mod handlers {
+    use super::*;
+
+    /// Body text, or the `s` query parameter when the body is empty
+    #[cfg(any(feature = "text", feature = "hash"))]
+    fn input(request: &Request) -> Result<String> {
+        match request.query("s") {
+            Some(s) if request.body.is_empty() => Ok(s),
+            _ => request.text().map(str::to_string),
+        }
+    }
+
+    /// Turn an error recorded by a `guard`ed export into `Err`
+    #[cfg(feature = "math")]
+    fn checked<T>(value: T) -> Result<T> {
+        error::last_error().map_or(Ok(value), Err)
+    }
+
+    #[cfg(feature = "text")]
+    pub fn text(request: &Request, params: &Params) -> Result<Response> {
+        let s = input(request)?;
+        let result = match params.require("op")? {
+            "count_vowels" => crate::count_vowels(&s).to_string(),
+            "word_count" => crate::word_count(&s).to_string(),
+            "longest_word_length" => crate::longest_word_length(&s).to_string(),
+            "is_palindrome" => crate::is_palindrome(&s).to_string(),
+            "reverse_string" => {
+                let mut out = String::new();
+                push_json_str(&mut out, &crate::reverse_string(&s));
+                out
+            }
+            op => return Ok(Response::not_found(&format!("unknown text op `{op}`"))),
+        };
+        Ok(Response::result(result))
+    }
+
+    #[cfg(feature = "hash")]
+    pub fn hash(request: &Request, _: &Params) -> Result<Response> {
+        Ok(Response::result(crate::hash_string(&input(request)?).to_string()))
+    }
+
+    #[cfg(feature = "cipher")]
+    pub fn caesar(request: &Request, _: &Params) -> Result<Response> {
+        let shift = match request.query("shift") {
+            Some(shift) => shift
+                .parse::<u8>()
+                .map_err(|_| EchelonError::new(ErrorCode::InvalidArgument, "`shift` must be an integer in 0..=255"))?,
+            None => 3,
+        };
+        let mut out = String::new();
+        push_json_str(&mut out, &crate::caesar_encrypt(request.text()?, shift));
+        Ok(Response::result(out))
+    }
+
+    #[cfg(feature = "math")]
+    pub fn math(_: &Request, params: &Params) -> Result<Response> {
+        let int = |name: &str| {
+            params.require(name)?.parse::<i32>().map_err(|_| {
+                EchelonError::new(ErrorCode::InvalidArgument, format!("`{name}` must be a 32-bit integer"))
+            })
+        };
+        let (a, b) = (int("a")?, int("b")?);
+        let result = match params.require("op")? {
+            "add" => checked(crate::add(a, b))?,
+            "multiply" => checked(crate::multiply(a, b))?,
+            op => return Ok(Response::not_found(&format!("unknown math op `{op}`"))),
+        };
+        Ok(Response::result(result.to_string()))
+    }
+
+    #[cfg(feature = "rpc")]
+    pub fn rpc(request: &Request, _: &Params) -> Result<Response> {
+        Ok(match crate::rpc::handle_line(request.text()?) {
+            Some(reply) => Response::json(200, reply),
+            None => Response::new(204),
+        })
+    }
+}
+
+thread_local! {
+    // Built on the first request, which may run inside a request scope
+    static ROUTER: Router = crate::persistent(routes);
+}
+
+/// Decode, dispatch and encode one request
+pub fn handle(buf: &[u8]) -> Result<Vec<u8>> {
+    let request = Request::decode(buf)?;
+    let response = ROUTER.with(|router| router.dispatch(&request));
+    // Handler errors are already part of the response
+    error::clear_last_error();
+    Ok(response.encode())
+}
+
+/// Handle one serialized request, returns the serialized response
+///
+/// Records `InvalidArgument` and returns nothing if the request is malformed.
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn handle_request(request: &[u8]) -> Vec<u8> {
+    guard(|| handle(request))
+}
+
+/// Decode `%XX` escapes (and `+` as space in query strings)
+fn percent_decode(s: &str, plus_as_space: bool) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = |at: usize| bytes.get(at).and_then(|b| (*b as char).to_digit(16));
+        match bytes[index] {
+            b'%' => match (hex(index + 1), hex(index + 2)) {
+                (Some(hi), Some(lo)) => {
+                    out.push((hi * 16 + lo) as u8);
+                    index += 3;
+                    continue;
+                }
+                _ => out.push(b'%'),
+            },
+            b'+' if plus_as_space => out.push(b' '),
+            b => out.push(b),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(bytes);
+}
+
+fn put_headers(out: &mut Vec<u8>, headers: &[(String, String)]) {
+    out.extend_from_slice(&(headers.len() as u32).to_le_bytes());
+    for (name, value) in headers {
+        put_bytes(out, name.as_bytes());
+        put_bytes(out, value.as_bytes());
+    }
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn truncated() -> EchelonError {
+        EchelonError::new(ErrorCode::InvalidArgument, "truncated HTTP request buffer")
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.buf.get(self.offset..self.offset + 4).ok_or_else(Self::truncated)?;
+        self.offset += 4;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        let end = self.offset.checked_add(len).ok_or_else(Self::truncated)?;
+        let bytes = self.buf.get(self.offset..end).ok_or_else(Self::truncated)?;
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let bytes = self.bytes()?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| EchelonError::new(ErrorCode::InvalidUtf8, "invalid UTF-8 in HTTP request head"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, url: &str) -> Request {
+        Request { method: method.to_string(), url: url.to_string(), ..Request::default() }
+    }
+
+    fn truncated(buf: &[u8]) -> bool {
+        Request::decode(buf).is_err_and(|err| err.code == ErrorCode::InvalidArgument)
+    }
+
+    #[test]
+    fn decode_round_trips() {
+        let mut original = request("POST", "/text/word_count?s=a+b");
+        original.headers.push(("Content-Type".to_string(), "text/plain".to_string()));
+        original.body = b"one two".to_vec();
+        assert_eq!(Request::decode(&original.encode()), Ok(original));
+    }
+
+    #[test]
+    fn decode_rejects_truncated_buffers() {
+        let buf = Request { body: b"body".to_vec(), ..request("GET", "/health") }.encode();
+        for len in 0..buf.len() {
+            assert!(truncated(&buf[..len]), "prefix of {len} bytes decoded");
+        }
+    }
+
+    #[test]
+    fn decode_rejects_oversized_lengths() {
+        // Method length past the end of the buffer
+        let mut buf = request("GET", "/").encode();
+        buf[..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(truncated(&buf));
+
+        // Header count far larger than the buffer could hold
+        let mut buf = Vec::new();
+        put_bytes(&mut buf, b"GET");
+        put_bytes(&mut buf, b"/");
+        buf.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(truncated(&buf));
+
+        // Body length one byte longer than the body
+        let mut buf = Request { body: b"abc".to_vec(), ..request("POST", "/hash") }.encode();
+        let at = buf.len() - 7;
+        buf[at..at + 4].copy_from_slice(&4u32.to_le_bytes());
+        assert!(truncated(&buf));
+    }
+
+    #[test]
+    fn decode_rejects_invalid_utf8_head() {
+        let mut buf = Vec::new();
+        put_bytes(&mut buf, b"GET");
+        put_bytes(&mut buf, b"/\xff");
+        put_headers(&mut buf, &[]);
+        put_bytes(&mut buf, b"");
+        assert_eq!(Request::decode(&buf).map_err(|err| err.code), Err(ErrorCode::InvalidUtf8));
+    }
+
+    #[test]
+    fn decode_strips_origin_and_uppercases_method() {
+        let decode = |url: &str| Request::decode(&request("get", url).encode()).unwrap();
+        assert_eq!(decode("https://example.com/text/count_vowels?s=x").url, "/text/count_vowels?s=x");
+        assert_eq!(decode("http://localhost:8080").url, "/");
+        assert_eq!(decode("/health").url, "/health");
+        assert_eq!(decode("/health").method, "GET");
+    }
+
+    fn echo(request: &Request, params: &Params) -> Result<Response> {
+        let body = format!("{} {:?}", request.method, params.0);
+        Ok(Response::text(200, body))
+    }
+
+    fn router() -> Router {
+        Router::new()
+            .get("/items/:id", echo)
+            .post("/items/:id", echo)
+            .post("/upload", echo)
+            .get("/files/*rest", echo)
+    }
+
+    fn body(response: &Response) -> &str {
+        std::str::from_utf8(&response.body).unwrap()
+    }
+
+    #[test]
+    fn dispatch_captures_params() {
+        let response = router().dispatch(&request("GET", "/items/a%20b?x=1"));
+        assert_eq!(response.status, 200);
+        assert_eq!(body(&response), r#"GET [("id", "a b")]"#);
+
+        let response = router().dispatch(&request("GET", "/files/docs/readme.md"));
+        assert_eq!(body(&response), r#"GET [("rest", "docs/readme.md")]"#);
+        let response = router().dispatch(&request("GET", "/files"));
+        assert_eq!(body(&response), r#"GET [("rest", "")]"#);
+
+        assert_eq!(router().dispatch(&request("GET", "/items")).status, 404);
+        assert_eq!(router().dispatch(&request("GET", "/items/1/2")).status, 404);
+    }
+
+    #[test]
+    fn head_falls_back_to_get_without_body() {
+        let response = router().dispatch(&request("HEAD", "/items/7"));
+        assert_eq!(response.status, 200);
+        assert!(response.body.is_empty());
+        assert_eq!(response.headers, router().dispatch(&request("GET", "/items/7")).headers);
+    }
+
+    #[test]
+    fn method_mismatch_is_405_with_allow() {
+        let allow = |method: &str, url: &str| {
+            let response = router().dispatch(&request(method, url));
+            assert_eq!(response.status, 405);
+            response.headers.into_iter().find(|(name, _)| name == "allow").map(|(_, value)| value)
+        };
+        assert_eq!(allow("DELETE", "/items/7").as_deref(), Some("GET, POST"));
+        assert_eq!(allow("GET", "/upload").as_deref(), Some("POST"));
+
+        assert_eq!(router().dispatch(&request("DELETE", "/missing")).status, 404);
+    }
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("a%20b+c", false), "a b+c");
+        assert_eq!(percent_decode("a%20b+c", true), "a b c");
+        assert_eq!(percent_decode("%C3%A9t%c3%a9", false), "été");
+        // Malformed escapes pass through
+        assert_eq!(percent_decode("100%", false), "100%");
+        assert_eq!(percent_decode("%zz%4", false), "%zz%4");
+        // Invalid UTF-8 after decoding becomes U+FFFD
+        assert_eq!(percent_decode("%ff", false), "\u{fffd}");
+    }
+
+    #[test]
+    fn query_decodes_keys_and_values() {
+        let request = request("GET", "/text/word_count?s=one+two%21&shift=3#s=frag");
+        assert_eq!(request.query("s").as_deref(), Some("one two!"));
+        assert_eq!(request.query("shift").as_deref(), Some("3"));
+        assert_eq!(request.query("missing"), None);
+        assert_eq!(request.path(), "/text/word_count");
+    }
+
+    #[test]
+    fn handle_serves_health() {
+        let response = handle(&request("GET", "/health").encode()).unwrap();
+        assert_eq!(u32::from_le_bytes([response[0], response[1], response[2], response[3]]), 200);
+    }
+}
@@ -0,0 +1,339 @@
+/*!
+ * Byte-scanning kernels behind the text and hash exports
+ *
+ * `count_vowels`, `word_count`, `is_palindrome` and the DJB2 hash each have
+ * a scalar version and a wasm32 `simd128` version that works on 16 bytes at
+ * a time. The choice is made at build time: the SIMD kernels are used when
+ * the module is built with `-C target-feature=+simd128` (`SIMD=1
+ * ./build.sh`), the scalar ones everywhere else. Both return exactly the
+ * same results, including for non-ASCII input.
+ */
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+pub use simd::*;
+
+#[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+pub use scalar::*;
+
+/// Kernel set compiled into this build (`"simd128"` or `"scalar"`)
+pub const KERNELS: &str = if cfg!(all(target_arch = "wasm32", target_feature = "simd128")) {
+    "simd128"
+} else {
+    "scalar"
+};
+
+/// One-char-at-a-time reference implementations
+pub mod scalar {
+    /// Count ASCII vowels, either case
+    pub fn count_vowels(s: &str) -> usize {
+        s.chars()
+            .filter(|c| matches!(c.to_ascii_lowercase(), 'a' | 'e' | 'i' | 'o' | 'u'))
+            .count()
+    }
+
+    /// Count runs of non-whitespace chars
+    pub fn word_count(s: &str) -> usize {
+        s.split_whitespace().count()
+    }
+
+    /// Compare alphanumeric chars, ASCII case-folded, with their reverse
+    pub fn is_palindrome(s: &str) -> bool {
+        let cleaned: String = s.chars()
+            .filter(|c| c.is_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        cleaned == cleaned.chars().rev().collect::<String>()
+    }
+
+    /// Fold bytes into a DJB2 state
+    pub fn djb2_update(mut hash: u32, bytes: &[u8]) -> u32 {
+        for &c in bytes {
+            hash = ((hash << 5).wrapping_add(hash)).wrapping_add(c as u32);
+        }
+        hash
+    }
+}
+
+/// wasm32 `simd128` implementations
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+pub mod simd {
+    use core::arch::wasm32::*;
+
+    const LANES: usize = 16;
+
+    /// `33^n` for n in 0..=16 (wrapping, as DJB2 itself)
+    const POWERS: [u32; LANES + 1] = {
+        let mut powers = [1u32; LANES + 1];
+        let mut n = 1;
+        while n <= LANES {
+            powers[n] = powers[n - 1].wrapping_mul(33);
+            n += 1;
+        }
+        powers
+    };
+
+    fn load(bytes: &[u8]) -> v128 {
+        debug_assert!(bytes.len() >= LANES);
+        // SAFETY: at least 16 readable bytes; v128_load has no alignment requirement
+        unsafe { v128_load(bytes.as_ptr() as *const v128) }
+    }
+
+    fn lanes(v: v128) -> [u8; LANES] {
+        let mut out = [0u8; LANES];
+        // SAFETY: `out` is 16 writable bytes
+        unsafe { v128_store(out.as_mut_ptr() as *mut v128, v) };
+        out
+    }
+
+    /// Lanes with `lo <= byte <= lo + span`
+    fn in_range(v: v128, lo: u8, span: u8) -> v128 {
+        u8x16_le(u8x16_sub(v, u8x16_splat(lo)), u8x16_splat(span))
+    }
+
+    fn is_vowel_byte(b: u8) -> bool {
+        matches!(b | 0x20, b'a' | b'e' | b'i' | b'o' | b'u')
+    }
+
+    /// Count ASCII vowels, either case
+    ///
+    /// Bytes of multi-byte chars are all >= 0x80, so counting bytes matches
+    /// counting chars.
+    pub fn count_vowels(s: &str) -> usize {
+        let mut chunks = s.as_bytes().chunks_exact(LANES);
+        let mut count = 0;
+
+        for chunk in &mut chunks {
+            let lower = v128_or(load(chunk), u8x16_splat(0x20));
+            let hits = v128_or(
+                v128_or(
+                    v128_or(u8x16_eq(lower, u8x16_splat(b'a')), u8x16_eq(lower, u8x16_splat(b'e'))),
+                    v128_or(u8x16_eq(lower, u8x16_splat(b'i')), u8x16_eq(lower, u8x16_splat(b'o'))),
+                ),
+                u8x16_eq(lower, u8x16_splat(b'u')),
+            );
+            count += u8x16_bitmask(hits).count_ones() as usize;
+        }
+
+        count + chunks.remainder().iter().filter(|&&b| is_vowel_byte(b)).count()
+    }
+
+    /// Count runs of non-whitespace chars
+    ///
+    /// All-ASCII blocks are classified 16 bytes at a time; blocks holding
+    /// other chars are walked with `char::is_whitespace`.
+    pub fn word_count(s: &str) -> usize {
+        let bytes = s.as_bytes();
+        let mut count = 0;
+        let mut pos = 0;
+        let mut after_space = true;
+
+        while pos < bytes.len() {
+            if bytes.len() - pos >= LANES {
+                let v = load(&bytes[pos..]);
+                if u8x16_bitmask(v) == 0 {
+                    // ASCII whitespace: ' ' and '\t'..='\r'
+                    let space = v128_or(u8x16_eq(v, u8x16_splat(b' ')), in_range(v, b'\t', 4));
+                    let space = u8x16_bitmask(space);
+                    let starts = !space & ((space << 1) | after_space as u16);
+                    count += starts.count_ones() as usize;
+                    after_space = space & 0x8000 != 0;
+                    pos += LANES;
+                    continue;
+                }
+            }
+
+            // Up to the first char boundary past this block
+            let end = (pos + LANES).min(bytes.len());
+            while pos < end {
+                let Some(c) = s[pos..].chars().next() else { break };
+                let space = c.is_whitespace();
+                count += (after_space && !space) as usize;
+                after_space = space;
+                pos += c.len_utf8();
+            }
+        }
+
+        count
+    }
+
+    /// Compare alphanumeric chars, ASCII case-folded, with their reverse
+    ///
+    /// ASCII input is filtered and lowercased 16 bytes at a time and compared
+    /// against its reverse in 16-byte blocks. Other input uses the scalar
+    /// version, as Unicode alphanumerics cannot be classified per byte.
+    pub fn is_palindrome(s: &str) -> bool {
+        if !s.is_ascii() {
+            return super::scalar::is_palindrome(s);
+        }
+
+        let bytes = s.as_bytes();
+        let mut cleaned = Vec::with_capacity(bytes.len());
+        let mut chunks = bytes.chunks_exact(LANES);
+
+        for chunk in &mut chunks {
+            let v = load(chunk);
+            let letter = in_range(v128_or(v, u8x16_splat(0x20)), b'a', 25);
+            let keep = u8x16_bitmask(v128_or(letter, in_range(v, b'0', 9)));
+            let lower = lanes(v128_or(v, v128_and(letter, u8x16_splat(0x20))));
+
+            if keep == 0xffff {
+                cleaned.extend_from_slice(&lower);
+            } else {
+                let mut keep = keep;
+                while keep != 0 {
+                    cleaned.push(lower[keep.trailing_zeros() as usize]);
+                    keep &= keep - 1;
+                }
+            }
+        }
+        cleaned.extend(
+            chunks.remainder().iter().filter(|b| b.is_ascii_alphanumeric()).map(|b| b.to_ascii_lowercase()),
+        );
+
+        let (mut front, mut back) = (0, cleaned.len());
+        while back - front >= 2 * LANES {
+            let head = load(&cleaned[front..]);
+            let tail = load(&cleaned[back - LANES..]);
+            let tail = i8x16_shuffle::<15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0>(tail, tail);
+            if !u8x16_all_true(u8x16_eq(head, tail)) {
+                return false;
+            }
+            front += LANES;
+            back -= LANES;
+        }
+
+        let rest = &cleaned[front..back];
+        rest.iter().eq(rest.iter().rev())
+    }
+
+    /// Fold bytes into a DJB2 state
+    ///
+    /// Sixteen steps of `h = h * 33 + c` collapse to
+    /// `h * 33^16 + sum(c[i] * 33^(15 - i))`, which holds in wrapping `u32`
+    /// arithmetic too, so each block is one vector multiply-add.
+    pub fn djb2_update(mut hash: u32, bytes: &[u8]) -> u32 {
+        let weights = [
+            u32x4(POWERS[15], POWERS[14], POWERS[13], POWERS[12]),
+            u32x4(POWERS[11], POWERS[10], POWERS[9], POWERS[8]),
+            u32x4(POWERS[7], POWERS[6], POWERS[5], POWERS[4]),
+            u32x4(POWERS[3], POWERS[2], POWERS[1], POWERS[0]),
+        ];
+        let mut chunks = bytes.chunks_exact(LANES);
+
+        for chunk in &mut chunks {
+            let v = load(chunk);
+            let (low, high) = (u16x8_extend_low_u8x16(v), u16x8_extend_high_u8x16(v));
+            let sum = i32x4_add(
+                i32x4_add(
+                    i32x4_mul(u32x4_extend_low_u16x8(low), weights[0]),
+                    i32x4_mul(u32x4_extend_high_u16x8(low), weights[1]),
+                ),
+                i32x4_add(
+                    i32x4_mul(u32x4_extend_low_u16x8(high), weights[2]),
+                    i32x4_mul(u32x4_extend_high_u16x8(high), weights[3]),
+                ),
+            );
+            let block = u32x4_extract_lane::<0>(sum)
+                .wrapping_add(u32x4_extract_lane::<1>(sum))
+                .wrapping_add(u32x4_extract_lane::<2>(sum))
+                .wrapping_add(u32x4_extract_lane::<3>(sum));
+            hash = hash.wrapping_mul(POWERS[LANES]).wrapping_add(block);
+        }
+
+        super::scalar::djb2_update(hash, chunks.remainder())
+    }
+}
+
+/// The active kernels against `scalar`
+///
+/// Natively both sides are the scalar kernels; run the SIMD side with
+/// `RUSTFLAGS="-C target-feature=+simd128" CARGO_TARGET_WASM32_WASIP1_RUNNER=tests/wasi_run.mjs
+/// cargo test --target wasm32-wasip1 --lib kernels`.
+#[cfg(test)]
+mod tests {
+    use super::scalar;
+
+    const PIECES: &[&str] = &[
+        "a", "E", "x", "Z", "7", " ", "\t", "\n", "\r", "\x0b", "\x0c", ",", "é", "ü", "ß", "Ω", "語", "😀",
+        "\u{a0}", "\u{85}", "\u{2003}", "\u{2028}", "\u{3000}", "\u{200b}", "\u{feff}", "Å",
+    ];
+
+    /// Cheap deterministic generator, so failures reproduce
+    fn next(state: &mut u64) -> usize {
+        *state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        (*state >> 33) as usize
+    }
+
+    fn corpus() -> Vec<String> {
+        let mut inputs: Vec<String> = [
+            "",
+            "The quick brown fox jumps over the lazy dog",
+            "A man, a plan, a canal: Panama",
+            "  leading and trailing  ",
+            "été",
+            "héllo wörld",
+            "日本語 テキスト",
+            "word\u{3000}word\u{2003}word\u{a0}word",
+        ]
+        .map(str::to_string)
+        .to_vec();
+
+        // A multi-byte char or Unicode space at every offset around the first lane boundaries
+        for piece in ["é", "語", "😀", "\u{a0}", "\u{2003}", "\u{3000}", " ", "\t"] {
+            for at in 0..40 {
+                let padding = "ab".repeat(at).chars().take(at).collect::<String>();
+                inputs.push(format!("{padding}{piece}xy zw"));
+                inputs.push(format!("{padding}{piece}{}", padding.chars().rev().collect::<String>()));
+            }
+        }
+
+        // ASCII palindromes across one, two and three lanes
+        for len in 1..=64 {
+            let half: String = (0..len).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+            let mirrored: String = half.chars().rev().collect();
+            inputs.push(format!("{half}{mirrored}"));
+            inputs.push(format!("{half}, {}!", mirrored.to_uppercase()));
+            inputs.push(format!("{half}x{mirrored}y"));
+        }
+
+        // Mixed, and ASCII-only so whole blocks take the vector path
+        let ascii: Vec<&str> = PIECES.iter().copied().filter(|piece| piece.is_ascii()).collect();
+        let mut state = 0x5eed;
+        for pieces in [PIECES, &ascii] {
+            for _ in 0..2000 {
+                let len = next(&mut state) % 80;
+                inputs.push((0..len).map(|_| pieces[next(&mut state) % pieces.len()]).collect());
+            }
+        }
+        inputs
+    }
+
+    #[test]
+    fn kernels_match_scalar() {
+        for input in corpus() {
+            assert_eq!(super::count_vowels(&input), scalar::count_vowels(&input), "count_vowels({input:?})");
+            assert_eq!(super::word_count(&input), scalar::word_count(&input), "word_count({input:?})");
+            assert_eq!(super::is_palindrome(&input), scalar::is_palindrome(&input), "is_palindrome({input:?})");
+            for seed in [0, 5381, u32::MAX] {
+                let bytes = input.as_bytes();
+                assert_eq!(
+                    super::djb2_update(seed, bytes),
+                    scalar::djb2_update(seed, bytes),
+                    "djb2_update({seed}, {input:?})"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn scalar_known_answers() {
+        assert_eq!(scalar::count_vowels("AEIOU aeiou y é"), 10);
+        assert_eq!(scalar::word_count(" one\u{3000}two\u{a0}three\u{200b}four "), 3);
+        // Case folding is ASCII-only
+        assert!(scalar::is_palindrome("Été, étÉ"));
+        assert!(!scalar::is_palindrome("Été, été"));
+        assert!(!scalar::is_palindrome("ab"));
+        assert_eq!(scalar::djb2_update(5381, b"hello"), 261_238_937);
+    }
+}
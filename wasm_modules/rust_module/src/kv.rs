@@ -0,0 +1,534 @@
+/*!
+ * Async exports over the host key-value store (`host-kv` feature)
+ *
+ * wasm32 builds with the feature import three functions from `env`, served
+ * by `WASMKvHost` in `wasm_kv.ts` through `WASMHostFunctionRegistry`:
+ *
+ * ```text
+ * kv_get(op, key_ptr, key_len)
+ * kv_set(op, key_ptr, key_len, value_ptr, value_len)
+ * kv_list(op, prefix_ptr, prefix_len, limit)
+ * ```
+ *
+ * An import copies its arguments, starts the operation and returns. The host
+ * later reports the outcome with `kv_complete(op, status, payload)` (raw:
+ * `echelon_kv_complete`), which may also happen before the import returns:
+ *
+ * - `Ok`: the value for `kv_get`, nothing for `kv_set`, and for `kv_list`
+ *   the entries as `u32` LE length-prefixed key, value, key, value, ...
+ * - `NotFound`: `kv_get` on a missing key.
+ * - `Denied` / `Failed`: the message, raised as `HostError` (`Denied` when
+ *   the sandbox lacks `kv-read` / `kv-write`).
+ *
+ * `get` / `set` / `list` are futures over these, so Rust code awaits lookups
+ * on demand instead of the host prefetching everything. The word index
+ * below is built on them and exported twice:
+ *
+ * - `#[wasm_bindgen] async fn`s returning Promises (wasm-bindgen-futures),
+ *   for hosts running the wasm-bindgen glue.
+ * - C ABI tasks: `echelon_index_document(...)` and friends return a task id,
+ *   the host feeds completions, and `echelon_task_take(task)` returns the
+ *   packed result once `echelon_task_state(task)` reports it finished.
+ *
+ * Native and WASI builds have no KV host; every operation fails with
+ * `HostError`.
+ *
+ * Operations and tasks outlive the call that started them, so the tables,
+ * payloads and task polls allocate through `crate::persistent` and survive
+ * `end_scope()`. The wasm-bindgen glue boxes an `async fn`'s future itself,
+ * so call those exports outside request scopes.
+ */
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{EchelonError, ErrorCode, Result};
+
+/// Key prefix of the word index entries (`idx/<word>` -> newline-separated document ids)
+pub const INDEX_PREFIX: &str = "idx/";
+
+/// Outcome of a host operation, passed to `kv_complete`
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KvStatus {
+    Ok = 0,
+    /// `kv_get` found no value
+    NotFound = 1,
+    /// The sandbox does not grant the capability; payload is the message
+    Denied = 2,
+    /// The store failed; payload is the message
+    Failed = 3,
+}
+
+#[cfg(all(target_arch = "wasm32", not(target_os = "wasi")))]
+mod host {
+    #[link(wasm_import_module = "env")]
+    extern "C" {
+        #[link_name = "kv_get"]
+        fn kv_get_import(op: u32, key_ptr: *const u8, key_len: usize);
+        #[link_name = "kv_set"]
+        fn kv_set_import(op: u32, key_ptr: *const u8, key_len: usize, value_ptr: *const u8, value_len: usize);
+        #[link_name = "kv_list"]
+        fn kv_list_import(op: u32, prefix_ptr: *const u8, prefix_len: usize, limit: u32);
+    }
+
+    pub fn kv_get(op: u32, key: &str) {
+        unsafe { kv_get_import(op, key.as_ptr(), key.len()) }
+    }
+
+    pub fn kv_set(op: u32, key: &str, value: &[u8]) {
+        unsafe { kv_set_import(op, key.as_ptr(), key.len(), value.as_ptr(), value.len()) }
+    }
+
+    pub fn kv_list(op: u32, prefix: &str, limit: u32) {
+        unsafe { kv_list_import(op, prefix.as_ptr(), prefix.len(), limit) }
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", not(target_os = "wasi"))))]
+mod host {
+    use super::{complete, KvStatus};
+
+    const MESSAGE: &[u8] = b"no KV host in this build";
+
+    pub fn kv_get(op: u32, _key: &str) {
+        complete(op, KvStatus::Failed as u32, MESSAGE);
+    }
+
+    pub fn kv_set(op: u32, _key: &str, _value: &[u8]) {
+        complete(op, KvStatus::Failed as u32, MESSAGE);
+    }
+
+    pub fn kv_list(op: u32, _prefix: &str, _limit: u32) {
+        complete(op, KvStatus::Failed as u32, MESSAGE);
+    }
+}
+
+enum Slot {
+    Pending(Option<Waker>),
+    Done(Result<Option<Vec<u8>>>),
+}
+
+thread_local! {
+    static NEXT_OP: Cell<u32> = const { Cell::new(1) };
+    /// Operations started and not yet awaited to completion
+    static OPS: RefCell<HashMap<u32, Slot>> = RefCell::new(HashMap::new());
+}
+
+/// Host operation in flight; dropping it makes a late completion a no-op
+struct KvOp {
+    op: u32,
+}
+
+impl KvOp {
+    fn start(issue: impl FnOnce(u32)) -> Self {
+        let op = NEXT_OP.get();
+        NEXT_OP.set(op.checked_add(1).unwrap_or(1));
+        crate::persistent(|| OPS.with_borrow_mut(|ops| ops.insert(op, Slot::Pending(None))));
+        issue(op);
+        KvOp { op }
+    }
+}
+
+impl Future for KvOp {
+    type Output = Result<Option<Vec<u8>>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        OPS.with_borrow_mut(|ops| match ops.remove(&self.op) {
+            Some(Slot::Done(result)) => Poll::Ready(result),
+            _ => {
+                crate::persistent(|| ops.insert(self.op, Slot::Pending(Some(cx.waker().clone()))));
+                Poll::Pending
+            }
+        })
+    }
+}
+
+impl Drop for KvOp {
+    fn drop(&mut self) {
+        OPS.with_borrow_mut(|ops| ops.remove(&self.op));
+    }
+}
+
+/// Record the outcome of operation `op` and wake whatever awaits it
+pub fn complete(op: u32, status: u32, payload: &[u8]) {
+    let result = crate::persistent(|| match status {
+        0 => Ok(Some(payload.to_vec())),
+        1 => Ok(None),
+        2 | 3 => Err(EchelonError::new(ErrorCode::HostError, String::from_utf8_lossy(payload))),
+        _ => Err(EchelonError::new(ErrorCode::HostError, format!("unknown KV status {status}"))),
+    });
+    let waker = OPS.with_borrow_mut(|ops| match ops.get_mut(&op) {
+        Some(slot @ Slot::Pending(_)) => match std::mem::replace(slot, Slot::Done(result)) {
+            Slot::Pending(waker) => waker,
+            Slot::Done(_) => None,
+        },
+        // Unknown, already completed or dropped
+        _ => None,
+    });
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// Value stored under `key`
+pub async fn get(key: &str) -> Result<Option<Vec<u8>>> {
+    KvOp::start(|op| host::kv_get(op, key)).await
+}
+
+/// Store `value` under `key`
+pub async fn set(key: &str, value: &[u8]) -> Result<()> {
+    KvOp::start(|op| host::kv_set(op, key, value)).await.map(|_| ())
+}
+
+/// Up to `limit` entries whose key starts with `prefix`, in key order
+pub async fn list(prefix: &str, limit: u32) -> Result<Vec<(String, Vec<u8>)>> {
+    let payload = KvOp::start(|op| host::kv_list(op, prefix, limit)).await?.unwrap_or_default();
+    decode_entries(&payload)
+}
+
+fn decode_entries(mut bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut entries = Vec::new();
+    while !bytes.is_empty() {
+        let key = String::from_utf8(take_item(&mut bytes)?)
+            .map_err(|_| EchelonError::new(ErrorCode::HostError, "kv_list returned a non UTF-8 key"))?;
+        entries.push((key, take_item(&mut bytes)?));
+    }
+    Ok(entries)
+}
+
+/// Split one `u32` LE length-prefixed item off the front of `bytes`
+fn take_item(bytes: &mut &[u8]) -> Result<Vec<u8>> {
+    let malformed = || EchelonError::new(ErrorCode::HostError, "malformed kv_list payload");
+    let (len, rest) = bytes.split_first_chunk::<4>().ok_or_else(malformed)?;
+    let len = u32::from_le_bytes(*len) as usize;
+    let item = rest.get(..len).ok_or_else(malformed)?.to_vec();
+    *bytes = &rest[len..];
+    Ok(item)
+}
+
+/// Lowercased words of `text`, trimmed of surrounding punctuation
+fn words(text: &str) -> BTreeSet<String> {
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+fn document_ids(value: Option<Vec<u8>>) -> Vec<String> {
+    value
+        .map(|bytes| String::from_utf8_lossy(&bytes).lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Add `doc_id` to the index entry of every word in `text`, returns the distinct words
+///
+/// Entries are read and written back one at a time, so concurrent calls
+/// touching the same words can lose updates.
+pub async fn index(doc_id: &str, text: &str) -> Result<u32> {
+    if doc_id.is_empty() || doc_id.contains('\n') {
+        return Err(EchelonError::new(ErrorCode::InvalidArgument, "document id must be non-empty without newlines"));
+    }
+
+    let words = words(text);
+    for word in &words {
+        let key = format!("{INDEX_PREFIX}{word}");
+        let mut ids = document_ids(get(&key).await?);
+        if !ids.iter().any(|id| id == doc_id) {
+            ids.push(doc_id.to_string());
+            set(&key, ids.join("\n").as_bytes()).await?;
+        }
+    }
+    Ok(words.len() as u32)
+}
+
+/// Documents containing every word of `query`, sorted
+pub async fn search(query: &str) -> Result<Vec<String>> {
+    let mut matches: Option<BTreeSet<String>> = None;
+    for word in words(query) {
+        let ids: BTreeSet<String> = document_ids(get(&format!("{INDEX_PREFIX}{word}")).await?).into_iter().collect();
+        let matches = matches.get_or_insert_with(|| ids.clone());
+        matches.retain(|id| ids.contains(id));
+        if matches.is_empty() {
+            break;
+        }
+    }
+    Ok(matches.unwrap_or_default().into_iter().collect())
+}
+
+/// Up to `limit` indexed words starting with `prefix`
+pub async fn indexed(prefix: &str, limit: u32) -> Result<Vec<String>> {
+    let entries = list(&format!("{INDEX_PREFIX}{}", prefix.to_lowercase()), limit).await?;
+    Ok(entries
+        .into_iter()
+        .filter_map(|(key, _)| key.strip_prefix(INDEX_PREFIX).map(str::to_string))
+        .collect())
+}
+
+type TaskFuture = Pin<Box<dyn Future<Output = Result<Vec<u8>>>>>;
+
+/// Wake flag of a C ABI task
+struct TaskWaker {
+    ready: AtomicBool,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.ready.store(true, Ordering::Relaxed);
+    }
+}
+
+enum Task {
+    Running(TaskFuture, Arc<TaskWaker>),
+    Finished(Result<Vec<u8>>),
+}
+
+thread_local! {
+    static NEXT_TASK: Cell<u32> = const { Cell::new(1) };
+    static TASKS: RefCell<HashMap<u32, Task>> = RefCell::new(HashMap::new());
+}
+
+/// Start `future` as a C ABI task and poll it until it waits on the host
+pub fn spawn(future: impl Future<Output = Result<Vec<u8>>> + 'static) -> u32 {
+    let task = NEXT_TASK.get();
+    NEXT_TASK.set(task.checked_add(1).unwrap_or(1));
+    crate::persistent(|| {
+        let waker = Arc::new(TaskWaker { ready: AtomicBool::new(true) });
+        TASKS.with_borrow_mut(|tasks| tasks.insert(task, Task::Running(Box::pin(future), waker)));
+    });
+    run_ready();
+    task
+}
+
+/// Poll every task woken since its last poll
+pub fn run_ready() {
+    crate::persistent(poll_ready)
+}
+
+fn poll_ready() {
+    loop {
+        let ready: Vec<u32> = TASKS.with_borrow(|tasks| {
+            tasks
+                .iter()
+                .filter_map(|(&id, task)| match task {
+                    Task::Running(_, waker) if waker.ready.swap(false, Ordering::Relaxed) => Some(id),
+                    _ => None,
+                })
+                .collect()
+        });
+        if ready.is_empty() {
+            return;
+        }
+
+        for id in ready {
+            // Taken out while polling, which can start operations or tasks
+            let Some(Task::Running(mut future, waker)) = TASKS.with_borrow_mut(|tasks| tasks.remove(&id)) else {
+                continue;
+            };
+            let task = match future.as_mut().poll(&mut Context::from_waker(&Waker::from(waker.clone()))) {
+                Poll::Ready(result) => Task::Finished(result),
+                Poll::Pending => Task::Running(future, waker),
+            };
+            TASKS.with_borrow_mut(|tasks| tasks.insert(id, task));
+        }
+    }
+}
+
+/// `0` unknown task, `1` waiting on the host, `2` finished
+pub fn task_state(task: u32) -> u32 {
+    TASKS.with_borrow(|tasks| match tasks.get(&task) {
+        None => 0,
+        Some(Task::Running(..)) => 1,
+        Some(Task::Finished(_)) => 2,
+    })
+}
+
+/// Result of a finished task; forgets the task
+pub fn take_task(task: u32) -> Result<Vec<u8>> {
+    TASKS.with_borrow_mut(|tasks| match tasks.remove(&task) {
+        Some(Task::Finished(result)) => result,
+        Some(running) => {
+            tasks.insert(task, running);
+            Err(EchelonError::new(ErrorCode::InvalidArgument, format!("task {task} is still waiting on the host")))
+        }
+        None => Err(EchelonError::new(ErrorCode::InvalidArgument, format!("unknown task {task}"))),
+    })
+}
+
+/// Drop a task and its pending operations
+pub fn cancel_task(task: u32) {
+    // Dropped outside the borrow: dropping the future drops its `KvOp`s
+    drop(TASKS.with_borrow_mut(|tasks| tasks.remove(&task)));
+}
+
+/// Report the outcome of a `kv_*` import call (`KvStatus` value)
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn kv_complete(op: u32, status: u32, payload: &[u8]) {
+    complete(op, status, payload);
+    run_ready();
+}
+
+/// Index the words of `text` under `doc_id`, resolves to the number of distinct words
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub async fn index_document(doc_id: String, text: String) -> std::result::Result<u32, JsValue> {
+    index(&doc_id, &text).await.map_err(js_error)
+}
+
+/// Documents containing every word of `query`
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub async fn search_index(query: String) -> std::result::Result<Vec<String>, JsValue> {
+    search(&query).await.map_err(js_error)
+}
+
+/// Up to `limit` indexed words starting with `prefix`
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub async fn indexed_words(prefix: String, limit: u32) -> std::result::Result<Vec<String>, JsValue> {
+    indexed(&prefix, limit).await.map_err(js_error)
+}
+
+fn js_error(err: EchelonError) -> JsValue {
+    JsError::new(&err.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    fn flag() -> (Arc<TaskWaker>, Waker) {
+        let flag = Arc::new(TaskWaker { ready: AtomicBool::new(false) });
+        (flag.clone(), Waker::from(flag))
+    }
+
+    fn poll(op: &mut KvOp, waker: &Waker) -> Poll<Result<Option<Vec<u8>>>> {
+        Pin::new(op).poll(&mut Context::from_waker(waker))
+    }
+
+    fn in_flight(op: u32) -> bool {
+        OPS.with_borrow(|ops| ops.contains_key(&op))
+    }
+
+    fn host_error(message: &str) -> EchelonError {
+        EchelonError::new(ErrorCode::HostError, message)
+    }
+
+    #[test]
+    fn completion_wakes_the_waiting_poll() {
+        let (flag, waker) = flag();
+        let mut op = KvOp::start(|_| {});
+        assert!(poll(&mut op, &waker).is_pending());
+        assert!(!flag.ready.load(Ordering::Relaxed));
+
+        complete(op.op, KvStatus::Ok as u32, b"value");
+        assert!(flag.ready.load(Ordering::Relaxed));
+        assert_eq!(poll(&mut op, &waker), Poll::Ready(Ok(Some(b"value".to_vec()))));
+        assert!(!in_flight(op.op));
+    }
+
+    #[test]
+    fn completion_before_the_first_poll_is_kept() {
+        let (_, waker) = flag();
+        let mut op = KvOp::start(|op| complete(op, KvStatus::NotFound as u32, b""));
+        assert_eq!(poll(&mut op, &waker), Poll::Ready(Ok(None)));
+    }
+
+    #[test]
+    fn statuses_map_to_results() {
+        let (_, waker) = flag();
+        let cases = [
+            (KvStatus::Denied as u32, Err(host_error("kv-read not granted"))),
+            (KvStatus::Failed as u32, Err(host_error("kv-read not granted"))),
+            (7, Err(host_error("unknown KV status 7"))),
+        ];
+        for (status, expected) in cases {
+            let mut op = KvOp::start(|op| complete(op, status, b"kv-read not granted"));
+            assert_eq!(poll(&mut op, &waker), Poll::Ready(expected), "status {status}");
+        }
+    }
+
+    #[test]
+    fn only_the_first_completion_counts() {
+        let (_, waker) = flag();
+        let mut op = KvOp::start(|_| {});
+        complete(op.op, KvStatus::Ok as u32, b"first");
+        complete(op.op, KvStatus::Failed as u32, b"second");
+        assert_eq!(poll(&mut op, &waker), Poll::Ready(Ok(Some(b"first".to_vec()))));
+
+        // Once taken, a late completion leaves nothing behind
+        complete(op.op, KvStatus::Ok as u32, b"third");
+        assert!(!in_flight(op.op));
+    }
+
+    #[test]
+    fn unknown_and_dropped_ops_ignore_completions() {
+        let op = KvOp::start(|_| {});
+        let id = op.op;
+        drop(op);
+        assert!(!in_flight(id));
+
+        kv_complete(id, KvStatus::Ok as u32, b"late");
+        kv_complete(u32::MAX, KvStatus::Ok as u32, b"unknown");
+        assert!(!in_flight(id) && !in_flight(u32::MAX));
+    }
+
+    #[test]
+    fn tasks_finish_once_the_host_completes() {
+        let started = Rc::new(Cell::new(0));
+        let op = started.clone();
+        let task = spawn(async move { Ok(KvOp::start(|id| op.set(id)).await?.unwrap_or_default()) });
+        assert_eq!(task_state(task), 1);
+        assert_eq!(take_task(task).unwrap_err().code, ErrorCode::InvalidArgument);
+        assert_eq!(task_state(task), 1);
+
+        kv_complete(started.get(), KvStatus::Ok as u32, b"done");
+        assert_eq!(task_state(task), 2);
+        assert_eq!(take_task(task), Ok(b"done".to_vec()));
+        assert_eq!(task_state(task), 0);
+        assert_eq!(take_task(task).unwrap_err().code, ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn cancelling_a_task_drops_its_ops() {
+        let started = Rc::new(Cell::new(0));
+        let op = started.clone();
+        let task = spawn(async move { Ok(KvOp::start(|id| op.set(id)).await?.unwrap_or_default()) });
+        assert!(in_flight(started.get()));
+
+        cancel_task(task);
+        assert_eq!(task_state(task), 0);
+        assert!(!in_flight(started.get()));
+        kv_complete(started.get(), KvStatus::Ok as u32, b"late");
+        assert_eq!(task_state(task), 0);
+    }
+
+    #[test]
+    #[cfg(not(all(target_arch = "wasm32", not(target_os = "wasi"))))]
+    fn builds_without_a_host_fail_every_op() {
+        let task = spawn(async { index("doc", "hello world").await.map(|words| words.to_le_bytes().to_vec()) });
+        assert_eq!(task_state(task), 2);
+        assert_eq!(take_task(task), Err(host_error("no KV host in this build")));
+
+        for result in [
+            spawn(async { search("hello").await.map(|_| Vec::new()) }),
+            spawn(async { indexed("he", 10).await.map(|_| Vec::new()) }),
+        ]
+        .map(take_task)
+        {
+            assert_eq!(result, Err(host_error("no KV host in this build")));
+        }
+        assert!(OPS.with_borrow(HashMap::is_empty));
+    }
+}
@@ -1,92 +1,107 @@
-/**
+/*!
  * Rust WASM Module - String and Data Processing
  *
  * Demonstrates WASM with Rust for string manipulation and data processing.
+ *
+ * Export groups are gated by cargo features so deployments can ship a
+ * subset (all enabled by default):
+ *
+ * - `text` - `count_vowels`, `reverse_string`, `is_palindrome`, `word_count`, ...
+ * - `hash` - `hash_string`
+ * - `cipher` - `caesar_encrypt`
+ * - `math` - `add`, `multiply`, `memory_intensive`
+ * - `batch` - array variants of the enabled `text` / `hash` exports
+ * - `stream` - `TextStats` (with `text`) and `StreamingHasher` (with `hash`)
+ * - `rpc` - JSON-RPC dispatcher and the `echelon_wasi` command
+ * - `heap-stats` - instrumented global allocator and `heap_stats`
+ * - `arena` - request-scoped bump allocation (`begin_scope` / `end_scope`)
+ * - `parallel` - batch jobs split across threads sharing memory
+ * - `http` - `handle_request` HTTP ABI and router for edge endpoints
+ * - `snapshot` - `snapshot` / `restore` of module state across instances
+ * - `selftest` - `run_self_test` known-answer vectors and `run_benchmarks`
+ * - `codec` - `call_encoded` with CBOR / MessagePack argument maps (serde)
+ * - `shared-buffers` - reusable input/output regions and `*_shared` exports
+ * - `host-kv` - async index exports awaiting the host's `env.kv_*` imports
+ *   (off by default)
+ * - `component` - canonical ABI exports for the `wit/echelon.wit` world
+ *   (off by default; wasm targets only). There it builds just the component
+ *   core (`component_core` cfg): no C ABI, no wasm-bindgen exports, and no
+ *   version, manifest, fuel, entropy or logging modules.
+ *
+ * `version` exposes `abi_version()` and `supports(name)` so hosts can refuse
+ * builds whose exports behave differently from what they persisted.
+ *
+ * Randomness and time go through `entropy`, which the host controls and can
+ * switch to a seeded deterministic mode.
+ *
+ * The text and hash scans use `simd128` kernels when built with that target
+ * feature (see `kernels`).
  */
 
-use wasm_bindgen::prelude::*;
-
-/// Count vowels in a string
-#[wasm_bindgen]
-pub fn count_vowels(s: &str) -> usize {
-    s.chars()
-        .filter(|c| matches!(c.to_ascii_lowercase(), 'a' | 'e' | 'i' | 'o' | 'u'))
-        .count()
-}
-
-/// Reverse a string
-#[wasm_bindgen]
-pub fn reverse_string(s: &str) -> String {
-    s.chars().rev().collect()
-}
-
-/// Check if string is palindrome
-#[wasm_bindgen]
-pub fn is_palindrome(s: &str) -> bool {
-    let cleaned: String = s.chars()
-        .filter(|c| c.is_alphanumeric())
-        .map(|c| c.to_ascii_lowercase())
-        .collect();
-
-    cleaned == cleaned.chars().rev().collect::<String>()
-}
-
-/// Calculate hash of string (simple DJB2 hash)
-#[wasm_bindgen]
-pub fn hash_string(s: &str) -> u32 {
-    let mut hash: u32 = 5381;
-    for c in s.bytes() {
-        hash = ((hash << 5).wrapping_add(hash)).wrapping_add(c as u32);
-    }
-    hash
-}
+#[cfg(not(component_core))]
+pub mod abi;
+#[cfg(feature = "arena")]
+pub mod arena;
+#[cfg(feature = "batch")]
+pub mod batch;
+#[cfg(feature = "cipher")]
+pub mod cipher;
+#[cfg(feature = "codec")]
+pub mod codec;
+#[cfg(all(feature = "component", any(target_family = "wasm", test)))]
+pub mod component;
+#[cfg(not(component_core))]
+pub mod entropy;
+pub mod error;
+#[cfg(not(component_core))]
+pub mod fuel;
+#[cfg(feature = "hash")]
+pub mod hash;
+#[cfg(feature = "heap-stats")]
+pub mod heap;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(any(feature = "text", feature = "hash"))]
+pub mod kernels;
+#[cfg(feature = "host-kv")]
+pub mod kv;
+#[cfg(not(component_core))]
+pub mod logging;
+#[cfg(not(component_core))]
+pub mod manifest;
+#[cfg(feature = "math")]
+pub mod math;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+#[cfg(feature = "selftest")]
+pub mod selftest;
+#[cfg(feature = "shared-buffers")]
+pub mod shared;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+#[cfg(feature = "stream")]
+pub mod stream;
+#[cfg(feature = "text")]
+pub mod text;
+#[cfg(not(component_core))]
+pub mod version;
 
-/// Find longest word in a string
-#[wasm_bindgen]
-pub fn longest_word_length(s: &str) -> usize {
-    s.split_whitespace()
-        .map(|word| word.len())
-        .max()
-        .unwrap_or(0)
-}
-
-/// Count word occurrences
-#[wasm_bindgen]
-pub fn word_count(s: &str) -> usize {
-    s.split_whitespace().count()
-}
+#[cfg(feature = "cipher")]
+pub use cipher::*;
+#[cfg(feature = "hash")]
+pub use hash::*;
+#[cfg(feature = "math")]
+pub use math::*;
+#[cfg(feature = "text")]
+pub use text::*;
 
-/// Simple encryption (Caesar cipher)
-#[wasm_bindgen]
-pub fn caesar_encrypt(s: &str, shift: u8) -> String {
-    s.chars()
-        .map(|c| {
-            if c.is_ascii_lowercase() {
-                ((((c as u8 - b'a') + shift) % 26) + b'a') as char
-            } else if c.is_ascii_uppercase() {
-                ((((c as u8 - b'A') + shift) % 26) + b'A') as char
-            } else {
-                c
-            }
-        })
-        .collect()
-}
-
-/// Add two numbers (for testing)
-#[wasm_bindgen]
-pub fn add(a: i32, b: i32) -> i32 {
-    a + b
-}
-
-/// Multiply two numbers
-#[wasm_bindgen]
-pub fn multiply(a: i32, b: i32) -> i32 {
-    a * b
-}
+/// Run `f` with allocations kept out of any open scope (see `arena`)
+#[cfg(feature = "arena")]
+pub use arena::persistent;
 
-/// Memory allocation test - creates a vector and sums it
-#[wasm_bindgen]
-pub fn memory_intensive(size: usize) -> i32 {
-    let vec: Vec<i32> = (0..size as i32).collect();
-    vec.iter().sum()
+#[cfg(not(feature = "arena"))]
+pub fn persistent<T>(f: impl FnOnce() -> T) -> T {
+    f()
 }
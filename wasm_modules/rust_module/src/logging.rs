@@ -0,0 +1,176 @@
+/*!
+ * Logging bridge to the host console imports
+ *
+ * Implements a `log` facade backend that serializes each record as a JSON
+ * line and forwards it to the `env.console_*` functions registered by
+ * `registerStandardHostFunctions` in `wasm_host_functions.ts`:
+ *
+ * ```json
+ * {"level":"WARN","target":"echelon_wasm::error","message":"...","line":42,"fields":{"code":2}}
+ * ```
+ *
+ * The imports are only linked into `wasm32-unknown-unknown` builds with the
+ * `host-log` feature, which is off by default so the default module has no
+ * console imports. Without them `init_logging` leaves the max level at
+ * `Off`, so every `log!` call is a no-op.
+ */
+
+use log::kv::{self, Key, Value, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use wasm_bindgen::prelude::*;
+
+/// Whether this build links the host console imports
+pub const HOST_SINK: bool = cfg!(all(target_arch = "wasm32", not(target_os = "wasi"), feature = "host-log"));
+
+#[cfg(all(target_arch = "wasm32", not(target_os = "wasi"), feature = "host-log"))]
+mod host {
+    #[link(wasm_import_module = "env")]
+    extern "C" {
+        pub fn console_log(ptr: *const u8, len: usize);
+        pub fn console_error(ptr: *const u8, len: usize);
+        pub fn console_warn(ptr: *const u8, len: usize);
+        pub fn console_info(ptr: *const u8, len: usize);
+        pub fn console_debug(ptr: *const u8, len: usize);
+    }
+}
+
+/// `log` backend writing to the host console imports
+struct HostLogger;
+
+static LOGGER: HostLogger = HostLogger;
+
+impl Log for HostLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        HOST_SINK && metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            emit(record.level(), &format_record(record));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(all(target_arch = "wasm32", not(target_os = "wasi"), feature = "host-log"))]
+fn emit(level: Level, line: &str) {
+    let (ptr, len) = (line.as_ptr(), line.len());
+    unsafe {
+        match level {
+            Level::Error => host::console_error(ptr, len),
+            Level::Warn => host::console_warn(ptr, len),
+            Level::Info => host::console_info(ptr, len),
+            Level::Debug => host::console_debug(ptr, len),
+            Level::Trace => host::console_log(ptr, len),
+        }
+    }
+}
+
+#[cfg(not(all(target_arch = "wasm32", not(target_os = "wasi"), feature = "host-log")))]
+fn emit(_level: Level, _line: &str) {}
+
+/// Map a numeric level (`0` = off ... `5` = trace) to a filter
+pub fn level_filter(level: u32) -> LevelFilter {
+    match level {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// Current numeric level (`0` = off ... `5` = trace)
+pub fn level() -> u32 {
+    log::max_level() as u32
+}
+
+/// Install the host logger (`0` = off ... `5` = trace)
+///
+/// Returns `false` when the host console imports are not linked into this
+/// build, in which case logging stays disabled.
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn init_logging(level: u32) -> bool {
+    if !HOST_SINK {
+        log::set_max_level(LevelFilter::Off);
+        return false;
+    }
+
+    // Already installed on a previous call; only the level changes
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level_filter(level));
+    true
+}
+
+/// Serialize a record as a single JSON line
+pub fn format_record(record: &Record) -> String {
+    let mut out = String::with_capacity(128);
+    out.push_str("{\"level\":\"");
+    out.push_str(record.level().as_str());
+    out.push_str("\",\"target\":");
+    push_json_str(&mut out, record.target());
+    out.push_str(",\"message\":");
+    push_json_str(&mut out, &record.args().to_string());
+    if let Some(line) = record.line() {
+        out.push_str(",\"line\":");
+        out.push_str(&line.to_string());
+    }
+
+    let mut fields = FieldWriter { out: String::new() };
+    if record.key_values().visit(&mut fields).is_ok() && !fields.out.is_empty() {
+        out.push_str(",\"fields\":{");
+        out.push_str(&fields.out);
+        out.push('}');
+    }
+
+    out.push('}');
+    out
+}
+
+/// Collects structured key-values as JSON object members
+struct FieldWriter {
+    out: String,
+}
+
+impl<'kvs> VisitSource<'kvs> for FieldWriter {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        if !self.out.is_empty() {
+            self.out.push(',');
+        }
+        push_json_str(&mut self.out, key.as_str());
+        self.out.push(':');
+
+        if let Some(b) = value.to_bool() {
+            self.out.push_str(if b { "true" } else { "false" });
+        } else if let Some(n) = value.to_i64() {
+            self.out.push_str(&n.to_string());
+        } else if let Some(n) = value.to_u64() {
+            self.out.push_str(&n.to_string());
+        } else if let Some(n) = value.to_f64().filter(|n| n.is_finite()) {
+            self.out.push_str(&n.to_string());
+        } else {
+            push_json_str(&mut self.out, &value.to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Append `s` as a quoted, escaped JSON string
+pub(crate) fn push_json_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
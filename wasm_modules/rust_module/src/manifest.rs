@@ -0,0 +1,29 @@
+/*!
+ * Export manifest
+ *
+ * `build.rs` generates a JSON description of every export (signature, ABI,
+ * purity, doc line) plus the crate version and enabled features. It is
+ * embedded as the `echelon.manifest` custom section so hosts can inspect a
+ * module with `WebAssembly.Module.customSections` before instantiating it,
+ * and is also returned by `manifest()` / `echelon_manifest()`.
+ */
+
+use wasm_bindgen::prelude::*;
+
+/// Manifest JSON for this build
+pub const MANIFEST_JSON: &str = include_str!(concat!(env!("OUT_DIR"), "/echelon_manifest.json"));
+
+/// Custom section name
+pub const MANIFEST_SECTION: &str = "echelon.manifest";
+
+#[cfg(target_arch = "wasm32")]
+#[link_section = "echelon.manifest"]
+#[used]
+static MANIFEST: [u8; MANIFEST_JSON.len()] =
+    *include_bytes!(concat!(env!("OUT_DIR"), "/echelon_manifest.json"));
+
+/// Manifest JSON describing every export of this build
+#[wasm_bindgen]
+pub fn manifest() -> String {
+    MANIFEST_JSON.to_string()
+}
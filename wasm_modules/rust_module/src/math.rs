@@ -0,0 +1,80 @@
+/*!
+ * Math and test probe functions (`math` feature)
+ */
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{guard, EchelonError, ErrorCode};
+use crate::fuel;
+
+/// Largest vector `memory_intensive` will allocate (elements)
+pub const MEMORY_INTENSIVE_MAX: usize = 16 * 1024 * 1024;
+
+/// Add two numbers (for testing, reports `Overflow`)
+#[wasm_bindgen]
+pub fn add(a: i32, b: i32) -> i32 {
+    guard(|| a.checked_add(b).ok_or_else(|| EchelonError::overflow("add")))
+}
+
+/// Multiply two numbers (reports `Overflow`)
+#[wasm_bindgen]
+pub fn multiply(a: i32, b: i32) -> i32 {
+    guard(|| a.checked_mul(b).ok_or_else(|| EchelonError::overflow("multiply")))
+}
+
+/// Progress of a `memory_intensive` call parked by the fuel budget
+struct MemoryIntensive {
+    size: usize,
+    vec: Vec<i32>,
+    summed: usize,
+    sum: i32,
+}
+
+/// Memory allocation test - creates a vector and sums it (wrapping sum)
+///
+/// Draws one fuel unit per element filled and per element summed, and
+/// resumes a call suspended with `BudgetExhausted` for the same `size`.
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn memory_intensive(size: usize) -> i32 {
+    guard(|| {
+        if size > MEMORY_INTENSIVE_MAX {
+            return Err(EchelonError::new(
+                ErrorCode::InvalidArgument,
+                format!("memory_intensive: size {size} exceeds {MEMORY_INTENSIVE_MAX}"),
+            ));
+        }
+
+        let mut job = match fuel::resume::<MemoryIntensive>("memory_intensive") {
+            Some(job) if job.size == size => job,
+            _ => {
+                let mut vec: Vec<i32> = Vec::new();
+                vec.try_reserve_exact(size).map_err(|_| {
+                    EchelonError::new(ErrorCode::OutOfMemory, format!("memory_intensive: cannot allocate {size} elements"))
+                })?;
+                MemoryIntensive { size, vec, summed: 0, sum: 0 }
+            }
+        };
+
+        while job.vec.len() < size {
+            let n = fuel::take(size - job.vec.len());
+            if n == 0 {
+                return Err(fuel::suspend("memory_intensive", job));
+            }
+            let start = job.vec.len();
+            job.vec.extend(start as i32..(start + n) as i32);
+        }
+
+        while job.summed < size {
+            let n = fuel::take(size - job.summed);
+            if n == 0 {
+                return Err(fuel::suspend("memory_intensive", job));
+            }
+            let chunk = &job.vec[job.summed..job.summed + n];
+            job.sum = chunk.iter().fold(job.sum, |acc, &x| acc.wrapping_add(x));
+            job.summed += n;
+        }
+
+        Ok(job.sum)
+    })
+}
@@ -0,0 +1,225 @@
+/*!
+ * Parallel batch jobs
+ *
+ * Splits a length-prefixed batch (see `crate::batch`) across threads that
+ * share the module's memory. The host creates a job on one thread with
+ * `echelon_par_begin` and then calls `echelon_par_work(job)` from every
+ * worker and from itself. Each call claims small runs of items through an
+ * atomic cursor until none are left. Once `echelon_par_pending(job)` is `0`,
+ * `echelon_par_finish(job)` returns the packed results and frees the job.
+ *
+ * Real parallelism needs the threads build (`THREADS=1 ./build.sh`: atomics,
+ * bulk-memory and shared memory). In the default build the same exports run
+ * on one thread, so hosts can use one code path for both.
+ */
+
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{EchelonError, ErrorCode, Result};
+
+/// Items claimed per cursor step
+const CLAIM: usize = 16;
+
+/// Batch operations a parallel job can run
+#[wasm_bindgen]
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchOp {
+    HashStrings = 0,
+    VowelCounts = 1,
+    WordCounts = 2,
+    LongestWordLengths = 3,
+    PalindromeFlags = 4,
+}
+
+impl BatchOp {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        Some(match value {
+            0 => Self::HashStrings,
+            1 => Self::VowelCounts,
+            2 => Self::WordCounts,
+            3 => Self::LongestWordLengths,
+            4 => Self::PalindromeFlags,
+            _ => return None,
+        })
+    }
+
+    /// Per-item function, `None` if its export group is not built
+    fn function(self) -> Option<fn(&str) -> u32> {
+        match self {
+            #[cfg(feature = "hash")]
+            Self::HashStrings => Some(crate::hash_string),
+            #[cfg(feature = "text")]
+            Self::VowelCounts => Some(|s| crate::count_vowels(s) as u32),
+            #[cfg(feature = "text")]
+            Self::WordCounts => Some(|s| crate::word_count(s) as u32),
+            #[cfg(feature = "text")]
+            Self::LongestWordLengths => Some(|s| crate::longest_word_length(s) as u32),
+            #[cfg(feature = "text")]
+            Self::PalindromeFlags => Some(|s| crate::is_palindrome(s) as u32),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
+/// A batch shared by the threads working on it
+pub struct ParallelJob {
+    op: BatchOp,
+    function: fn(&str) -> u32,
+    input: Vec<u8>,
+    /// Byte range of every item in `input`
+    items: Vec<(usize, usize)>,
+    results: Vec<AtomicU32>,
+    next: AtomicUsize,
+    done: AtomicUsize,
+}
+
+impl ParallelJob {
+    /// Copy and validate a length-prefixed batch
+    pub fn new(op: BatchOp, buf: &[u8]) -> Result<Self> {
+        let function = op.function().ok_or_else(|| {
+            EchelonError::new(ErrorCode::InvalidArgument, format!("{op:?} is not available in this build"))
+        })?;
+
+        let input = buf.to_vec();
+        let items: Vec<(usize, usize)> = crate::batch::decode_strings(&input)?
+            .iter()
+            .map(|s| {
+                let start = s.as_ptr() as usize - input.as_ptr() as usize;
+                (start, start + s.len())
+            })
+            .collect();
+        let results = items.iter().map(|_| AtomicU32::new(0)).collect();
+
+        Ok(Self {
+            op,
+            function,
+            input,
+            items,
+            results,
+            next: AtomicUsize::new(0),
+            done: AtomicUsize::new(0),
+        })
+    }
+
+    /// Process claimed items until none are left, returns the items processed
+    pub fn work(&self) -> usize {
+        let mut processed = 0;
+        loop {
+            let start = self.next.fetch_add(CLAIM, Ordering::Relaxed);
+            if start >= self.items.len() {
+                return processed;
+            }
+            let end = (start + CLAIM).min(self.items.len());
+
+            for index in start..end {
+                let (from, to) = self.items[index];
+                // Validated in `new`
+                let item = std::str::from_utf8(&self.input[from..to]).unwrap_or_default();
+                self.results[index].store((self.function)(item), Ordering::Relaxed);
+            }
+            processed += end - start;
+            self.done.fetch_add(end - start, Ordering::Release);
+        }
+    }
+
+    /// Items not finished yet
+    pub fn pending(&self) -> usize {
+        self.items.len() - self.done.load(Ordering::Acquire)
+    }
+
+    /// Results in batch order (`u32` LE, or one byte per item for flags)
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        if self.pending() > 0 {
+            return Err(EchelonError::new(
+                ErrorCode::InvalidArgument,
+                format!("parallel job has {} pending items", self.pending()),
+            ));
+        }
+
+        let values: Vec<u32> = self.results.iter().map(|r| r.load(Ordering::Acquire)).collect();
+        Ok(match self.op {
+            BatchOp::PalindromeFlags => values.iter().map(|&v| v as u8).collect(),
+            _ => crate::batch::encode_u32s(&values),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "text", not(target_arch = "wasm32")))]
+mod tests {
+    use std::sync::atomic::AtomicU32;
+
+    use super::*;
+    use crate::batch::encode_strings;
+
+    const THREADS: usize = 8;
+
+    /// Run `job` on `THREADS` threads, returns the items each one processed
+    fn run(job: &ParallelJob) -> Vec<usize> {
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..THREADS).map(|_| scope.spawn(|| job.work())).collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+        })
+    }
+
+    #[test]
+    fn cursor_hands_out_every_index_once() {
+        const ITEMS: usize = 5_000;
+        static HITS: [AtomicU32; ITEMS] = [const { AtomicU32::new(0) }; ITEMS];
+
+        /// Items are their own index; count how often each is processed
+        fn count_hit(item: &str) -> u32 {
+            let index: usize = item.parse().unwrap();
+            HITS[index].fetch_add(1, Ordering::Relaxed);
+            index as u32
+        }
+
+        let items: Vec<String> = (0..ITEMS).map(|i| i.to_string()).collect();
+        let mut job = ParallelJob::new(BatchOp::WordCounts, &encode_strings(&items)).unwrap();
+        job.function = count_hit;
+
+        let processed = run(&job);
+        assert_eq!(processed.iter().sum::<usize>(), ITEMS);
+        assert_eq!(job.pending(), 0);
+        let hits: Vec<u32> = HITS.iter().map(|hits| hits.load(Ordering::Relaxed)).collect();
+        assert!(hits.iter().all(|&hits| hits == 1), "{hits:?}");
+        // Late workers find nothing left
+        assert_eq!(job.work(), 0);
+
+        let expected: Vec<u32> = (0..ITEMS as u32).collect();
+        assert_eq!(job.encode().unwrap(), crate::batch::encode_u32s(&expected));
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn results_match_sequential_batches() {
+        use crate::batch::{hash_strings, longest_word_lengths, palindrome_flags, vowel_counts, word_counts};
+
+        let words = ["racecar", "héllo wörld", "", "a b  c", "Was it a car or a cat I saw", "日本語 テキスト"];
+        let items: Vec<String> = (0..1_000).map(|i| format!("{} {i}", words[i % words.len()])).collect();
+        let buf = encode_strings(&items);
+
+        let sequential = [
+            (BatchOp::HashStrings, crate::batch::encode_u32s(&hash_strings(items.clone()))),
+            (BatchOp::VowelCounts, crate::batch::encode_u32s(&vowel_counts(items.clone()))),
+            (BatchOp::WordCounts, crate::batch::encode_u32s(&word_counts(items.clone()))),
+            (BatchOp::LongestWordLengths, crate::batch::encode_u32s(&longest_word_lengths(items.clone()))),
+            (BatchOp::PalindromeFlags, palindrome_flags(items.clone())),
+        ];
+        for (op, expected) in sequential {
+            let job = ParallelJob::new(op, &buf).unwrap();
+            run(&job);
+            assert_eq!(job.encode().unwrap(), expected, "{op:?}");
+        }
+    }
+
+    #[test]
+    fn unfinished_jobs_do_not_encode() {
+        let job = ParallelJob::new(BatchOp::WordCounts, &encode_strings(&["a b"; 40])).unwrap();
+        assert_eq!(job.pending(), 40);
+        assert_eq!(job.encode().unwrap_err().code, ErrorCode::InvalidArgument);
+    }
+}
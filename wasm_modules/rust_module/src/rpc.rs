@@ -0,0 +1,327 @@
+/*!
+ * JSON-RPC 2.0 dispatcher
+ *
+ * Maps newline-delimited JSON-RPC requests onto the module's exports so the
+ * WASI command build (`src/bin/echelon_wasi.rs`) can run under the
+ * `wasm_wasi.ts` host with no JS glue:
+ *
+ * ```text
+ * --> {"jsonrpc":"2.0","id":1,"method":"caesar_encrypt","params":["abc",3]}
+ * <-- {"jsonrpc":"2.0","id":1,"result":"def"}
+ * --> {"jsonrpc":"2.0","id":2,"method":"word_count","params":{"s":"a b c"}}
+ * <-- {"jsonrpc":"2.0","id":2,"result":3}
+ * ```
+ *
+ * Params may be positional or named after the Rust parameters. Failures
+ * recorded through the `last_error` slot are returned as error `-32000`
+ * with the `ErrorCode` in `data`.
+ *
+ * Only the scalar exports are dispatched: text, hash, cipher, math, the
+ * string batch helpers and the version queries. The byte-oriented and
+ * stateful groups (http, codec, stream, snapshot, entropy, selftest) take
+ * buffers or handles that do not map onto JSON params and are out of scope.
+ */
+
+use serde_json::{json, Map, Value};
+
+#[cfg(feature = "batch")]
+use crate::batch::map_batch;
+use crate::error::{self, EchelonError};
+
+/// JSON-RPC error codes
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const EXECUTION_ERROR: i64 = -32000;
+
+/// JSON-RPC error object
+#[derive(Clone, Debug, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut obj = Map::new();
+        obj.insert("code".into(), json!(self.code));
+        obj.insert("message".into(), json!(self.message));
+        if let Some(data) = &self.data {
+            obj.insert("data".into(), data.clone());
+        }
+        Value::Object(obj)
+    }
+}
+
+impl From<EchelonError> for RpcError {
+    fn from(err: EchelonError) -> Self {
+        Self {
+            code: EXECUTION_ERROR,
+            message: err.message,
+            data: Some(json!({ "code": err.code as u32, "kind": format!("{:?}", err.code) })),
+        }
+    }
+}
+
+/// Positional or named call parameters
+pub struct Params<'a> {
+    value: Option<&'a Value>,
+}
+
+impl<'a> Params<'a> {
+    pub fn new(value: Option<&'a Value>) -> Self {
+        Self { value }
+    }
+
+    /// Look up a parameter by position or by name
+    pub fn get(&self, index: usize, name: &str) -> Result<&'a Value, RpcError> {
+        let found = match self.value {
+            Some(Value::Array(items)) => items.get(index),
+            Some(Value::Object(fields)) => fields.get(name),
+            _ => None,
+        };
+        found.ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("missing parameter `{name}`")))
+    }
+
+    pub fn str(&self, index: usize, name: &str) -> Result<&'a str, RpcError> {
+        self.get(index, name)?
+            .as_str()
+            .ok_or_else(|| invalid_type(name, "a string"))
+    }
+
+    pub fn strings(&self, index: usize, name: &str) -> Result<Vec<&'a str>, RpcError> {
+        self.get(index, name)?
+            .as_array()
+            .and_then(|items| items.iter().map(Value::as_str).collect())
+            .ok_or_else(|| invalid_type(name, "an array of strings"))
+    }
+
+    pub fn i64(&self, index: usize, name: &str) -> Result<i64, RpcError> {
+        self.get(index, name)?
+            .as_i64()
+            .ok_or_else(|| invalid_type(name, "an integer"))
+    }
+
+    pub fn i32(&self, index: usize, name: &str) -> Result<i32, RpcError> {
+        i32::try_from(self.i64(index, name)?).map_err(|_| invalid_type(name, "a 32-bit integer"))
+    }
+
+    pub fn u8(&self, index: usize, name: &str) -> Result<u8, RpcError> {
+        u8::try_from(self.i64(index, name)?).map_err(|_| invalid_type(name, "an integer in 0..=255"))
+    }
+
+    pub fn usize(&self, index: usize, name: &str) -> Result<usize, RpcError> {
+        usize::try_from(self.i64(index, name)?).map_err(|_| invalid_type(name, "a non-negative integer"))
+    }
+}
+
+fn invalid_type(name: &str, expected: &str) -> RpcError {
+    RpcError::new(INVALID_PARAMS, format!("parameter `{name}` must be {expected}"))
+}
+
+/// Call an export by name (only the enabled export groups are reachable)
+pub fn dispatch(method: &str, params: &Params) -> Result<Value, RpcError> {
+    error::clear_last_error();
+
+    let result = match method {
+        "abi_version" => json!(crate::version::abi_version()),
+        "supports" => json!(crate::version::supports(params.str(0, "feature_name")?)),
+        #[cfg(feature = "text")]
+        "count_vowels" => json!(crate::count_vowels(params.str(0, "s")?)),
+        #[cfg(feature = "text")]
+        "reverse_string" => json!(crate::reverse_string(params.str(0, "s")?)),
+        #[cfg(feature = "text")]
+        "is_palindrome" => json!(crate::is_palindrome(params.str(0, "s")?)),
+        #[cfg(feature = "hash")]
+        "hash_string" => json!(crate::hash_string(params.str(0, "s")?)),
+        #[cfg(feature = "text")]
+        "longest_word_length" => json!(crate::longest_word_length(params.str(0, "s")?)),
+        #[cfg(feature = "text")]
+        "word_count" => json!(crate::word_count(params.str(0, "s")?)),
+        #[cfg(feature = "cipher")]
+        "caesar_encrypt" => json!(crate::caesar_encrypt(params.str(0, "s")?, params.u8(1, "shift")?)),
+        #[cfg(feature = "math")]
+        "add" => json!(crate::add(params.i32(0, "a")?, params.i32(1, "b")?)),
+        #[cfg(feature = "math")]
+        "multiply" => json!(crate::multiply(params.i32(0, "a")?, params.i32(1, "b")?)),
+        #[cfg(feature = "math")]
+        "memory_intensive" => json!(crate::memory_intensive(params.usize(0, "size")?)),
+        #[cfg(all(feature = "batch", feature = "hash"))]
+        "hash_strings" => json!(map_batch(&params.strings(0, "items")?, crate::hash_string)),
+        #[cfg(all(feature = "batch", feature = "text"))]
+        "vowel_counts" => json!(map_batch(&params.strings(0, "items")?, crate::count_vowels)),
+        #[cfg(all(feature = "batch", feature = "text"))]
+        "word_counts" => json!(map_batch(&params.strings(0, "items")?, crate::word_count)),
+        #[cfg(all(feature = "batch", feature = "text"))]
+        "longest_word_lengths" => json!(map_batch(&params.strings(0, "items")?, crate::longest_word_length)),
+        #[cfg(all(feature = "batch", feature = "text"))]
+        "palindrome_flags" => json!(map_batch(&params.strings(0, "items")?, crate::is_palindrome)),
+        _ => return Err(RpcError::new(METHOD_NOT_FOUND, format!("method not found: {method}"))),
+    };
+
+    match error::last_error() {
+        Some(err) => Err(err.into()),
+        None => Ok(result),
+    }
+}
+
+/// Handle one request object, `None` for notifications
+///
+/// Malformed requests always get a reply, with `"id": null` when they carry
+/// no usable id; only well-formed requests without an `id` go unanswered.
+fn handle_request(request: &Value) -> Option<Value> {
+    let Some(obj) = request.as_object() else {
+        return Some(response(Value::Null, Err(RpcError::new(INVALID_REQUEST, "request must be an object"))));
+    };
+
+    let id = obj.get("id");
+    let valid_id = id.is_none_or(|id| id.is_null() || id.is_string() || id.is_number());
+    match obj.get("method").and_then(Value::as_str) {
+        Some(method) if obj.get("jsonrpc") == Some(&json!("2.0")) && valid_id => {
+            let outcome = dispatch(method, &Params::new(obj.get("params")));
+            id.map(|id| response(id.clone(), outcome))
+        }
+        _ => {
+            let err = RpcError::new(INVALID_REQUEST, "expected jsonrpc \"2.0\", a method and a valid id");
+            let id = id.filter(|_| valid_id).cloned().unwrap_or(Value::Null);
+            Some(response(id, Err(err)))
+        }
+    }
+}
+
+fn response(id: Value, outcome: Result<Value, RpcError>) -> Value {
+    match outcome {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => json!({ "jsonrpc": "2.0", "id": id, "error": err.to_json() }),
+    }
+}
+
+/// Handle one input line (single request or batch), `None` if nothing to send
+pub fn handle_line(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    let reply = match serde_json::from_str::<Value>(line) {
+        Ok(Value::Array(batch)) if !batch.is_empty() => {
+            let replies: Vec<Value> = batch.iter().filter_map(handle_request).collect();
+            if replies.is_empty() {
+                return None;
+            }
+            Value::Array(replies)
+        }
+        Ok(request) => handle_request(&request)?,
+        Err(err) => response(Value::Null, Err(RpcError::new(PARSE_ERROR, err.to_string()))),
+    };
+
+    Some(reply.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(line: &str) -> Value {
+        serde_json::from_str(&handle_line(line).expect("a reply")).unwrap()
+    }
+
+    fn error_code(reply: &Value) -> i64 {
+        reply["error"]["code"].as_i64().unwrap()
+    }
+
+    #[test]
+    fn version_queries() {
+        let out = reply(r#"{"jsonrpc":"2.0","id":1,"method":"abi_version"}"#);
+        assert_eq!(out, json!({ "jsonrpc": "2.0", "id": 1, "result": crate::version::ABI_VERSION }));
+        let out = reply(r#"{"jsonrpc":"2.0","id":"s","method":"supports","params":["no.such.feature"]}"#);
+        assert_eq!(out["result"], json!(false));
+        assert_eq!(out["id"], json!("s"));
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn positional_and_named_params() {
+        let positional = reply(r#"{"jsonrpc":"2.0","id":1,"method":"word_count","params":["a b c"]}"#);
+        let named = reply(r#"{"jsonrpc":"2.0","id":1,"method":"word_count","params":{"s":"a b c"}}"#);
+        assert_eq!(positional["result"], json!(3));
+        assert_eq!(named, positional);
+    }
+
+    #[cfg(feature = "math")]
+    #[test]
+    fn param_errors_and_module_errors() {
+        let missing = reply(r#"{"jsonrpc":"2.0","id":1,"method":"add","params":{"a":1}}"#);
+        assert_eq!(error_code(&missing), INVALID_PARAMS);
+        let wrong_type = reply(r#"{"jsonrpc":"2.0","id":1,"method":"add","params":["1",2]}"#);
+        assert_eq!(error_code(&wrong_type), INVALID_PARAMS);
+        let too_wide = reply(r#"{"jsonrpc":"2.0","id":1,"method":"add","params":[4294967296,2]}"#);
+        assert_eq!(error_code(&too_wide), INVALID_PARAMS);
+
+        let overflow = reply(r#"{"jsonrpc":"2.0","id":1,"method":"add","params":[2147483647,1]}"#);
+        assert_eq!(error_code(&overflow), EXECUTION_ERROR);
+        assert_eq!(overflow["error"]["data"], json!({ "code": 2, "kind": "Overflow" }));
+        // The failure does not leak into the next call
+        let ok = reply(r#"{"jsonrpc":"2.0","id":2,"method":"add","params":[2,3]}"#);
+        assert_eq!(ok["result"], json!(5));
+    }
+
+    #[test]
+    fn unknown_method() {
+        let out = reply(r#"{"jsonrpc":"2.0","id":7,"method":"nope"}"#);
+        assert_eq!((error_code(&out), &out["id"]), (METHOD_NOT_FOUND, &json!(7)));
+    }
+
+    #[test]
+    fn parse_errors_reply_with_null_id() {
+        let out = reply(r#"{"jsonrpc":"2.0","id":1,"method""#);
+        assert_eq!((error_code(&out), &out["id"]), (PARSE_ERROR, &Value::Null));
+    }
+
+    #[test]
+    fn malformed_requests_without_id_still_get_a_reply() {
+        for line in [r#"{"foo":1}"#, r#"{"jsonrpc":"2.0"}"#, r#"{"method":"abi_version"}"#, "1", "[]"] {
+            let out = reply(line);
+            assert_eq!((error_code(&out), &out["id"]), (INVALID_REQUEST, &Value::Null), "{line}");
+        }
+        // A malformed request with an id echoes it
+        let out = reply(r#"{"jsonrpc":"1.0","id":4,"method":"abi_version"}"#);
+        assert_eq!((error_code(&out), &out["id"]), (INVALID_REQUEST, &json!(4)));
+        let out = reply(r#"{"jsonrpc":"2.0","id":[1],"method":"abi_version"}"#);
+        assert_eq!((error_code(&out), &out["id"]), (INVALID_REQUEST, &Value::Null));
+    }
+
+    #[test]
+    fn notifications_get_no_reply() {
+        assert_eq!(handle_line(r#"{"jsonrpc":"2.0","method":"abi_version"}"#), None);
+        // Even when the call itself fails
+        assert_eq!(handle_line(r#"{"jsonrpc":"2.0","method":"nope"}"#), None);
+        assert_eq!(handle_line(r#"[{"jsonrpc":"2.0","method":"abi_version"}]"#), None);
+        assert_eq!(handle_line("   "), None);
+    }
+
+    #[test]
+    fn batches_reply_in_order_and_skip_notifications() {
+        let out = reply(concat!(
+            r#"[{"jsonrpc":"2.0","id":1,"method":"abi_version"},"#,
+            r#"{"jsonrpc":"2.0","method":"abi_version"},"#,
+            r#"{"foo":1},"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"nope"}]"#,
+        ));
+        let replies = out.as_array().unwrap();
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[0]["id"], json!(1));
+        assert_eq!((error_code(&replies[1]), &replies[1]["id"]), (INVALID_REQUEST, &Value::Null));
+        assert_eq!((error_code(&replies[2]), &replies[2]["id"]), (METHOD_NOT_FOUND, &json!(2)));
+    }
+}
@@ -0,0 +1,392 @@
+/*!
+ * Built-in self-test and micro-benchmarks (`selftest` feature)
+ *
+ * `run_self_test()` checks every enabled export against embedded
+ * known-answer vectors, so a deployed module can prove it computes what the
+ * source says (including the `simd128` kernels). `run_benchmarks(iterations)`
+ * times the exports on a fixed 4 KiB corpus.
+ *
+ * Both return JSON:
+ *
+ * ```json
+ * {"passed":12,"failed":0,"checks":[{"function":"count_vowels","vectors":4,"passed":true}, ...]}
+ * {"iterations":1000,"clock":"host","results":[{"function":"count_vowels","bytes_per_call":4096,
+ *   "elapsed_ms":1.25,"calls_per_sec":800000.0,"mb_per_sec":3125.0}, ...]}
+ * ```
+ *
+ * A failed check adds `"input"`, `"expected"` and `"actual"` for its first
+ * failing vector. Timing uses `entropy::monotonic_ms()`: wasm32 builds need
+ * the `host-entropy` feature for a real clock, and in deterministic mode the
+ * clock is virtual (`"clock":"virtual"`), so throughput is `null`.
+ *
+ * Both run outside the fuel budget and leave the module state (budget,
+ * parked progress, entropy, last error) as they found it.
+ */
+
+use std::fmt::Debug;
+#[cfg(any(feature = "text", feature = "hash", feature = "cipher", feature = "math"))]
+use std::hint::black_box;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{self, guard, EchelonError, ErrorCode, Result};
+use crate::logging::push_json_str;
+
+/// Most iterations `run_benchmarks` accepts per function
+pub const MAX_BENCH_ITERATIONS: u32 = 1_000_000;
+
+/// Size of the benchmark corpus in bytes
+pub const BENCH_CORPUS_LEN: usize = 4096;
+
+const PANGRAM: &str = "The quick brown fox jumps over the lazy dog";
+
+/// Known-answer results for one export
+struct Check {
+    function: &'static str,
+    vectors: u32,
+    /// Input, expected and actual value of the first failing vector
+    failure: Option<[String; 3]>,
+}
+
+#[derive(Default)]
+struct SelfTest {
+    checks: Vec<Check>,
+}
+
+impl SelfTest {
+    fn check<T: PartialEq + Debug>(&mut self, function: &'static str, input: impl Debug, actual: T, expected: T) {
+        let index = match self.checks.iter().position(|check| check.function == function) {
+            Some(index) => index,
+            None => {
+                self.checks.push(Check { function, vectors: 0, failure: None });
+                self.checks.len() - 1
+            }
+        };
+        let check = &mut self.checks[index];
+        check.vectors += 1;
+        if actual != expected && check.failure.is_none() {
+            check.failure = Some([format!("{input:?}"), format!("{expected:?}"), format!("{actual:?}")]);
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let failed = self.checks.iter().filter(|check| check.failure.is_some()).count();
+        let mut out = format!("{{\"passed\":{},\"failed\":{failed},\"checks\":[", self.checks.len() - failed);
+        for (index, check) in self.checks.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            out.push_str("{\"function\":");
+            push_json_str(&mut out, check.function);
+            out.push_str(&format!(",\"vectors\":{},\"passed\":{}", check.vectors, check.failure.is_none()));
+            if let Some([input, expected, actual]) = &check.failure {
+                for (key, value) in [("input", input), ("expected", expected), ("actual", actual)] {
+                    out.push_str(&format!(",\"{key}\":"));
+                    push_json_str(&mut out, value);
+                }
+            }
+            out.push('}');
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+#[cfg(feature = "text")]
+fn text_vectors(t: &mut SelfTest) {
+    use crate::text::*;
+
+    for (input, expected) in [(PANGRAM, 11), ("AEIOU aeiou xyz", 10), ("héllo wörld", 1), ("", 0)] {
+        t.check("count_vowels", input, count_vowels(input), expected);
+    }
+    for (input, expected) in [("Hello", "olleH"), ("héllo wörld", "dlröw olléh"), ("", "")] {
+        t.check("reverse_string", input, reverse_string(input).as_str(), expected);
+    }
+    for (input, expected) in [
+        ("A man, a plan, a canal: Panama", true),
+        ("racecar", true),
+        ("été", true),
+        (PANGRAM, false),
+    ] {
+        t.check("is_palindrome", input, is_palindrome(input), expected);
+    }
+    for (input, expected) in [(PANGRAM, 5), ("héllo wörld", 6), ("  ", 0)] {
+        t.check("longest_word_length", input, longest_word_length(input), expected);
+    }
+    for (input, expected) in [(PANGRAM, 9), ("  one\ttwo\n three  ", 3), ("", 0)] {
+        t.check("word_count", input, word_count(input), expected);
+    }
+}
+
+#[cfg(feature = "hash")]
+fn hash_vectors(t: &mut SelfTest) {
+    for (input, expected) in [("", 5381), ("hello", 261_238_937), (PANGRAM, 885_799_134)] {
+        t.check("hash_string", input, crate::hash_string(input), expected);
+    }
+}
+
+#[cfg(feature = "cipher")]
+fn cipher_vectors(t: &mut SelfTest) {
+    for (input, shift, expected) in [("Hello, World!", 3, "Khoor, Zruog!"), ("xyz", 29, "abc")] {
+        t.check("caesar_encrypt", (input, shift), crate::caesar_encrypt(input, shift).as_str(), expected);
+    }
+}
+
+#[cfg(feature = "math")]
+fn math_vectors(t: &mut SelfTest) {
+    use crate::math::*;
+
+    let overflow = ErrorCode::Overflow as u32;
+    t.check("add", (2, 3), (add(2, 3), error::last_error_code()), (5, 0));
+    t.check("add", (i32::MAX, 1), (add(i32::MAX, 1), error::last_error_code()), (0, overflow));
+    t.check("multiply", (6, -7), (multiply(6, -7), error::last_error_code()), (-42, 0));
+    t.check("multiply", (i32::MIN, -1), (multiply(i32::MIN, -1), error::last_error_code()), (0, overflow));
+    // Version queries leave the slot for the host to read
+    add(i32::MAX, 1);
+    let version = crate::version::ABI_VERSION;
+    t.check("abi_version", (), (crate::version::abi_version(), error::last_error_code()), (version, overflow));
+    // Infallible exports that do work reset it
+    #[cfg(feature = "hash")]
+    t.check("hash_string", "", (crate::hash_string(""), error::last_error_code()), (crate::hash::DJB2_SEED, 0));
+    t.check("memory_intensive", 1000, memory_intensive(1000), 499_500);
+}
+
+#[cfg(all(feature = "batch", any(feature = "text", feature = "hash")))]
+fn batch_vectors(t: &mut SelfTest) {
+    let items = || vec!["racecar".to_string(), "a b".to_string()];
+    #[cfg(feature = "hash")]
+    t.check("hash_strings", items(), crate::batch::hash_strings(items()), vec![883_662_486, 193_483_784]);
+    #[cfg(feature = "text")]
+    {
+        t.check("palindrome_flags", items(), crate::batch::palindrome_flags(items()), vec![1, 0]);
+        t.check("word_counts", items(), crate::batch::word_counts(items()), vec![1, 2]);
+    }
+}
+
+#[cfg(feature = "stream")]
+fn stream_vectors(t: &mut SelfTest) {
+    // Chunk boundary inside a multi-byte character
+    #[cfg(feature = "text")]
+    {
+        let mut stats = crate::stream::TextStats::new();
+        let bytes = "héllo wörld".as_bytes();
+        stats.update(&bytes[..2]);
+        stats.update(&bytes[2..]);
+        let summary = stats.finish();
+        t.check(
+            "TextStats",
+            "héllo wörld",
+            (summary.bytes, summary.chars, summary.vowels, summary.words, summary.longest_word),
+            (13, 11, 1, 2, 6),
+        );
+    }
+    #[cfg(feature = "hash")]
+    {
+        let mut hasher = crate::stream::StreamingHasher::new();
+        hasher.update_str("hel");
+        hasher.update_str("lo");
+        t.check("StreamingHasher", ("hel", "lo"), hasher.finish(), 261_238_937);
+    }
+}
+
+fn entropy_vectors(t: &mut SelfTest) {
+    use crate::entropy::*;
+
+    // SplitMix64 reference outputs for seed 0
+    let saved = state();
+    set_deterministic(0, 1000.0);
+    let draws = (random_u32(), random_u32(), now_ms());
+    set_state(saved);
+    t.check("set_deterministic", (0, 1000.0), draws, (0xe220_a839, 0x6e78_9e6a, 1000.0));
+}
+
+#[cfg(feature = "snapshot")]
+fn snapshot_vectors(t: &mut SelfTest) {
+    let snapshot = crate::snapshot::encode();
+    t.check("restore", "snapshot()", crate::snapshot::decode(&snapshot).is_ok(), true);
+
+    let mut corrupted = snapshot;
+    corrupted[4] ^= 0xff;
+    t.check("restore", "corrupted snapshot()", crate::snapshot::decode(&corrupted).is_ok(), false);
+}
+
+#[cfg(feature = "http")]
+fn http_vectors(t: &mut SelfTest) {
+    let mut request = Vec::new();
+    for field in ["GET", "/health"] {
+        request.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        request.extend_from_slice(field.as_bytes());
+    }
+    request.extend_from_slice(&[0; 8]);
+    let status = crate::http::handle(&request).ok().and_then(|response| response.get(..4).map(|s| s.to_vec()));
+    t.check("handle_request", "GET /health", status, Some(200u32.to_le_bytes().to_vec()));
+}
+
+#[cfg(all(feature = "rpc", feature = "math"))]
+fn rpc_vectors(t: &mut SelfTest) {
+    let line = r#"{"jsonrpc":"2.0","id":1,"method":"add","params":[2,3]}"#;
+    let response = crate::rpc::handle_line(line).unwrap_or_default();
+    t.check("rpc", line, response.contains("\"result\":5"), true);
+}
+
+#[cfg(all(feature = "codec", feature = "math"))]
+fn codec_vectors(t: &mut SelfTest) {
+    use crate::codec::{call, Encoding};
+
+    // {"a": 2, "b": 3}
+    let cbor = [0xa2, 0x61, b'a', 0x02, 0x61, b'b', 0x03];
+    let msgpack = [0x82, 0xa1, b'a', 0x02, 0xa1, b'b', 0x03];
+    t.check("call_encoded", "Cbor add {a: 2, b: 3}", call(Encoding::Cbor, "add", &cbor).ok(), Some(vec![0x05]));
+    t.check("call_encoded", "MessagePack add {a: 2, b: 3}", call(Encoding::MessagePack, "add", &msgpack).ok(), Some(vec![0x05]));
+}
+
+/// Run every known-answer vector of this build
+pub fn self_test() -> String {
+    let mut t = SelfTest::default();
+    crate::fuel::unmetered(|| {
+        #[cfg(feature = "text")]
+        text_vectors(&mut t);
+        #[cfg(feature = "hash")]
+        hash_vectors(&mut t);
+        #[cfg(feature = "cipher")]
+        cipher_vectors(&mut t);
+        #[cfg(feature = "math")]
+        math_vectors(&mut t);
+        #[cfg(all(feature = "batch", any(feature = "text", feature = "hash")))]
+        batch_vectors(&mut t);
+        #[cfg(feature = "stream")]
+        stream_vectors(&mut t);
+        entropy_vectors(&mut t);
+        #[cfg(feature = "snapshot")]
+        snapshot_vectors(&mut t);
+        #[cfg(feature = "http")]
+        http_vectors(&mut t);
+        #[cfg(all(feature = "rpc", feature = "math"))]
+        rpc_vectors(&mut t);
+        #[cfg(all(feature = "codec", feature = "math"))]
+        codec_vectors(&mut t);
+    });
+    error::clear_last_error();
+    t.to_json()
+}
+
+/// Time `iterations` calls of every enabled export
+#[cfg_attr(
+    not(any(feature = "text", feature = "hash", feature = "cipher")),
+    allow(unused_mut, unused_variables)
+)]
+pub fn benchmarks(iterations: u32) -> Result<String> {
+    if iterations == 0 || iterations > MAX_BENCH_ITERATIONS {
+        return Err(EchelonError::new(
+            ErrorCode::InvalidArgument,
+            format!("run_benchmarks: iterations must be 1..={MAX_BENCH_ITERATIONS}, got {iterations}"),
+        ));
+    }
+
+    let corpus = corpus();
+    let corpus = corpus.as_str();
+    let mut results: Vec<(&'static str, usize, f64)> = Vec::new();
+    let mut bench = |function: &'static str, bytes: usize, f: &dyn Fn()| {
+        let start = crate::entropy::monotonic_ms();
+        for _ in 0..iterations {
+            f();
+        }
+        results.push((function, bytes, crate::entropy::monotonic_ms() - start));
+    };
+
+    crate::fuel::unmetered(|| {
+        #[cfg(feature = "text")]
+        {
+            bench("count_vowels", corpus.len(), &|| {
+                black_box(crate::count_vowels(black_box(corpus)));
+            });
+            bench("reverse_string", corpus.len(), &|| {
+                black_box(crate::reverse_string(black_box(corpus)));
+            });
+            bench("is_palindrome", corpus.len(), &|| {
+                black_box(crate::is_palindrome(black_box(corpus)));
+            });
+            bench("longest_word_length", corpus.len(), &|| {
+                black_box(crate::longest_word_length(black_box(corpus)));
+            });
+            bench("word_count", corpus.len(), &|| {
+                black_box(crate::word_count(black_box(corpus)));
+            });
+        }
+        #[cfg(feature = "hash")]
+        bench("hash_string", corpus.len(), &|| {
+            black_box(crate::hash_string(black_box(corpus)));
+        });
+        #[cfg(feature = "cipher")]
+        bench("caesar_encrypt", corpus.len(), &|| {
+            black_box(crate::caesar_encrypt(black_box(corpus), 13));
+        });
+        #[cfg(feature = "math")]
+        {
+            bench("add", 0, &|| {
+                black_box(crate::add(black_box(20), black_box(22)));
+            });
+            bench("multiply", 0, &|| {
+                black_box(crate::multiply(black_box(6), black_box(7)));
+            });
+            bench("memory_intensive", 0, &|| {
+                black_box(crate::memory_intensive(black_box(1024)));
+            });
+        }
+    });
+    error::clear_last_error();
+
+    let clock = if crate::entropy::is_deterministic() { "virtual" } else { "host" };
+    let mut out = format!("{{\"iterations\":{iterations},\"clock\":\"{clock}\",\"results\":[");
+    for (index, (function, bytes, elapsed)) in results.iter().enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+        let seconds = elapsed / 1000.0;
+        let calls_per_sec = (seconds > 0.0).then(|| iterations as f64 / seconds);
+        let mb_per_sec = calls_per_sec.filter(|_| *bytes > 0).map(|calls| calls * *bytes as f64 / 1e6);
+        out.push_str(&format!(
+            "{{\"function\":\"{function}\",\"bytes_per_call\":{bytes},\"elapsed_ms\":{},\"calls_per_sec\":{},\"mb_per_sec\":{}}}",
+            json_f64(Some(*elapsed)),
+            json_f64(calls_per_sec),
+            json_f64(mb_per_sec),
+        ));
+    }
+    out.push_str("]}");
+    Ok(out)
+}
+
+/// Check every enabled export against embedded known-answer vectors (JSON report)
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn run_self_test() -> String {
+    self_test()
+}
+
+/// Per-function throughput over `iterations` calls (JSON report)
+///
+/// Records `InvalidArgument` and returns an empty string unless
+/// `1 <= iterations <= MAX_BENCH_ITERATIONS`.
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn run_benchmarks(iterations: u32) -> String {
+    guard(|| benchmarks(iterations))
+}
+
+/// `PANGRAM` repeated to `BENCH_CORPUS_LEN` bytes
+fn corpus() -> String {
+    let mut corpus = String::with_capacity(BENCH_CORPUS_LEN + PANGRAM.len());
+    while corpus.len() < BENCH_CORPUS_LEN {
+        corpus.push_str(PANGRAM);
+        corpus.push(' ');
+    }
+    corpus.truncate(BENCH_CORPUS_LEN);
+    corpus
+}
+
+fn json_f64(value: Option<f64>) -> String {
+    match value {
+        Some(value) if value.is_finite() => format!("{value:.3}"),
+        _ => "null".to_string(),
+    }
+}
@@ -0,0 +1,316 @@
+/*!
+ * Shared input/output buffers (`shared-buffers` feature)
+ *
+ * The string exports take their input as a fresh allocation (wasm-bindgen
+ * glue or `echelon_alloc`) and return a fresh allocation the host copies out
+ * and frees. For large payloads the module instead keeps one reusable input
+ * region and one output region per thread, and the `*_shared` exports work
+ * on them in place:
+ *
+ * ```text
+ * ptr = input_buffer(capacity)        // grows the region if needed
+ * <host encodes UTF-8 straight into memory[ptr..]>
+ * len = reverse_string_shared(written)
+ * <host decodes memory[output_buffer()..][..len]>
+ * ```
+ *
+ * - `input_buffer(capacity)` returns the region address, `0` on failure. It
+ *   only moves when it has to grow, and growing drops the old contents.
+ * - Exports returning a string write it to the output region and return its
+ *   byte length; `output_buffer()` is valid until the next `*_shared` call.
+ * - A `len` beyond the input capacity records `InvalidArgument`.
+ *
+ * The regions come straight from `System`, bypassing the tracking allocator
+ * and the arena, so they survive `end_scope()` and are not counted by
+ * `heap_stats`.
+ * `release_shared_buffers()` gives them back.
+ */
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{guard, EchelonError, ErrorCode, Result};
+
+const REGION_ALIGN: usize = 16;
+
+/// Reusable block whose contents are not kept when it grows
+struct Region {
+    ptr: *mut u8,
+    capacity: usize,
+}
+
+impl Region {
+    const fn new() -> Self {
+        Region { ptr: std::ptr::null_mut(), capacity: 0 }
+    }
+
+    /// Make room for at least `capacity` bytes and return the start
+    fn reserve(&mut self, capacity: usize) -> Result<*mut u8> {
+        if capacity <= self.capacity && !self.ptr.is_null() {
+            return Ok(self.ptr);
+        }
+
+        self.release();
+        let layout = Layout::from_size_align(capacity.max(1), REGION_ALIGN).map_err(|_| out_of_memory(capacity))?;
+        // SAFETY: `layout` has a non-zero size; zeroed so the host never hands us uninitialized bytes
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if ptr.is_null() {
+            return Err(out_of_memory(capacity));
+        }
+        self.ptr = ptr;
+        self.capacity = capacity;
+        Ok(ptr)
+    }
+
+    fn release(&mut self) {
+        if !self.ptr.is_null() {
+            // SAFETY: `ptr` was allocated by `reserve` with this layout
+            unsafe { System.dealloc(self.ptr, Layout::from_size_align_unchecked(self.capacity.max(1), REGION_ALIGN)) };
+        }
+        self.ptr = std::ptr::null_mut();
+        self.capacity = 0;
+    }
+}
+
+impl Drop for Region {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+thread_local! {
+    static INPUT: RefCell<Region> = const { RefCell::new(Region::new()) };
+    static OUTPUT: RefCell<Region> = const { RefCell::new(Region::new()) };
+}
+
+fn out_of_memory(capacity: usize) -> EchelonError {
+    EchelonError::new(ErrorCode::OutOfMemory, format!("cannot allocate {capacity} byte shared buffer"))
+}
+
+/// Borrow the first `len` bytes of the input region as UTF-8
+#[cfg_attr(not(any(feature = "text", feature = "hash", feature = "cipher")), allow(dead_code))]
+fn with_input<T>(len: usize, f: impl FnOnce(&str) -> T) -> Result<T> {
+    INPUT.with_borrow(|input| {
+        if len > input.capacity {
+            return Err(EchelonError::new(
+                ErrorCode::InvalidArgument,
+                format!("input length {len} exceeds the {} byte input buffer", input.capacity),
+            ));
+        }
+        // SAFETY: the region is valid for reads of `capacity` bytes
+        Ok(f(unsafe { crate::abi::read_str(input.ptr, len)? }))
+    })
+}
+
+/// Fill the output region with `len` bytes from `write`, returns `len`
+#[cfg_attr(not(any(feature = "text", feature = "cipher")), allow(dead_code))]
+fn write_output(len: usize, write: impl FnOnce(&mut [u8])) -> Result<usize> {
+    OUTPUT.with_borrow_mut(|output| {
+        let ptr = output.reserve(len)?;
+        // SAFETY: `reserve` made `ptr` valid for writes of `len` bytes
+        write(unsafe { std::slice::from_raw_parts_mut(ptr, len) });
+        Ok(len)
+    })
+}
+
+/// Address of the input region, grown to at least `capacity` bytes (`0` on failure)
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn input_buffer(capacity: usize) -> usize {
+    guard(|| INPUT.with_borrow_mut(|input| input.reserve(capacity)).map(|ptr| ptr as usize))
+}
+
+/// Bytes the input region can hold
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn input_capacity() -> usize {
+    INPUT.with_borrow(|input| input.capacity)
+}
+
+/// Address of the output region (`0` before the first string result)
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn output_buffer() -> usize {
+    OUTPUT.with_borrow(|output| output.ptr as usize)
+}
+
+/// Free both regions; the next `input_buffer` call allocates again
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn release_shared_buffers() {
+    INPUT.with_borrow_mut(Region::release);
+    OUTPUT.with_borrow_mut(Region::release);
+}
+
+/// `count_vowels` of the first `len` input bytes
+#[cfg(feature = "text")]
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn count_vowels_shared(len: usize) -> usize {
+    guard(|| with_input(len, crate::count_vowels))
+}
+
+/// `reverse_string` of the first `len` input bytes into the output region, returns its length
+#[cfg(feature = "text")]
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn reverse_string_shared(len: usize) -> usize {
+    guard(|| {
+        with_input(len, |s| {
+            write_output(s.len(), |out| {
+                let mut end = out.len();
+                for c in s.chars() {
+                    end -= c.len_utf8();
+                    c.encode_utf8(&mut out[end..]);
+                }
+            })
+        })?
+    })
+}
+
+/// `is_palindrome` of the first `len` input bytes
+#[cfg(feature = "text")]
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn is_palindrome_shared(len: usize) -> bool {
+    guard(|| with_input(len, crate::is_palindrome))
+}
+
+/// `hash_string` of the first `len` input bytes
+#[cfg(feature = "hash")]
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn hash_string_shared(len: usize) -> u32 {
+    guard(|| with_input(len, crate::hash_string))
+}
+
+/// `longest_word_length` of the first `len` input bytes
+#[cfg(feature = "text")]
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn longest_word_length_shared(len: usize) -> usize {
+    guard(|| with_input(len, crate::longest_word_length))
+}
+
+/// `word_count` of the first `len` input bytes
+#[cfg(feature = "text")]
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn word_count_shared(len: usize) -> usize {
+    guard(|| with_input(len, crate::word_count))
+}
+
+/// `caesar_encrypt` of the first `len` input bytes into the output region, returns its length
+#[cfg(feature = "cipher")]
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn caesar_encrypt_shared(len: usize, shift: u8) -> usize {
+    guard(|| {
+        with_input(len, |s| {
+            // Only ASCII letters change, so the shift works byte by byte
+            write_output(s.len(), |out| {
+                for (dst, &byte) in out.iter_mut().zip(s.as_bytes()) {
+                    *dst = crate::cipher::shift_ascii(byte, shift);
+                }
+            })
+        })?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "text")]
+    use crate::error::last_error_code;
+
+    /// Copy `bytes` into the input region the way the host does, returns their length
+    fn write_input(bytes: &[u8]) -> usize {
+        let ptr = input_buffer(bytes.len()) as *mut u8;
+        assert!(!ptr.is_null());
+        // SAFETY: `input_buffer` made the region valid for `bytes.len()` bytes
+        unsafe { ptr.copy_from_nonoverlapping(bytes.as_ptr(), bytes.len()) };
+        bytes.len()
+    }
+
+    /// The first `len` bytes of the output region
+    #[cfg(any(feature = "text", feature = "cipher"))]
+    fn read_output(len: usize) -> String {
+        // SAFETY: the last `*_shared` call wrote `len` bytes there
+        let bytes = unsafe { std::slice::from_raw_parts(output_buffer() as *const u8, len) };
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn regions_grow_only_when_needed() {
+        release_shared_buffers();
+        assert_eq!((input_capacity(), output_buffer()), (0, 0));
+
+        let ptr = input_buffer(16);
+        assert_ne!(ptr, 0);
+        assert_eq!(ptr % REGION_ALIGN, 0);
+        assert_eq!(input_capacity(), 16);
+        // SAFETY: the region is valid for 16 bytes
+        assert_eq!(unsafe { std::slice::from_raw_parts(ptr as *const u8, 16) }, [0; 16]);
+
+        // Smaller requests reuse the region and keep its contents
+        write_input(b"kept");
+        assert_eq!(input_buffer(8), ptr);
+        assert_eq!(input_capacity(), 16);
+        // SAFETY: as above
+        assert_eq!(unsafe { std::slice::from_raw_parts(ptr as *const u8, 4) }, b"kept");
+
+        // Growing drops them
+        let ptr = input_buffer(64);
+        assert_eq!(input_capacity(), 64);
+        // SAFETY: the region is valid for 64 bytes
+        assert_eq!(unsafe { std::slice::from_raw_parts(ptr as *const u8, 64) }, [0; 64]);
+
+        release_shared_buffers();
+        assert_eq!((input_capacity(), output_buffer()), (0, 0));
+        assert_ne!(input_buffer(0), 0);
+        release_shared_buffers();
+    }
+
+    #[test]
+    #[cfg(feature = "text")]
+    fn lengths_are_checked_against_the_input_capacity() {
+        release_shared_buffers();
+        let len = write_input(b"aeiou");
+        assert_eq!(count_vowels_shared(len), 5);
+        assert_eq!(last_error_code(), ErrorCode::Ok as u32);
+
+        assert_eq!(count_vowels_shared(input_capacity() + 1), 0);
+        assert_eq!(last_error_code(), ErrorCode::InvalidArgument as u32);
+        assert_eq!(reverse_string_shared(input_capacity() + 1), 0);
+        assert_eq!(last_error_code(), ErrorCode::InvalidArgument as u32);
+
+        let len = write_input(b"ab\xff");
+        assert_eq!(word_count_shared(len), 0);
+        assert_eq!(last_error_code(), ErrorCode::InvalidUtf8 as u32);
+        release_shared_buffers();
+    }
+
+    #[test]
+    #[cfg(feature = "text")]
+    fn reverse_keeps_multi_byte_characters_whole() {
+        release_shared_buffers();
+        for s in ["héllo wörld", "🦀 ok ✓", "a", ""] {
+            let len = write_input(s.as_bytes());
+            assert_eq!(reverse_string_shared(len), s.len());
+            assert_eq!(read_output(s.len()), s.chars().rev().collect::<String>());
+        }
+        release_shared_buffers();
+    }
+
+    #[test]
+    #[cfg(feature = "cipher")]
+    fn caesar_leaves_multi_byte_characters_alone() {
+        release_shared_buffers();
+        let len = write_input("Héllo, wörld 🦀".as_bytes());
+        assert_eq!(caesar_encrypt_shared(len, 1), len);
+        assert_eq!(read_output(len), "Iémmp, xösme 🦀");
+        release_shared_buffers();
+    }
+}
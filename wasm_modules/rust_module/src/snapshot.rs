@@ -0,0 +1,362 @@
+/*!
+ * Snapshot and restore of module state (`snapshot` feature)
+ *
+ * `snapshot()` serializes the module's global state, so the host can persist
+ * it (`WASMModuleCache.saveSnapshot`) when the runtime recycles an instance
+ * and `restore(bytes)` it into a fresh one:
+ *
+ * ```text
+ * snapshot: "ECHS" u16(version) u16(count) section*count u32(crc32)
+ * section:  u16(tag) u32(len) [len bytes]
+ *
+ * u16/u32 = LE, crc32 = CRC-32 (IEEE) of every byte before it
+ * ```
+ *
+ * | Tag | State | Payload |
+ * |-----|-------|---------|
+ * | 1 | `entropy` | u8(seeded) u64(generator) f64(clock) |
+ * | 2 | `fuel` | u8(limited) u32(remaining) u32(used) |
+ * | 3 | `logging` | u8(level) |
+ *
+ * Compatibility: new state gets a new tag without a version bump, and
+ * `restore` skips tags it does not know, so older modules accept newer
+ * snapshots. `FORMAT_VERSION` only changes when an existing payload does;
+ * snapshots from a newer format are rejected. Sections missing from a
+ * snapshot leave that state as it is.
+ *
+ * `restore` decodes every section before applying any, so a rejected
+ * snapshot changes nothing. Fuel progress parked by a suspended call is not
+ * captured and is dropped on restore.
+ */
+
+use wasm_bindgen::prelude::*;
+
+use crate::error::{guard, EchelonError, ErrorCode, Result};
+
+/// Leading bytes of every snapshot
+pub const MAGIC: &[u8; 4] = b"ECHS";
+
+/// Current snapshot format version
+pub const FORMAT_VERSION: u16 = 1;
+
+const TAG_ENTROPY: u16 = 1;
+const TAG_FUEL: u16 = 2;
+const TAG_LOGGING: u16 = 3;
+
+/// Sections decoded from a snapshot, applied together once all are valid
+#[derive(Default)]
+struct State {
+    entropy: Option<Option<(u64, f64)>>,
+    fuel: Option<(Option<u32>, u32)>,
+    log_level: Option<u32>,
+}
+
+impl State {
+    fn capture() -> Self {
+        Self {
+            entropy: Some(crate::entropy::state()),
+            fuel: Some(crate::fuel::state()),
+            log_level: Some(crate::logging::level()),
+        }
+    }
+
+    fn apply(self) {
+        if let Some(seeded) = self.entropy {
+            crate::entropy::set_state(seeded);
+        }
+        if let Some(fuel) = self.fuel {
+            crate::fuel::set_state(fuel);
+        }
+        if let Some(level) = self.log_level {
+            crate::logging::init_logging(level);
+        }
+    }
+
+    fn sections(&self) -> Vec<(u16, Vec<u8>)> {
+        let mut sections = Vec::new();
+        if let Some(seeded) = self.entropy {
+            let (state, clock) = seeded.unwrap_or_default();
+            let mut payload = vec![seeded.is_some() as u8];
+            payload.extend_from_slice(&state.to_le_bytes());
+            payload.extend_from_slice(&clock.to_le_bytes());
+            sections.push((TAG_ENTROPY, payload));
+        }
+        if let Some((fuel, used)) = self.fuel {
+            let mut payload = vec![fuel.is_some() as u8];
+            payload.extend_from_slice(&fuel.unwrap_or_default().to_le_bytes());
+            payload.extend_from_slice(&used.to_le_bytes());
+            sections.push((TAG_FUEL, payload));
+        }
+        if let Some(level) = self.log_level {
+            sections.push((TAG_LOGGING, vec![level as u8]));
+        }
+        sections
+    }
+
+    fn decode_section(&mut self, tag: u16, payload: &[u8]) -> Result<()> {
+        let mut reader = Reader { buf: payload, offset: 0 };
+        match tag {
+            TAG_ENTROPY => {
+                let seeded = reader.flag()?;
+                let state = u64::from_le_bytes(reader.array()?);
+                let clock = f64::from_le_bytes(reader.array()?);
+                self.entropy = Some(seeded.then_some((state, clock)));
+            }
+            TAG_FUEL => {
+                let limited = reader.flag()?;
+                let remaining = u32::from_le_bytes(reader.array()?);
+                let used = u32::from_le_bytes(reader.array()?);
+                self.fuel = Some((limited.then_some(remaining), used));
+            }
+            TAG_LOGGING => {
+                let [level] = reader.array()?;
+                if level > 5 {
+                    return Err(invalid(format!("log level {level} out of range")));
+                }
+                self.log_level = Some(level as u32);
+            }
+            _ => return Ok(()),
+        }
+        reader.finish()
+    }
+}
+
+/// Serialize the current module state
+pub fn encode() -> Vec<u8> {
+    let sections = State::capture().sections();
+    let mut out = Vec::with_capacity(64);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&(sections.len() as u16).to_le_bytes());
+    for (tag, payload) in &sections {
+        out.extend_from_slice(&tag.to_le_bytes());
+        out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        out.extend_from_slice(payload);
+    }
+    out.extend_from_slice(&crc32(&out).to_le_bytes());
+    out
+}
+
+/// Validate a snapshot and apply it; nothing changes on error
+pub fn decode(bytes: &[u8]) -> Result<()> {
+    let body_len = bytes
+        .len()
+        .checked_sub(4)
+        .filter(|len| *len >= MAGIC.len() + 4)
+        .ok_or_else(|| invalid("truncated snapshot"))?;
+    let (body, checksum) = bytes.split_at(body_len);
+    if &body[..MAGIC.len()] != MAGIC {
+        return Err(invalid("not an echelon_wasm snapshot"));
+    }
+    if crc32(body).to_le_bytes() != checksum {
+        return Err(invalid("snapshot checksum mismatch"));
+    }
+
+    let mut reader = Reader { buf: body, offset: MAGIC.len() };
+    let version = u16::from_le_bytes(reader.array()?);
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(invalid(format!(
+            "snapshot format version {version} is not supported (expected 1..={FORMAT_VERSION})"
+        )));
+    }
+
+    let mut state = State::default();
+    for _ in 0..u16::from_le_bytes(reader.array()?) {
+        let tag = u16::from_le_bytes(reader.array()?);
+        let payload = reader.bytes()?;
+        state.decode_section(tag, payload)?;
+    }
+    reader.finish()?;
+
+    state.apply();
+    Ok(())
+}
+
+/// Serialize the module state (see the module docs for the format)
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn snapshot() -> Vec<u8> {
+    encode()
+}
+
+/// Restore state from `snapshot()` bytes
+///
+/// Returns `false` and records `InvalidArgument` if the snapshot is
+/// truncated, corrupted or from a newer format; the state is then unchanged.
+#[doc(alias = "impure")]
+#[wasm_bindgen]
+pub fn restore(bytes: &[u8]) -> bool {
+    guard(|| decode(bytes).map(|()| true))
+}
+
+/// CRC-32 (IEEE, reflected) of `bytes`
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+fn invalid(message: impl Into<String>) -> EchelonError {
+    EchelonError::new(ErrorCode::InvalidArgument, message)
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let bytes = self.buf.get(self.offset..self.offset + N).ok_or_else(|| invalid("truncated snapshot"))?;
+        self.offset += N;
+        let mut out = [0u8; N];
+        out.copy_from_slice(bytes);
+        Ok(out)
+    }
+
+    fn flag(&mut self) -> Result<bool> {
+        match self.array()? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            [other] => Err(invalid(format!("invalid snapshot flag {other}"))),
+        }
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = u32::from_le_bytes(self.array()?) as usize;
+        let end = self.offset.checked_add(len).ok_or_else(|| invalid("truncated snapshot"))?;
+        let bytes = self.buf.get(self.offset..end).ok_or_else(|| invalid("truncated snapshot"))?;
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn finish(&self) -> Result<()> {
+        if self.offset == self.buf.len() {
+            Ok(())
+        } else {
+            Err(invalid("trailing bytes in snapshot"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replace the trailing checksum after editing a snapshot
+    fn reseal(bytes: &mut [u8]) {
+        let body_len = bytes.len() - 4;
+        let crc = crc32(&bytes[..body_len]);
+        bytes[body_len..].copy_from_slice(&crc.to_le_bytes());
+    }
+
+    fn rejected(bytes: &[u8], message: &str) {
+        let before = (crate::entropy::state(), crate::fuel::state());
+        let err = decode(bytes).expect_err("snapshot accepted");
+        assert_eq!(err.code, ErrorCode::InvalidArgument);
+        assert!(err.message.contains(message), "{err}");
+        assert_eq!((crate::entropy::state(), crate::fuel::state()), before);
+    }
+
+    #[test]
+    fn crc32_matches_ieee() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn round_trip_restores_state() {
+        crate::entropy::set_state(Some((42, 1_000.0)));
+        crate::fuel::set_state((Some(7), 3));
+        let bytes = encode();
+        assert_eq!(&bytes[..4], MAGIC);
+
+        crate::entropy::set_state(Some((1, 0.0)));
+        crate::fuel::set_state((None, 0));
+        decode(&bytes).unwrap();
+        assert_eq!(crate::entropy::state(), Some((42, 1_000.0)));
+        assert_eq!(crate::fuel::state(), (Some(7), 3));
+        assert_eq!(encode(), bytes);
+    }
+
+    #[test]
+    fn skips_unknown_sections() {
+        crate::fuel::set_state((Some(9), 1));
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&99u16.to_le_bytes());
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(b"new");
+        bytes.extend_from_slice(&[0; 4]);
+        reseal(&mut bytes);
+        decode(&bytes).unwrap();
+        assert_eq!(crate::fuel::state(), (Some(9), 1));
+    }
+
+    #[test]
+    fn rejects_corrupted_checksum() {
+        crate::entropy::set_state(Some((5, 0.0)));
+        let mut bytes = encode();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        rejected(&bytes, "checksum");
+
+        // A flipped payload bit is caught the same way
+        let mut bytes = encode();
+        bytes[12] ^= 0x80;
+        rejected(&bytes, "checksum");
+    }
+
+    #[test]
+    fn rejects_wrong_magic() {
+        let mut bytes = encode();
+        bytes[..4].copy_from_slice(b"ECHX");
+        reseal(&mut bytes);
+        rejected(&bytes, "not an echelon_wasm snapshot");
+    }
+
+    #[test]
+    fn rejects_unsupported_version() {
+        for version in [0, FORMAT_VERSION + 1] {
+            let mut bytes = encode();
+            bytes[4..6].copy_from_slice(&version.to_le_bytes());
+            reseal(&mut bytes);
+            rejected(&bytes, "format version");
+        }
+    }
+
+    #[test]
+    fn rejects_truncation() {
+        let bytes = encode();
+        for len in 0..bytes.len() {
+            let mut cut = bytes[..len].to_vec();
+            if len >= MAGIC.len() + 8 {
+                // Valid checksum, so only the layout can reject it
+                reseal(&mut cut);
+            }
+            assert!(decode(&cut).is_err(), "prefix of {len} bytes accepted");
+        }
+        rejected(&bytes[..6], "truncated snapshot");
+    }
+
+    #[test]
+    fn rejects_trailing_bytes_and_bad_payloads() {
+        let mut bytes = encode();
+        bytes.insert(bytes.len() - 4, 0);
+        reseal(&mut bytes);
+        rejected(&bytes, "trailing bytes");
+
+        // Entropy `seeded` flag of 2 (first section payload starts at byte 14)
+        let mut bytes = encode();
+        bytes[14] = 2;
+        reseal(&mut bytes);
+        rejected(&bytes, "invalid snapshot flag 2");
+    }
+}
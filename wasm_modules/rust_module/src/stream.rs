@@ -0,0 +1,333 @@
+/*!
+ * Streaming incremental processors
+ *
+ * Stateful structs fed chunk by chunk (e.g. `ReadableStream` chunks from the
+ * HTTP layer) so large bodies never need to be buffered in JS. Chunks are
+ * raw bytes and may split a UTF-8 sequence anywhere; `finish()` returns the
+ * result and resets the processor for the next stream.
+ *
+ * For valid UTF-8 input the results equal the one-shot exports over the whole
+ * text. Invalid sequences are treated as U+FFFD, like `TextDecoder`.
+ * `TextStats` needs the `text` feature and `StreamingHasher` the `hash` feature.
+ */
+
+#[cfg(any(feature = "text", feature = "hash"))]
+use wasm_bindgen::prelude::*;
+
+#[cfg(any(feature = "text", feature = "hash"))]
+use crate::error::clear_last_error;
+
+/// Incremental UTF-8 decoder that carries split sequences across chunks
+#[derive(Default, Clone, Debug)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode a chunk, calling `f` for every complete character
+    pub fn feed(&mut self, chunk: &[u8], mut f: impl FnMut(char)) {
+        let mut rest = chunk;
+
+        if !self.pending.is_empty() {
+            // Complete the character left over from the previous chunk
+            let take = (4 - self.pending.len()).min(rest.len());
+            let mut head = std::mem::take(&mut self.pending);
+            let carried = head.len();
+            head.extend_from_slice(&rest[..take]);
+
+            let (ch, len) = match std::str::from_utf8(&head) {
+                Ok(s) => first_char(s),
+                Err(err) if err.valid_up_to() > 0 => {
+                    first_char(std::str::from_utf8(&head[..err.valid_up_to()]).unwrap_or_default())
+                }
+                Err(err) => match err.error_len() {
+                    Some(len) => (char::REPLACEMENT_CHARACTER, len),
+                    None => {
+                        // Chunk too short to complete the sequence
+                        self.pending = head;
+                        return;
+                    }
+                },
+            };
+
+            f(ch);
+            rest = &rest[len.saturating_sub(carried)..];
+        }
+
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(s) => {
+                    s.chars().for_each(&mut f);
+                    return;
+                }
+                Err(err) => {
+                    let (valid, after) = rest.split_at(err.valid_up_to());
+                    std::str::from_utf8(valid).unwrap_or_default().chars().for_each(&mut f);
+
+                    match err.error_len() {
+                        Some(len) => {
+                            f(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                        None => {
+                            self.pending.extend_from_slice(after);
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Flush a trailing incomplete sequence as U+FFFD
+    pub fn finish(&mut self, mut f: impl FnMut(char)) {
+        if !self.pending.is_empty() {
+            self.pending.clear();
+            f(char::REPLACEMENT_CHARACTER);
+        }
+    }
+}
+
+fn first_char(s: &str) -> (char, usize) {
+    s.chars()
+        .next()
+        .map_or((char::REPLACEMENT_CHARACTER, 1), |c| (c, c.len_utf8()))
+}
+
+/// Final counts produced by `TextStats::finish` (each wraps at `u32::MAX`)
+#[cfg(feature = "text")]
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextSummary {
+    /// Input size in bytes
+    pub bytes: u32,
+    /// Number of characters
+    pub chars: u32,
+    /// Same as `count_vowels`
+    pub vowels: u32,
+    /// Same as `word_count`
+    pub words: u32,
+    /// Same as `longest_word_length`
+    pub longest_word: u32,
+}
+
+/// Incremental `count_vowels` / `word_count` / `longest_word_length`
+#[cfg(feature = "text")]
+#[wasm_bindgen]
+#[derive(Default, Clone, Debug)]
+pub struct TextStats {
+    decoder: Utf8Decoder,
+    summary: TextSummary,
+    current_word: u32,
+}
+
+#[cfg(feature = "text")]
+#[wasm_bindgen]
+impl TextStats {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> TextStats {
+        TextStats::default()
+    }
+
+    /// Feed a chunk of UTF-8 bytes
+    pub fn update(&mut self, chunk: &[u8]) {
+        clear_last_error();
+        self.summary.bytes = self.summary.bytes.wrapping_add(chunk.len() as u32);
+        let mut decoder = std::mem::take(&mut self.decoder);
+        decoder.feed(chunk, |c| self.push_char(c));
+        self.decoder = decoder;
+    }
+
+    /// Feed a chunk that is already a string
+    pub fn update_str(&mut self, chunk: &str) {
+        self.update(chunk.as_bytes());
+    }
+
+    /// Return the counts and reset for the next stream
+    pub fn finish(&mut self) -> TextSummary {
+        clear_last_error();
+        let mut decoder = std::mem::take(&mut self.decoder);
+        decoder.finish(|c| self.push_char(c));
+        self.end_word();
+
+        let summary = self.summary;
+        *self = TextStats::default();
+        summary
+    }
+
+    fn push_char(&mut self, c: char) {
+        self.summary.chars = self.summary.chars.wrapping_add(1);
+        if matches!(c.to_ascii_lowercase(), 'a' | 'e' | 'i' | 'o' | 'u') {
+            self.summary.vowels = self.summary.vowels.wrapping_add(1);
+        }
+
+        if c.is_whitespace() {
+            self.end_word();
+        } else {
+            if self.current_word == 0 {
+                self.summary.words = self.summary.words.wrapping_add(1);
+            }
+            self.current_word = self.current_word.wrapping_add(c.len_utf8() as u32);
+        }
+    }
+
+    fn end_word(&mut self) {
+        self.summary.longest_word = self.summary.longest_word.max(self.current_word);
+        self.current_word = 0;
+    }
+}
+
+/// Incremental `hash_string` (DJB2 over raw bytes)
+#[cfg(feature = "hash")]
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct StreamingHasher {
+    hash: u32,
+}
+
+#[cfg(feature = "hash")]
+impl Default for StreamingHasher {
+    fn default() -> Self {
+        Self { hash: crate::hash::DJB2_SEED }
+    }
+}
+
+#[cfg(feature = "hash")]
+#[wasm_bindgen]
+impl StreamingHasher {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> StreamingHasher {
+        StreamingHasher::default()
+    }
+
+    /// Feed a chunk of bytes
+    pub fn update(&mut self, chunk: &[u8]) {
+        clear_last_error();
+        self.hash = crate::hash::djb2_update(self.hash, chunk);
+    }
+
+    /// Feed a chunk that is already a string
+    pub fn update_str(&mut self, chunk: &str) {
+        self.update(chunk.as_bytes());
+    }
+
+    /// Return the hash and reset for the next stream
+    pub fn finish(&mut self) -> u32 {
+        clear_last_error();
+        std::mem::take(self).hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1-, 2-, 3- and 4-byte chars, ASCII and Unicode whitespace
+    const TEXT: &str = "héllo wörld\u{3000}日本語 ok😀 Ωmega\u{a0}end a";
+
+    fn decode_chunks(chunks: &[&[u8]]) -> String {
+        let mut decoder = Utf8Decoder::new();
+        let mut out = String::new();
+        for chunk in chunks {
+            decoder.feed(chunk, |c| out.push(c));
+        }
+        decoder.finish(|c| out.push(c));
+        out
+    }
+
+    /// Every way to cut `bytes` in two, and byte by byte
+    fn splits(bytes: &[u8]) -> Vec<Vec<&[u8]>> {
+        let mut out: Vec<Vec<&[u8]>> =
+            (0..=bytes.len()).map(|at| vec![&bytes[..at], &bytes[at..]]).collect();
+        out.push(bytes.chunks(1).collect());
+        // Cuts around every char, with an empty chunk in the middle
+        for at in 0..bytes.len() {
+            out.push(vec![&bytes[..at], &[], &bytes[at..at + 1], &bytes[at + 1..]]);
+        }
+        out
+    }
+
+    fn inputs() -> Vec<Vec<u8>> {
+        let mut inputs = vec![TEXT.as_bytes().to_vec(), b"".to_vec()];
+        for tail in [
+            &b"\xc3"[..],         // truncated 2-byte
+            b"\xe6\x97",         // truncated 3-byte
+            b"\xf0\x9f\x98",     // truncated 4-byte
+            b"\xff",             // never valid
+            b"\x80a",            // stray continuation
+            b"\xe6\x97a e",       // truncated 3-byte before ASCII
+            b"\xf0\x9f\x98\xf0\x9f\x98\x80", // truncated then complete
+            b"\xed\xa0\x80",     // surrogate
+            b"\xc0\xaf",         // overlong
+        ] {
+            inputs.push([TEXT.as_bytes(), tail].concat());
+            inputs.push([tail, TEXT.as_bytes()].concat());
+        }
+        inputs
+    }
+
+    #[test]
+    fn decoder_matches_lossy_decoding_at_every_split() {
+        for input in inputs() {
+            let expected = String::from_utf8_lossy(&input);
+            for chunks in splits(&input) {
+                assert_eq!(decode_chunks(&chunks), expected, "{chunks:?}");
+            }
+        }
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn text_stats_match_one_shot_at_every_split() {
+        for input in inputs() {
+            let text = String::from_utf8_lossy(&input);
+            let expected = TextSummary {
+                bytes: input.len() as u32,
+                chars: text.chars().count() as u32,
+                vowels: crate::count_vowels(&text) as u32,
+                words: crate::word_count(&text) as u32,
+                longest_word: crate::longest_word_length(&text) as u32,
+            };
+            let mut stats = TextStats::new();
+            for chunks in splits(&input) {
+                for chunk in chunks {
+                    stats.update(chunk);
+                }
+                assert_eq!(stats.finish(), expected, "{input:?}");
+            }
+        }
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    fn hasher_matches_one_shot_at_every_split() {
+        let mut hasher = StreamingHasher::new();
+        for chunks in splits(TEXT.as_bytes()) {
+            for chunk in chunks {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finish(), crate::hash_string(TEXT));
+        }
+        // Raw bytes, including invalid UTF-8, hash as they are
+        for input in inputs() {
+            hasher.update(&input);
+            assert_eq!(hasher.finish(), crate::hash::djb2_update(crate::hash::DJB2_SEED, &input));
+        }
+    }
+
+    #[cfg(feature = "text")]
+    #[test]
+    fn counters_wrap_instead_of_overflowing() {
+        let mut stats = TextStats::new();
+        let max = u32::MAX;
+        stats.summary = TextSummary { bytes: max, chars: max, vowels: max, words: max, longest_word: 0 };
+        stats.update(b"a");
+        let summary = stats.finish();
+        assert_eq!((summary.bytes, summary.chars, summary.vowels, summary.words), (0, 0, 0, 0));
+        assert_eq!(summary.longest_word, 1);
+    }
+}
@@ -0,0 +1,68 @@
+/*!
+ * Text functions (`text` feature)
+ *
+ * The scanning functions run on `crate::kernels` (SIMD in `simd128` builds).
+ */
+
+#[cfg(not(component_core))]
+use wasm_bindgen::prelude::*;
+
+use crate::error::clear_last_error;
+
+/// Count vowels in a string
+#[cfg_attr(not(component_core), wasm_bindgen)]
+pub fn count_vowels(s: &str) -> usize {
+    clear_last_error();
+    crate::kernels::count_vowels(s)
+}
+
+/// Reverse a string
+#[cfg_attr(not(component_core), wasm_bindgen)]
+pub fn reverse_string(s: &str) -> String {
+    clear_last_error();
+    s.chars().rev().collect()
+}
+
+/// Check if string is palindrome
+#[cfg_attr(not(component_core), wasm_bindgen)]
+pub fn is_palindrome(s: &str) -> bool {
+    clear_last_error();
+    crate::kernels::is_palindrome(s)
+}
+
+/// Find longest word in a string
+#[cfg_attr(not(component_core), wasm_bindgen)]
+pub fn longest_word_length(s: &str) -> usize {
+    clear_last_error();
+    s.split_whitespace()
+        .map(|word| word.len())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Count word occurrences
+#[cfg_attr(not(component_core), wasm_bindgen)]
+pub fn word_count(s: &str) -> usize {
+    clear_last_error();
+    crate::kernels::word_count(s)
+}
+
+/// Occurrences of each word (trimmed of surrounding punctuation) at least
+/// `min_length` characters long, most frequent first, then alphabetical
+///
+/// Takes options, so it is only reachable through `call_encoded`.
+pub fn word_frequencies(s: &str, min_length: usize, case_sensitive: bool) -> Vec<(String, usize)> {
+    let mut counts = std::collections::HashMap::new();
+    for word in s.split_whitespace() {
+        let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+        if word.is_empty() || word.chars().count() < min_length {
+            continue;
+        }
+        let word = if case_sensitive { word.to_string() } else { word.to_lowercase() };
+        *counts.entry(word).or_insert(0) += 1;
+    }
+
+    let mut frequencies: Vec<_> = counts.into_iter().collect();
+    frequencies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    frequencies
+}
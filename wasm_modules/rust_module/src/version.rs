@@ -0,0 +1,103 @@
+/*!
+ * ABI version and capability negotiation
+ *
+ * Hosts that persist module output (KV entries keyed by `hash_string`,
+ * snapshots) or call a fixed set of exports check a build before using it:
+ *
+ * - `abi_version()` identifies the contract of the existing exports. It is
+ *   bumped when an export is removed or renamed, its signature or the C ABI
+ *   memory protocol changes, or it returns a different result for the same
+ *   input (e.g. `hash_string` switching algorithms). Adding exports, features
+ *   or snapshot sections does not bump it.
+ * - `supports(name)` reports what this build offers: an enabled cargo
+ *   feature (`"hash"`, `"snapshot"`) or a versioned contract
+ *   (`"hash.djb2"`, `"snapshot.v1"`) that a host relying on one specific
+ *   behaviour can require across ABI versions.
+ *
+ * `NativeWASMRegistry.register(alias, specifier, { compatibility })` applies
+ * both checks and refuses (or warns about) builds outside the host's policy.
+ * The ABI version is also recorded in the `echelon.manifest` section.
+ */
+
+use wasm_bindgen::prelude::*;
+
+/// Contract revision of the existing exports (see the module docs for when it changes)
+pub const ABI_VERSION: u32 = 1;
+
+/// Cargo features enabled in this build
+const FEATURES: &[&str] = &include!(concat!(env!("OUT_DIR"), "/echelon_features.rs"));
+
+/// Versioned behaviours of this build, as `<area>.<revision>`
+const CONTRACTS: &[&str] = &[
+    // Packed `(ptr << 32) | len` results and `echelon_last_error_*`
+    "c-abi.v1",
+    "entropy.splitmix64",
+    #[cfg(feature = "hash")]
+    "hash.djb2",
+    #[cfg(feature = "snapshot")]
+    "snapshot.v1",
+    #[cfg(feature = "rpc")]
+    "rpc.jsonrpc2",
+    #[cfg(feature = "http")]
+    "http.v1",
+    #[cfg(feature = "codec")]
+    "codec.v1",
+    #[cfg(feature = "shared-buffers")]
+    "shared-buffers.v1",
+    // `env.kv_*` imports completed through `kv_complete`
+    #[cfg(feature = "host-kv")]
+    "kv.v1",
+];
+
+/// ABI contract version of this build
+#[wasm_bindgen]
+pub fn abi_version() -> u32 {
+    ABI_VERSION
+}
+
+/// Whether this build enables the cargo feature or contract `feature_name`
+#[wasm_bindgen]
+pub fn supports(feature_name: &str) -> bool {
+    FEATURES.contains(&feature_name) || CONTRACTS.contains(&feature_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supports_known_names() {
+        assert!(supports("c-abi.v1"));
+        assert!(supports("entropy.splitmix64"));
+    }
+
+    #[test]
+    fn supports_rejects_unknown_names() {
+        for name in ["", "nope", "hash.fnv1a", "c-abi.v2", "C-ABI.V1", "c-abi", " hash"] {
+            assert!(!supports(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn supports_follows_features() {
+        assert_eq!(supports("hash"), cfg!(feature = "hash"));
+        assert_eq!(supports("hash.djb2"), cfg!(feature = "hash"));
+        assert_eq!(supports("snapshot"), cfg!(feature = "snapshot"));
+        assert_eq!(supports("snapshot.v1"), cfg!(feature = "snapshot"));
+        assert_eq!(supports("host-kv"), cfg!(feature = "host-kv"));
+        assert_eq!(supports("kv.v1"), cfg!(feature = "host-kv"));
+        assert_eq!(supports("shared-buffers"), cfg!(feature = "shared-buffers"));
+        // `default` is not a feature of the build
+        assert!(!supports("default"));
+    }
+
+    #[test]
+    fn queries_leave_the_error_slot() {
+        crate::error::set_last_error(crate::error::EchelonError::overflow("add"));
+        assert_eq!(abi_version(), ABI_VERSION);
+        supports("hash");
+        crate::manifest::manifest();
+        assert_eq!(crate::error::last_error_code(), crate::error::ErrorCode::Overflow as u32);
+        crate::error::clear_last_error();
+    }
+}
@@ -0,0 +1,84 @@
+//! The generated `string_utils.d.ts` against the manifest of the same build
+//!
+//! Both come out of `build/main.rs`; this checks that every export is
+//! declared once, under the right type, with the manifest's arity.
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+const DTS: &str = include_str!(concat!(env!("OUT_DIR"), "/string_utils.d.ts"));
+
+/// Declared members of one `export type X = { ... };` or `export declare class X { ... }`
+fn block(header: &str) -> BTreeMap<String, usize> {
+    let start = DTS.find(header).unwrap_or_else(|| panic!("no `{header}` in string_utils.d.ts"));
+    let body = &DTS[start + header.len()..];
+    let body = &body[..body.find("\n}").unwrap()];
+
+    let mut members = BTreeMap::new();
+    for line in body.lines().map(str::trim) {
+        if line.starts_with("/**") || line.is_empty() {
+            continue;
+        }
+        let Some((name, rest)) = line.split_once('(') else {
+            continue; // `memory: ...`, fields, `Name: typeof Name`
+        };
+        let params = &rest[..rest.find(')').unwrap()];
+        let arity = if params.is_empty() { 0 } else { params.split(", ").count() };
+        assert!(members.insert(name.to_string(), arity).is_none(), "{header}: `{name}` declared twice");
+    }
+    members
+}
+
+fn manifest_exports() -> Vec<Value> {
+    let manifest: Value = serde_json::from_str(echelon_wasm::manifest::MANIFEST_JSON).unwrap();
+    manifest["exports"].as_array().unwrap().clone()
+}
+
+fn arity(export: &Value) -> usize {
+    export["params"].as_array().unwrap().len()
+}
+
+#[test]
+fn raw_exports_match_manifest() {
+    let declared = block("export type StringUtilsExports = {");
+    let exports = manifest_exports();
+
+    // Every C ABI export is callable without glue
+    for export in exports.iter().filter(|e| e["abi"] == "c") {
+        let name = export["name"].as_str().unwrap();
+        assert_eq!(declared.get(name), Some(&arity(export)), "{name}");
+    }
+    for (name, &count) in &declared {
+        let export = exports.iter().find(|e| e["name"] == name.as_str() && e["class"].is_null());
+        assert_eq!(export.map(arity), Some(count), "{name}: declared but not in the manifest");
+    }
+}
+
+#[test]
+fn bindings_match_manifest() {
+    let declared = block("export type StringUtilsBindings = {");
+    let expected: BTreeMap<String, usize> = manifest_exports()
+        .iter()
+        .filter(|e| e["abi"] == "wasm-bindgen" && e["class"].is_null())
+        .map(|e| (e["name"].as_str().unwrap().to_string(), arity(e)))
+        .collect();
+    assert_eq!(declared, expected);
+}
+
+#[test]
+fn classes_match_manifest() {
+    let exports = manifest_exports();
+    let mut classes: BTreeMap<&str, BTreeMap<String, usize>> = BTreeMap::new();
+    for export in exports.iter().filter(|e| e["class"].is_string()) {
+        let class = export["class"].as_str().unwrap();
+        let method = export["name"].as_str().unwrap().strip_prefix(&format!("{}_", class.to_lowercase())).unwrap();
+        let member = if method == "new" { "constructor" } else { method };
+        classes.entry(class).or_default().insert(member.to_string(), arity(export));
+    }
+
+    for (class, mut expected) in classes {
+        expected.insert("free".to_string(), 0);
+        assert_eq!(block(&format!("export declare class {class} {{")), expected, "{class}");
+    }
+}
@@ -0,0 +1,81 @@
+//! Allocator counters across alloc / realloc / free
+//!
+//! The counters are process-wide, so these checks run one after another on
+//! the main thread with nothing else allocating in between.
+
+use std::alloc::{alloc, alloc_zeroed, dealloc, realloc, Layout};
+
+use echelon_wasm::heap::{heap_stats, reset_heap_peak, HeapStats};
+
+const SIZE: usize = 1 << 20;
+
+fn alloc_free_pair() {
+    reset_heap_peak();
+    let before = heap_stats();
+    assert_eq!(before.peak_bytes, before.live_bytes);
+
+    let layout = Layout::from_size_align(SIZE, 8).unwrap();
+    let ptr = unsafe { alloc(layout) };
+    assert!(!ptr.is_null());
+    let held = heap_stats();
+    assert_eq!(held.live_bytes, before.live_bytes + SIZE);
+    assert_eq!(held.peak_bytes, held.live_bytes);
+    assert_eq!(held.allocations, before.allocations + 1);
+    assert_eq!(held.deallocations, before.deallocations);
+    // Outside wasm32 the heap is the peak, all of it live right now
+    assert_eq!(held.heap_size, held.peak_bytes);
+    assert_eq!(held.fragmentation, 0.0);
+
+    unsafe { dealloc(ptr, layout) };
+    let after = heap_stats();
+    assert_eq!(after.live_bytes, before.live_bytes);
+    assert_eq!(after.peak_bytes, held.peak_bytes, "peak survives the free");
+    assert_eq!(after.allocations, held.allocations);
+    assert_eq!(after.deallocations, before.deallocations + 1);
+    let expected = SIZE as f64 / after.heap_size as f64;
+    assert!((after.fragmentation - expected).abs() < 1e-9, "{} != {expected}", after.fragmentation);
+
+    reset_heap_peak();
+    let reset = heap_stats();
+    assert_eq!((reset.peak_bytes, reset.fragmentation), (reset.live_bytes, 0.0));
+}
+
+fn realloc_moves_live_bytes() {
+    let before = heap_stats();
+    let layout = Layout::from_size_align(SIZE, 8).unwrap();
+    unsafe {
+        let ptr = alloc_zeroed(layout);
+        let grown = realloc(ptr, layout, 2 * SIZE);
+        assert!(!grown.is_null());
+        let stats = heap_stats();
+        assert_eq!(stats.live_bytes, before.live_bytes + 2 * SIZE);
+        assert_eq!((stats.allocations, stats.reallocations), (before.allocations + 1, before.reallocations + 1));
+
+        let grown_layout = Layout::from_size_align(2 * SIZE, 8).unwrap();
+        let shrunk = realloc(grown, grown_layout, SIZE / 2);
+        assert!(!shrunk.is_null());
+        assert_eq!(heap_stats().live_bytes, before.live_bytes + SIZE / 2);
+        assert!(heap_stats().peak_bytes >= before.live_bytes + 2 * SIZE);
+
+        dealloc(shrunk, Layout::from_size_align(SIZE / 2, 8).unwrap());
+    }
+    let after = heap_stats();
+    assert_eq!(after.live_bytes, before.live_bytes);
+    assert_eq!(after.deallocations, before.deallocations + 1);
+    assert_eq!(after.reallocations, before.reallocations + 2);
+}
+
+fn failed_allocations_are_not_counted() {
+    let before: HeapStats = heap_stats();
+    let mut huge = Vec::<u8>::new();
+    assert!(huge.try_reserve_exact(isize::MAX as usize / 2).is_err());
+    let after = heap_stats();
+    assert_eq!((after.live_bytes, after.allocations), (before.live_bytes, before.allocations));
+}
+
+fn main() {
+    alloc_free_pair();
+    realloc_moves_live_bytes();
+    failed_allocations_are_not_counted();
+    println!("heap: ok");
+}
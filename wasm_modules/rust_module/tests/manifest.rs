@@ -0,0 +1,147 @@
+//! The generated manifest against a real wasm32 build
+//!
+//! Builds the module for `wasm32-unknown-unknown` with the features of this
+//! build, then checks the embedded `echelon.manifest` section against the
+//! module's export section. Skipped when the wasm32 target is not installed.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde_json::Value;
+
+const TARGET: &str = "wasm32-unknown-unknown";
+
+/// Names the Rust toolchain and wasm-bindgen add on top of the crate's exports
+fn is_glue(name: &str) -> bool {
+    name == "memory" || name.starts_with("__")
+}
+
+/// Export name without the `_<crate hash>` suffix wasm-bindgen adds before its CLI runs
+fn unhashed(name: &str) -> &str {
+    match name.rsplit_once('_') {
+        Some((base, hash)) if hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()) => base,
+        _ => name,
+    }
+}
+
+/// Build the wasm module, returns its path (`None` without the wasm32 target)
+fn build_wasm() -> Option<PathBuf> {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    let sysroot = Command::new(rustc).args(["--print", "sysroot"]).output().ok()?;
+    let sysroot = PathBuf::from(String::from_utf8(sysroot.stdout).ok()?.trim());
+    if !sysroot.join("lib/rustlib").join(TARGET).exists() {
+        eprintln!("skipped: {TARGET} is not installed");
+        return None;
+    }
+
+    let manifest: Value = serde_json::from_str(echelon_wasm::manifest::MANIFEST_JSON).unwrap();
+    // `component` turns a wasm build into the component core, which has no manifest section
+    let features: Vec<&str> =
+        manifest["features"].as_array().unwrap().iter().filter_map(Value::as_str).filter(|f| *f != "component").collect();
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("manifest");
+    let output = Command::new(env!("CARGO"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["build", "--lib", "--target", TARGET, "--no-default-features", "--features"])
+        .arg(features.join(","))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .output()
+        .expect("cargo build");
+    assert!(output.status.success(), "wasm32 build failed:\n{}", String::from_utf8_lossy(&output.stderr));
+    Some(target_dir.join(TARGET).join("debug/echelon_wasm.wasm"))
+}
+
+fn leb128(bytes: &[u8], pos: &mut usize) -> u32 {
+    let (mut value, mut shift) = (0u32, 0);
+    loop {
+        let byte = bytes[*pos];
+        *pos += 1;
+        value |= u32::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+fn name<'a>(bytes: &'a [u8], pos: &mut usize) -> &'a str {
+    let len = leb128(bytes, pos) as usize;
+    let name = std::str::from_utf8(&bytes[*pos..*pos + len]).unwrap();
+    *pos += len;
+    name
+}
+
+/// Exported function names and custom sections of a wasm binary
+fn read_wasm(bytes: &[u8]) -> (BTreeSet<String>, Vec<(String, Vec<u8>)>) {
+    assert_eq!(&bytes[..8], b"\0asm\x01\0\0\0", "not a wasm module");
+    let (mut functions, mut custom) = (BTreeSet::new(), Vec::new());
+    let mut pos = 8;
+    while pos < bytes.len() {
+        let id = bytes[pos];
+        pos += 1;
+        let size = leb128(bytes, &mut pos) as usize;
+        let end = pos + size;
+        let mut at = pos;
+        match id {
+            0 => {
+                let section = name(bytes, &mut at).to_string();
+                custom.push((section, bytes[at..end].to_vec()));
+            }
+            7 => {
+                for _ in 0..leb128(bytes, &mut at) {
+                    let export = name(bytes, &mut at).to_string();
+                    let kind = bytes[at];
+                    at += 1;
+                    leb128(bytes, &mut at);
+                    if kind == 0 {
+                        functions.insert(export);
+                    }
+                }
+            }
+            _ => {}
+        }
+        pos = end;
+    }
+    (functions, custom)
+}
+
+#[test]
+fn manifest_matches_wasm_exports() {
+    let Some(path) = build_wasm() else { return };
+    let bytes = std::fs::read(&path).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+    let (functions, custom) = read_wasm(&bytes);
+
+    let section = custom
+        .iter()
+        .find(|(name, _)| name == echelon_wasm::manifest::MANIFEST_SECTION)
+        .map(|(_, data)| data.as_slice())
+        .expect("echelon.manifest section");
+    let manifest: Value = serde_json::from_slice(section).unwrap();
+    let exports = manifest["exports"].as_array().unwrap();
+
+    let declared: BTreeSet<String> = exports.iter().map(|e| e["name"].as_str().unwrap().to_string()).collect();
+    let actual: BTreeSet<String> =
+        functions.iter().map(|name| unhashed(name)).filter(|name| !is_glue(name)).map(String::from).collect();
+    assert_eq!(
+        declared.difference(&actual).collect::<Vec<_>>(),
+        Vec::<&String>::new(),
+        "in the manifest but not exported"
+    );
+    assert_eq!(
+        actual.difference(&declared).collect::<Vec<_>>(),
+        Vec::<&String>::new(),
+        "exported but missing from the manifest"
+    );
+
+    // A C ABI wrapper is exactly as pure as the export it wraps
+    let pure = |name: &str| exports.iter().find(|e| e["name"] == name).map(|e| e["pure"].as_bool().unwrap());
+    for export in exports {
+        let name = export["name"].as_str().unwrap();
+        if let Some(wrapped) = name.strip_prefix("echelon_").and_then(pure) {
+            assert_eq!(export["pure"].as_bool(), Some(wrapped), "{name}: purity differs from the wrapped export");
+        }
+    }
+}
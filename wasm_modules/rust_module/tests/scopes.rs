@@ -0,0 +1,153 @@
+//! Request scopes against module state that outlives them
+//!
+//! Every allocation of the process goes to the arena while a scope is open,
+//! so these checks run one after another on the main thread.
+
+use echelon_wasm::arena::{begin_scope, end_scope, persistent};
+
+/// Fill a scoped allocation so reused arena memory is overwritten
+fn scribble(len: usize) -> Vec<u8> {
+    vec![0xaa; len]
+}
+
+fn persistent_outlives_scope() {
+    begin_scope();
+    let kept = persistent(|| b"racecar".repeat(64));
+    let scoped = scribble(kept.len());
+    drop(scoped);
+    let released = end_scope();
+    assert!(released >= kept.len(), "scoped bytes released");
+
+    begin_scope();
+    let reused = scribble(4096);
+    assert_eq!(kept, b"racecar".repeat(64), "persistent block overwritten by the next scope");
+    drop(reused);
+    end_scope();
+}
+
+fn persistent_moves_grown_blocks_out() {
+    begin_scope();
+    let mut kept = vec![1u8; 16];
+    persistent(|| kept.extend_from_slice(&[2; 4096]));
+    end_scope();
+
+    begin_scope();
+    let reused = scribble(8192);
+    assert!(kept[..16].iter().all(|&b| b == 1) && kept[16..].iter().all(|&b| b == 2));
+    drop(reused);
+    end_scope();
+}
+
+/// Pending until `open`, then ready
+#[cfg(feature = "host-kv")]
+mod gate {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+    use std::task::{Context, Poll, Waker};
+
+    static OPEN: AtomicBool = AtomicBool::new(false);
+    static WAKER: Mutex<Option<Waker>> = Mutex::new(None);
+
+    pub struct Gate;
+
+    impl Future for Gate {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if OPEN.load(Ordering::Relaxed) {
+                return Poll::Ready(());
+            }
+            *WAKER.lock().unwrap() = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    pub fn open() {
+        OPEN.store(true, Ordering::Relaxed);
+        if let Some(waker) = WAKER.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(feature = "host-kv")]
+fn kv_task_outlives_scope() {
+    use echelon_wasm::kv;
+
+    begin_scope();
+    let task = kv::spawn(async {
+        let text = "index ".repeat(100);
+        gate::Gate.await;
+        Ok(text.into_bytes())
+    });
+    assert_eq!(kv::task_state(task), 1);
+    end_scope();
+
+    begin_scope();
+    let reused = scribble(16 * 1024);
+    gate::open();
+    kv::run_ready();
+    assert_eq!(kv::take_task(task).unwrap(), "index ".repeat(100).into_bytes());
+    drop(reused);
+    end_scope();
+}
+
+#[cfg(feature = "shared-buffers")]
+fn shared_region_outlives_scope() {
+    use echelon_wasm::heap::heap_stats;
+    use echelon_wasm::shared::{input_buffer, release_shared_buffers};
+
+    release_shared_buffers();
+    begin_scope();
+    let live = heap_stats().live_bytes;
+    let ptr = input_buffer(64) as *mut u8;
+    assert_eq!(heap_stats().live_bytes, live, "region counted by heap_stats");
+    // SAFETY: `input_buffer` returned a region of at least 64 bytes
+    let region = unsafe { std::slice::from_raw_parts_mut(ptr, 64) };
+    region[..7].copy_from_slice(b"racecar");
+    end_scope();
+
+    begin_scope();
+    let reused = scribble(4096);
+    assert_eq!(input_buffer(64) as *mut u8, ptr);
+    assert_eq!(&region[..7], b"racecar", "input region overwritten by the next scope");
+    drop(reused);
+    end_scope();
+    release_shared_buffers();
+}
+
+#[cfg(feature = "http")]
+fn router_outlives_scope() {
+    use echelon_wasm::http::{handle, Request};
+
+    let status = |method: &str, url: &str, body: &[u8]| {
+        let request = Request { method: method.into(), url: url.into(), headers: Vec::new(), body: body.to_vec() };
+        let response = handle(&request.encode()).expect("well-formed request");
+        u32::from_le_bytes(response[..4].try_into().unwrap())
+    };
+
+    // The router is built by the first request, inside a scope
+    for _ in 0..3 {
+        begin_scope();
+        assert_eq!(status("GET", "/health", b""), 200);
+        #[cfg(feature = "hash")]
+        assert_eq!(status("POST", "/hash", b"hello"), 200);
+        assert_eq!(status("GET", "/missing", b""), 404);
+        drop(scribble(64 * 1024));
+        end_scope();
+    }
+}
+
+fn main() {
+    persistent_outlives_scope();
+    #[cfg(feature = "http")]
+    router_outlives_scope();
+    persistent_moves_grown_blocks_out();
+    #[cfg(feature = "shared-buffers")]
+    shared_region_outlives_scope();
+    #[cfg(feature = "host-kv")]
+    kv_task_outlives_scope();
+    println!("scopes: ok");
+}
@@ -0,0 +1,11 @@
+#!/usr/bin/env node
+// Cargo runner for wasm32-wasip1 test binaries (no wasmtime needed):
+//   CARGO_TARGET_WASM32_WASIP1_RUNNER=tests/wasi_run.mjs cargo test --target wasm32-wasip1 --lib
+import { readFile } from 'node:fs/promises';
+import { argv, env, exit } from 'node:process';
+import { WASI } from 'node:wasi';
+
+const [module, ...args] = argv.slice(2);
+const wasi = new WASI({ version: 'preview1', args: [module, ...args], env, returnOnExit: true });
+const { instance } = await WebAssembly.instantiate(await readFile(module), wasi.getImportObject());
+exit(wasi.start(instance));